@@ -81,50 +81,56 @@ impl Visualizable {
         let (x_axis_index, y_axis_index) = (self.x_axis_index, self.y_axis_index);
         let inputs = self.input_values.clone();
 
+        let evaluate_hcas = match self.viewee {
+            VisualizableKey::HCasCartesianInterpolated => opencas::hcas::evaluate_interpolated,
+            _ => opencas::hcas::evaluate,
+        };
+
         match self.viewee {
-            VisualizableKey::HCasCartesian => Box::new(move |x, y| {
-                let mut cas = opencas::HCas {
-                    last_advisory: (last_adv as u8).try_into().unwrap(),
-                };
-
-                let get_value = |index: usize| {
-                    if index == x_axis_index {
-                        x
-                    } else if index == y_axis_index {
-                        y
-                    } else {
-                        inputs[index]
-                    }
-                };
-
-                let tau = Time::new::<second>(get_value(0));
-                let forward = Length::new::<foot>(get_value(1));
-                let left = Length::new::<foot>(get_value(2));
-                let psi = Angle::new::<radian>(get_value(3));
-                cas.process_cartesian(tau, forward, left, psi).0 as usize
-            }),
-            VisualizableKey::VCas => Box::new(move |x, y| {
-                let mut cas = opencas::VCas {
-                    last_advisory: (last_adv as u8).try_into().unwrap(),
-                };
-
-                let get_value = |index: usize| {
-                    if index == x_axis_index {
-                        x
-                    } else if index == y_axis_index {
-                        y
-                    } else {
-                        inputs[index]
-                    }
-                };
+            VisualizableKey::HCasCartesian | VisualizableKey::HCasCartesianInterpolated => {
+                let pra = (last_adv as u8).try_into().unwrap();
+
+                Box::new(move |x, y| {
+                    let get_value = |index: usize| {
+                        if index == x_axis_index {
+                            x
+                        } else if index == y_axis_index {
+                            y
+                        } else {
+                            inputs[index]
+                        }
+                    };
+
+                    let tau = Time::new::<second>(get_value(0));
+                    let forward = Length::new::<foot>(get_value(1));
+                    let left = Length::new::<foot>(get_value(2));
+                    let psi = Angle::new::<radian>(get_value(3));
+                    evaluate_hcas(pra, tau, forward, left, psi).advisory() as usize
+                })
+            }
+            VisualizableKey::VCas => {
+                let pra = (last_adv as u8).try_into().unwrap();
+
+                Box::new(move |x, y| {
+                    let get_value = |index: usize| {
+                        if index == x_axis_index {
+                            x
+                        } else if index == y_axis_index {
+                            y
+                        } else {
+                            inputs[index]
+                        }
+                    };
 
-                let tau = Time::new::<second>(get_value(0));
-                let delta_altitude = Length::new::<foot>(get_value(1));
-                let own_roc = Velocity::new::<foot_per_second>(get_value(2));
-                let intruder_roc = Velocity::new::<foot_per_second>(get_value(3));
+                    let tau = Time::new::<second>(get_value(0));
+                    let delta_altitude = Length::new::<foot>(get_value(1));
+                    let own_roc = Velocity::new::<foot_per_second>(get_value(2));
+                    let intruder_roc = Velocity::new::<foot_per_second>(get_value(3));
 
-                cas.process(delta_altitude, own_roc, intruder_roc, tau).0 as usize
-            }),
+                    opencas::vcas::evaluate(pra, delta_altitude, own_roc, intruder_roc, tau)
+                        .advisory() as usize
+                })
+            }
         }
     }
 
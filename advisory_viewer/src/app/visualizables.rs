@@ -18,6 +18,8 @@ use super::*;
 pub enum VisualizableKey {
     #[strum(serialize = "Horizontal CAS Cartesian")]
     HCasCartesian,
+    #[strum(serialize = "Horizontal CAS Cartesian (τ interpolated)")]
+    HCasCartesianInterpolated,
     #[strum(serialize = "Vertical CAS")]
     VCas,
 }
@@ -90,6 +92,10 @@ impl From<VisualizableKey> for Visualizable {
                 #[cfg(not(target_arch = "wasm32"))]
                 max_level: 10,
             },
+            VisualizableKey::HCasCartesianInterpolated => Visualizable {
+                viewee: v,
+                ..VisualizableKey::HCasCartesian.into()
+            },
             VisualizableKey::VCas => {
                 let speed_range = -1e2..=1e2;
                 Visualizable {
@@ -15,9 +15,7 @@ pub struct Status {
 
 pub struct VisualizerBackend {
     pub conf: ArcSwap<Option<ViewerConfig>>,
-    #[cfg_attr(feature = "persistence", serde(skip))]
     data: Arc<RwLock<(VisualizerNode, Uuid)>>,
-    #[cfg_attr(feature = "persistence", serde(skip))]
     quad_counter: Arc<RelaxedCounter>,
     level_done: Arc<ArcSwap<usize>>,
 }
@@ -70,7 +70,8 @@
           src = ./.;
           doCheck = true;
           cargoBuildOptions = x: x ++ [ "-p" name ];
-          cargoTestOptions = x: x ++ [ "-p" name ];
+          # the regression scenarios are only run with the scenario feature
+          cargoTestOptions = x: x ++ [ "-p" name "--features" "scenario" ];
           doDoc = true;
           copyBins = false;
           doDocFail = true;
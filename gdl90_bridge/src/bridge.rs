@@ -0,0 +1,342 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use opencas::encounter::{AircraftState, Frame, IntruderCas};
+use opencas::geodesy::{Geodetic, LocalFrame};
+use opencas::{Advisory, HAdvisory, VAdvisory};
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::velocity::meter_per_second;
+
+use crate::gdl90::{decode_frames, Message, Report};
+
+/// The advisories for one traffic target, one line of the output stream
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrafficAdvisory {
+    /// The 24 bit address of the target
+    pub address: u32,
+    pub callsign: String,
+    pub horizontal: HAdvisory,
+    pub vertical: VAdvisory,
+}
+
+impl fmt::Display for TrafficAdvisory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:06X},{},{:?},{:?}",
+            self.address, self.callsign, self.horizontal, self.vertical
+        )
+    }
+}
+
+impl TrafficAdvisory {
+    /// Parses one line of the output stream, see the crate documentation
+    pub fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.trim_end().split(',');
+        let address = u32::from_str_radix(fields.next()?, 16).ok()?;
+        let callsign = fields.next()?.to_owned();
+        let horizontal = by_name(fields.next()?)?;
+        let vertical = by_name(fields.next()?)?;
+
+        fields.next().is_none().then_some(Self {
+            address,
+            callsign,
+            horizontal,
+            vertical,
+        })
+    }
+}
+
+/// Finds the advisory with the given `Debug` name
+fn by_name<A: Advisory>(name: &str) -> Option<A> {
+    A::ALL
+        .iter()
+        .copied()
+        .find(|advisory| format!("{advisory:?}") == name)
+}
+
+/// Time after which a traffic target without a report is forgotten
+pub const TARGET_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Both systems, running for one traffic target
+#[derive(Debug, Clone, Copy)]
+struct Target {
+    cas: IntruderCas,
+    last_report: Instant,
+}
+
+/// Runs the HorizontalCAS and the VerticalCAS for every traffic target reported over GDL90
+#[derive(Debug, Clone)]
+pub struct Bridge {
+    /// The last ownship report, and when it was received
+    ownship: Option<(Report, Instant)>,
+    targets: HashMap<u32, Target>,
+
+    /// Targets without a report for this long are forgotten, [`TARGET_TIMEOUT`] by default
+    pub timeout: Duration,
+
+    /// Number of frames that could not be decoded
+    pub rejected: usize,
+}
+
+impl Default for Bridge {
+    fn default() -> Self {
+        Self {
+            ownship: None,
+            targets: HashMap::new(),
+            timeout: TARGET_TIMEOUT,
+            rejected: 0,
+        }
+    }
+}
+
+impl Bridge {
+    /// Creates a new bridge, waiting for the first ownship report
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The last ownship report
+    pub fn ownship(&self) -> Option<&Report> {
+        self.ownship.as_ref().map(|(report, _)| report)
+    }
+
+    /// Handles one message, returning the advisories for a traffic report
+    ///
+    /// Traffic is skipped as long as no current ownship report was received, and if either report
+    /// lacks an altitude or a horizontal velocity.
+    pub fn handle(&mut self, message: &Message) -> Option<TrafficAdvisory> {
+        self.handle_at(message, Instant::now())
+    }
+
+    /// Same as [`Bridge::handle`], with the message received at `now`
+    ///
+    /// Targets without a report for longer than `timeout` before `now` are forgotten, so a
+    /// target reappearing later starts over clear of conflict. The same holds for the ownship
+    /// report, so traffic is not resolved against a position the ownship has long left.
+    pub fn handle_at(&mut self, message: &Message, now: Instant) -> Option<TrafficAdvisory> {
+        let timeout = self.timeout;
+        let current = |received: Instant| now.saturating_duration_since(received) <= timeout;
+        self.targets.retain(|_, target| current(target.last_report));
+        if self
+            .ownship
+            .as_ref()
+            .is_some_and(|(_, received)| !current(*received))
+        {
+            self.ownship = None;
+        }
+
+        let traffic = match message {
+            Message::OwnshipReport(ownship) => {
+                self.ownship = Some((ownship.clone(), now));
+                return None;
+            }
+            Message::TrafficReport(traffic) => traffic,
+            _ => return None,
+        };
+        let (ownship, _) = self.ownship.as_ref()?;
+
+        let local = LocalFrame::new(geodetic(ownship)?);
+        let (ownship, intruder) = (
+            aircraft_state(&local, ownship)?,
+            aircraft_state(&local, traffic)?,
+        );
+
+        let target = self.targets.entry(traffic.address).or_insert(Target {
+            cas: IntruderCas::default(),
+            last_report: now,
+        });
+        target.last_report = now;
+        let (horizontal, vertical) = target.cas.step(Frame::Enu, ownship, intruder);
+
+        Some(TrafficAdvisory {
+            address: traffic.address,
+            callsign: traffic.callsign.clone(),
+            horizontal,
+            vertical,
+        })
+    }
+
+    /// Handles all frames of a datagram, counting the ones that can not be decoded
+    pub fn process_datagram(&mut self, datagram: &[u8]) -> Vec<TrafficAdvisory> {
+        let mut advisories = Vec::new();
+        for message in decode_frames(datagram) {
+            match message {
+                Ok(message) => advisories.extend(self.handle(&message)),
+                Err(_) => self.rejected += 1,
+            }
+        }
+        advisories
+    }
+
+    /// Receives one datagram from `socket`, and sends one line per advisory to `output`
+    ///
+    /// Returns the number of advisories sent.
+    pub fn serve_one(
+        &mut self,
+        socket: &UdpSocket,
+        output: impl ToSocketAddrs,
+    ) -> io::Result<usize> {
+        let mut buffer = [0; 2048];
+        let (len, _) = socket.recv_from(&mut buffer)?;
+
+        let advisories = self.process_datagram(&buffer[..len]);
+        for advisory in &advisories {
+            socket.send_to(format!("{advisory}\n").as_bytes(), &output)?;
+        }
+        Ok(advisories.len())
+    }
+}
+
+fn geodetic(report: &Report) -> Option<Geodetic> {
+    Some(Geodetic::new(
+        report.latitude,
+        report.longitude,
+        report.altitude?,
+    ))
+}
+
+fn aircraft_state(local: &LocalFrame, report: &Report) -> Option<AircraftState> {
+    let speed = report.horizontal_velocity?.get::<meter_per_second>();
+    let track = report.track.get::<radian>();
+    let up = report
+        .vertical_velocity
+        .map(|v| v.get::<meter_per_second>())
+        .unwrap_or(0.0);
+
+    Some(local.aircraft_state(
+        Frame::Enu,
+        geodetic(report)?,
+        [speed * track.sin(), speed * track.cos(), up].map(Velocity::new::<meter_per_second>),
+        report.track,
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::gdl90::TrackType;
+    use uom::si::angle::degree;
+    use uom::si::length::foot;
+    use uom::si::velocity::{foot_per_minute, knot};
+
+    fn report(address: u32, latitude: f32, altitude: f32, track: f32) -> Report {
+        Report {
+            alert: false,
+            address_type: 0,
+            address,
+            latitude: Angle::new::<degree>(latitude),
+            longitude: Angle::new::<degree>(10.0),
+            altitude: Some(Length::new::<foot>(altitude)),
+            track_type: TrackType::TrueTrack,
+            extrapolated: false,
+            airborne: true,
+            nic: 10,
+            nacp: 9,
+            horizontal_velocity: Some(Velocity::new::<knot>(200.0)),
+            vertical_velocity: Some(Velocity::new::<foot_per_minute>(0.0)),
+            track: Angle::new::<degree>(track),
+            emitter_category: 1,
+            callsign: format!("T{address:X}"),
+            priority: 0,
+        }
+    }
+
+    #[test]
+    pub fn advisory_lines_round_trip() {
+        let advisory = TrafficAdvisory {
+            address: 0xAB4549,
+            callsign: "N825V".into(),
+            horizontal: HAdvisory::StrongRight,
+            vertical: VAdvisory::DoNotClimb,
+        };
+        let line = advisory.to_string();
+
+        assert_eq!(line, "AB4549,N825V,StrongRight,DoNotClimb");
+        assert_eq!(TrafficAdvisory::from_line(&line), Some(advisory));
+        assert_eq!(TrafficAdvisory::from_line("AB4549,N825V,Left,COC"), None);
+    }
+
+    #[test]
+    pub fn traffic_needs_ownship() {
+        let mut bridge = Bridge::new();
+        let traffic = Message::TrafficReport(report(2, 53.02, 5000.0, 180.0));
+
+        assert_eq!(bridge.handle(&traffic), None);
+        bridge.handle(&Message::OwnshipReport(report(1, 53.0, 5000.0, 0.0)));
+        assert!(bridge.handle(&traffic).is_some());
+    }
+
+    #[test]
+    pub fn stale_targets_expire() {
+        let mut bridge = Bridge::new();
+        let start = Instant::now();
+        bridge.handle_at(&Message::OwnshipReport(report(1, 53.0, 5000.0, 0.0)), start);
+        bridge.handle_at(
+            &Message::TrafficReport(report(2, 53.02, 5000.0, 180.0)),
+            start,
+        );
+        bridge.handle_at(
+            &Message::TrafficReport(report(3, 53.02, 5000.0, 180.0)),
+            start + Duration::from_secs(20),
+        );
+        assert_eq!(bridge.targets.len(), 2);
+
+        bridge.handle_at(
+            &Message::OwnshipReport(report(1, 53.0, 5000.0, 0.0)),
+            start + TARGET_TIMEOUT + Duration::from_secs(1),
+        );
+        assert_eq!(bridge.targets.keys().collect::<Vec<_>>(), [&3]);
+
+        // without ownship reports, traffic is no longer resolved
+        let late = start + 2 * TARGET_TIMEOUT + Duration::from_secs(2);
+        let traffic = Message::TrafficReport(report(3, 53.02, 5000.0, 180.0));
+        assert_eq!(bridge.handle_at(&traffic, late), None);
+        assert!(bridge.ownship().is_none());
+
+        bridge.handle_at(&Message::OwnshipReport(report(1, 53.0, 5000.0, 0.0)), late);
+        assert!(bridge.handle_at(&traffic, late).is_some());
+    }
+
+    #[test]
+    pub fn loopback() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let listener = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        listener
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        // head on at the same altitude, 2.2 km apart, plus a corrupted frame
+        let mut datagram = Message::OwnshipReport(report(1, 53.0, 5000.0, 0.0)).to_frame();
+        datagram.extend(Message::TrafficReport(report(2, 53.02, 5000.0, 180.0)).to_frame());
+        let mut corrupted = Message::TrafficReport(report(3, 53.02, 5000.0, 180.0)).to_frame();
+        corrupted[10] ^= 0xFF;
+        datagram.extend(corrupted);
+        sender
+            .send_to(&datagram, socket.local_addr().unwrap())
+            .unwrap();
+
+        let mut bridge = Bridge::new();
+        assert_eq!(
+            bridge
+                .serve_one(&socket, listener.local_addr().unwrap())
+                .unwrap(),
+            1
+        );
+        assert_eq!(bridge.rejected, 1);
+
+        let mut buffer = [0; 256];
+        let len = listener.recv(&mut buffer).unwrap();
+        let line = std::str::from_utf8(&buffer[..len]).unwrap();
+        let advisory = TrafficAdvisory::from_line(line).unwrap();
+
+        assert_eq!(advisory.address, 2);
+        assert_eq!(advisory.callsign, "T2");
+        assert_ne!(advisory.vertical, VAdvisory::ClearOfConflict);
+    }
+}
@@ -0,0 +1,493 @@
+use std::fmt;
+
+use uom::si::angle::degree;
+use uom::si::f32::*;
+use uom::si::length::foot;
+use uom::si::velocity::{foot_per_minute, knot};
+
+/// Marks the start and the end of every frame
+pub const FLAG: u8 = 0x7E;
+
+/// Precedes an escaped byte within a frame
+const ESCAPE: u8 = 0x7D;
+
+/// An escaped byte is xored with this value
+const ESCAPE_XOR: u8 = 0x20;
+
+/// Resolution of latitude and longitude in degrees
+const LAT_LON_RESOLUTION: f32 = 180.0 / (1 << 23) as f32;
+
+/// Resolution of the track angle in degrees
+const TRACK_RESOLUTION: f32 = 360.0 / 256.0;
+
+/// Length of an ownship or traffic report, including the message ID
+const REPORT_LEN: usize = 28;
+
+/// Lookup table of the CRC-16-CCITT, as given in the GDL90 specification
+const CRC16_TABLE: [u16; 256] = crc16_table();
+
+const fn crc16_table() -> [u16; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = (crc << 1) ^ if crc & 0x8000 != 0 { 0x1021 } else { 0 };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+/// Computes the frame check sequence of a message, as given in the GDL90 specification
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0, |crc, &byte| {
+        CRC16_TABLE[(crc >> 8) as usize] ^ (crc << 8) ^ byte as u16
+    })
+}
+
+/// Errors reported when decoding a frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Gdl90Error {
+    /// The frame is too short to hold a message ID and the CRC
+    Empty,
+
+    /// The frame ends with an escape byte
+    InvalidEscape,
+
+    /// The CRC does not match
+    Crc { expected: u16, found: u16 },
+
+    /// The message with the given ID is shorter than its format requires
+    Truncated { id: u8, len: usize },
+
+    /// The message with the given ID is longer than its format allows
+    Length { id: u8, len: usize },
+}
+
+impl fmt::Display for Gdl90Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "frame is empty"),
+            Self::InvalidEscape => write!(f, "frame ends with an escape byte"),
+            Self::Crc { expected, found } => {
+                write!(f, "CRC is {found:04X}, but {expected:04X} was expected")
+            }
+            Self::Truncated { id, len } => write!(f, "message {id} is truncated to {len} bytes"),
+            Self::Length { id, len } => write!(f, "message {id} is too long with {len} bytes"),
+        }
+    }
+}
+
+impl std::error::Error for Gdl90Error {}
+
+/// The heartbeat, message ID 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Heartbeat {
+    /// Both status bytes, with the top bit of the timestamp cleared
+    pub status: [u8; 2],
+
+    /// Seconds since 0000Z
+    pub timestamp: u32,
+
+    /// Both message count bytes
+    pub counts: [u8; 2],
+}
+
+/// What the track angle of a report refers to
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TrackType {
+    #[default]
+    Invalid,
+    TrueTrack,
+    MagneticHeading,
+    TrueHeading,
+}
+
+/// An ownship or traffic report, message IDs 10 and 20
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    /// Whether a traffic alert is active for this target
+    pub alert: bool,
+
+    pub address_type: u8,
+
+    /// The 24 bit address of the target
+    pub address: u32,
+
+    pub latitude: Angle,
+    pub longitude: Angle,
+
+    /// Pressure altitude, `None` if invalid
+    pub altitude: Option<Length>,
+
+    pub track_type: TrackType,
+
+    /// Whether the report is extrapolated, as opposed to updated
+    pub extrapolated: bool,
+
+    pub airborne: bool,
+
+    /// Navigation integrity and accuracy categories
+    pub nic: u8,
+    pub nacp: u8,
+
+    /// Horizontal velocity, `None` if unavailable
+    pub horizontal_velocity: Option<Velocity>,
+
+    /// Vertical velocity, positive when climbing, `None` if unavailable
+    pub vertical_velocity: Option<Velocity>,
+
+    /// Track or heading, clockwise from north, see `track_type`
+    pub track: Angle,
+
+    pub emitter_category: u8,
+
+    /// Callsign, at most 8 characters
+    pub callsign: String,
+
+    pub priority: u8,
+}
+
+/// A GDL90 message
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    Heartbeat(Heartbeat),
+    OwnshipReport(Report),
+    TrafficReport(Report),
+
+    /// A message with the given ID, which is not decoded
+    Other {
+        id: u8,
+        data: Vec<u8>,
+    },
+}
+
+impl Message {
+    /// Message ID of the heartbeat
+    pub const HEARTBEAT: u8 = 0;
+
+    /// Message ID of the ownship report
+    pub const OWNSHIP_REPORT: u8 = 10;
+
+    /// Message ID of the traffic report
+    pub const TRAFFIC_REPORT: u8 = 20;
+
+    /// Decodes a message from its unescaped bytes, starting with the message ID, without the CRC
+    pub fn decode(message: &[u8]) -> Result<Self, Gdl90Error> {
+        let (&id, data) = message.split_first().ok_or(Gdl90Error::Empty)?;
+        let len = message.len();
+        let wrong_length = |expected: usize| {
+            if len < expected {
+                Gdl90Error::Truncated { id, len }
+            } else {
+                Gdl90Error::Length { id, len }
+            }
+        };
+
+        Ok(match id {
+            Self::HEARTBEAT => {
+                let [status0, status1, ts0, ts1, count0, count1] =
+                    *<&[u8; 6]>::try_from(data).map_err(|_| wrong_length(7))?;
+                Self::Heartbeat(Heartbeat {
+                    status: [status0, status1 & 0x7F],
+                    timestamp: ((status1 as u32 >> 7) << 16)
+                        | u16::from_le_bytes([ts0, ts1]) as u32,
+                    counts: [count0, count1],
+                })
+            }
+            Self::OWNSHIP_REPORT | Self::TRAFFIC_REPORT => {
+                let data = <&[u8; REPORT_LEN - 1]>::try_from(data)
+                    .map_err(|_| wrong_length(REPORT_LEN))?;
+                let report = Report::decode(data);
+                if id == Self::OWNSHIP_REPORT {
+                    Self::OwnshipReport(report)
+                } else {
+                    Self::TrafficReport(report)
+                }
+            }
+            _ => Self::Other {
+                id,
+                data: data.to_vec(),
+            },
+        })
+    }
+
+    /// Encodes the message to its unescaped bytes, starting with the message ID, without the CRC
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Self::Heartbeat(heartbeat) => {
+                let [ts0, ts1, ts2, _] = heartbeat.timestamp.to_le_bytes();
+                vec![
+                    Self::HEARTBEAT,
+                    heartbeat.status[0],
+                    (heartbeat.status[1] & 0x7F) | ((ts2 & 1) << 7),
+                    ts0,
+                    ts1,
+                    heartbeat.counts[0],
+                    heartbeat.counts[1],
+                ]
+            }
+            Self::OwnshipReport(report) => report.encode(Self::OWNSHIP_REPORT),
+            Self::TrafficReport(report) => report.encode(Self::TRAFFIC_REPORT),
+            Self::Other { id, data } => [&[*id], data.as_slice()].concat(),
+        }
+    }
+
+    /// Encodes the message into a complete frame, including the CRC, byte stuffing and flags
+    pub fn to_frame(&self) -> Vec<u8> {
+        let mut message = self.encode();
+        let crc = crc16(&message);
+        message.extend(crc.to_le_bytes());
+
+        let mut frame = vec![FLAG];
+        for byte in message {
+            if byte == FLAG || byte == ESCAPE {
+                frame.extend([ESCAPE, byte ^ ESCAPE_XOR]);
+            } else {
+                frame.push(byte);
+            }
+        }
+        frame.push(FLAG);
+        frame
+    }
+
+    /// Decodes a single frame, with or without the enclosing flags
+    pub fn from_frame(frame: &[u8]) -> Result<Self, Gdl90Error> {
+        let frame = frame.strip_prefix(&[FLAG]).unwrap_or(frame);
+        let frame = frame.strip_suffix(&[FLAG]).unwrap_or(frame);
+
+        let mut message = Vec::with_capacity(frame.len());
+        let mut bytes = frame.iter();
+        while let Some(&byte) = bytes.next() {
+            if byte == ESCAPE {
+                let escaped = bytes.next().ok_or(Gdl90Error::InvalidEscape)?;
+                message.push(escaped ^ ESCAPE_XOR);
+            } else {
+                message.push(byte);
+            }
+        }
+
+        if message.len() < 3 {
+            return Err(Gdl90Error::Empty);
+        }
+        let (message, crc) = message.split_at(message.len() - 2);
+        let (expected, found) = (crc16(message), u16::from_le_bytes([crc[0], crc[1]]));
+        if expected != found {
+            return Err(Gdl90Error::Crc { expected, found });
+        }
+
+        Self::decode(message)
+    }
+}
+
+/// Decodes all frames of a datagram, which may hold any number of frames
+pub fn decode_frames(datagram: &[u8]) -> impl Iterator<Item = Result<Message, Gdl90Error>> + '_ {
+    datagram
+        .split(|&byte| byte == FLAG)
+        .filter(|frame| !frame.is_empty())
+        .map(Message::from_frame)
+}
+
+impl Report {
+    fn decode(d: &[u8; REPORT_LEN - 1]) -> Self {
+        let u24 = |b: &[u8]| u32::from_be_bytes([0, b[0], b[1], b[2]]);
+        // sign extends a 24 bit two's complement number
+        let angle = |b: &[u8]| {
+            Angle::new::<degree>(((u24(b) << 8) as i32 >> 8) as f32 * LAT_LON_RESOLUTION)
+        };
+
+        let altitude = ((d[10] as u16) << 4) | (d[11] as u16 >> 4);
+        let misc = d[11] & 0x0F;
+        let horizontal = ((d[13] as u16) << 4) | (d[14] as u16 >> 4);
+        let vertical = (((d[14] as u16) & 0x0F) << 8) | d[15] as u16;
+
+        Self {
+            alert: d[0] >> 4 == 1,
+            address_type: d[0] & 0x0F,
+            address: u24(&d[1..4]),
+            latitude: angle(&d[4..7]),
+            longitude: angle(&d[7..10]),
+            altitude: (altitude != 0xFFF)
+                .then(|| Length::new::<foot>(altitude as f32 * 25.0 - 1000.0)),
+            track_type: match misc & 0x3 {
+                0 => TrackType::Invalid,
+                1 => TrackType::TrueTrack,
+                2 => TrackType::MagneticHeading,
+                _ => TrackType::TrueHeading,
+            },
+            extrapolated: misc & 0x4 != 0,
+            airborne: misc & 0x8 != 0,
+            nic: d[12] >> 4,
+            nacp: d[12] & 0x0F,
+            horizontal_velocity: (horizontal != 0xFFF)
+                .then(|| Velocity::new::<knot>(horizontal as f32)),
+            // sign extends the 12 bit two's complement number
+            vertical_velocity: (vertical != 0x800).then(|| {
+                Velocity::new::<foot_per_minute>(((vertical << 4) as i16 >> 4) as f32 * 64.0)
+            }),
+            track: Angle::new::<degree>(d[16] as f32 * TRACK_RESOLUTION),
+            emitter_category: d[17],
+            callsign: String::from_utf8_lossy(&d[18..26]).trim_end().to_owned(),
+            priority: d[26] >> 4,
+        }
+    }
+
+    fn encode(&self, id: u8) -> Vec<u8> {
+        let angle = |a: Angle| {
+            let raw = (a.get::<degree>() / LAT_LON_RESOLUTION).round() as i32;
+            let [_, b0, b1, b2] = raw.to_be_bytes();
+            [b0, b1, b2]
+        };
+
+        let altitude = self
+            .altitude
+            .map(|a| {
+                ((a.get::<foot>() + 1000.0) / 25.0)
+                    .round()
+                    .clamp(0.0, 4094.0) as u16
+            })
+            .unwrap_or(0xFFF);
+        let misc =
+            self.track_type as u8 | (self.extrapolated as u8) << 2 | (self.airborne as u8) << 3;
+        let horizontal = self
+            .horizontal_velocity
+            .map(|v| v.get::<knot>().round().clamp(0.0, 4094.0) as u16)
+            .unwrap_or(0xFFF);
+        let vertical = self
+            .vertical_velocity
+            .map(|v| {
+                (v.get::<foot_per_minute>() / 64.0)
+                    .round()
+                    .clamp(-510.0, 510.0) as i16 as u16
+            })
+            .unwrap_or(0x800)
+            & 0xFFF;
+        let track =
+            (self.track.get::<degree>().rem_euclid(360.0) / TRACK_RESOLUTION).round() as u32;
+
+        let mut callsign = [b' '; 8];
+        for (c, byte) in callsign.iter_mut().zip(self.callsign.bytes()) {
+            *c = byte;
+        }
+
+        let mut message = vec![id, (self.alert as u8) << 4 | (self.address_type & 0x0F)];
+        message.extend(&self.address.to_be_bytes()[1..]);
+        message.extend(angle(self.latitude));
+        message.extend(angle(self.longitude));
+        message.extend([
+            (altitude >> 4) as u8,
+            ((altitude & 0x0F) as u8) << 4 | misc,
+            self.nic << 4 | (self.nacp & 0x0F),
+            (horizontal >> 4) as u8,
+            ((horizontal & 0x0F) as u8) << 4 | (vertical >> 8) as u8,
+            vertical as u8,
+            (track % 256) as u8,
+            self.emitter_category,
+        ]);
+        message.extend(callsign);
+        message.push(self.priority << 4);
+        message
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// The traffic report example of the GDL90 specification
+    const TRAFFIC_EXAMPLE: [u8; 28] = [
+        0x14, 0x00, 0xAB, 0x45, 0x49, 0x1F, 0xEF, 0x15, 0xA8, 0x89, 0x78, 0x0F, 0x09, 0xA9, 0x07,
+        0xB0, 0x01, 0x20, 0x01, 0x4E, 0x38, 0x32, 0x35, 0x56, 0x20, 0x20, 0x20, 0x00,
+    ];
+
+    #[test]
+    pub fn heartbeat_crc_example() {
+        // the heartbeat example of the GDL90 specification
+        let frame = [
+            0x7E, 0x00, 0x81, 0x41, 0xDB, 0xD0, 0x08, 0x02, 0xB3, 0x8B, 0x7E,
+        ];
+        let message = Message::from_frame(&frame).unwrap();
+
+        assert!(matches!(message, Message::Heartbeat(_)));
+        assert_eq!(message.to_frame(), frame);
+    }
+
+    #[test]
+    pub fn traffic_report_example() {
+        let Message::TrafficReport(report) = Message::decode(&TRAFFIC_EXAMPLE).unwrap() else {
+            panic!("example is no traffic report");
+        };
+
+        assert_eq!(report.address, 0xAB4549);
+        assert!((report.latitude.get::<degree>() - 44.907_08).abs() < 1e-4);
+        assert!((report.longitude.get::<degree>() + 122.994_88).abs() < 1e-4);
+        assert_eq!(report.altitude, Some(Length::new::<foot>(5000.0)));
+        assert_eq!(report.track_type, TrackType::TrueTrack);
+        assert!(report.airborne && !report.extrapolated);
+        assert_eq!(
+            report.horizontal_velocity,
+            Some(Velocity::new::<knot>(123.0))
+        );
+        assert_eq!(
+            report.vertical_velocity,
+            Some(Velocity::new::<foot_per_minute>(64.0))
+        );
+        assert!((report.track.get::<degree>() - 45.0).abs() < 1.0);
+        assert_eq!(report.callsign, "N825V");
+
+        assert_eq!(
+            Message::TrafficReport(report).encode(),
+            TRAFFIC_EXAMPLE.to_vec()
+        );
+    }
+
+    #[test]
+    pub fn byte_stuffing_and_errors() {
+        // the address contains both the flag and the escape byte
+        let mut message = TRAFFIC_EXAMPLE;
+        message[2..4].copy_from_slice(&[FLAG, ESCAPE]);
+        let message = Message::decode(&message).unwrap();
+
+        let frame = message.to_frame();
+        assert_eq!(frame.iter().filter(|&&b| b == FLAG).count(), 2);
+        assert_eq!(Message::from_frame(&frame).unwrap(), message);
+
+        let mut datagram = frame.clone();
+        datagram.extend(&frame);
+        assert_eq!(decode_frames(&datagram).count(), 2);
+
+        let mut corrupted = frame.clone();
+        corrupted[5] ^= 1;
+        assert!(matches!(
+            Message::from_frame(&corrupted),
+            Err(Gdl90Error::Crc { .. })
+        ));
+        assert_eq!(
+            Message::from_frame(&[FLAG, 0x14, ESCAPE, FLAG]),
+            Err(Gdl90Error::InvalidEscape)
+        );
+        let short = Message::Other {
+            id: Message::TRAFFIC_REPORT,
+            data: vec![0; 3],
+        };
+        assert_eq!(
+            Message::from_frame(&short.to_frame()),
+            Err(Gdl90Error::Truncated { id: 20, len: 4 })
+        );
+        let long = Message::Other {
+            id: Message::TRAFFIC_REPORT,
+            data: vec![0; REPORT_LEN],
+        };
+        assert_eq!(
+            Message::from_frame(&long.to_frame()),
+            Err(Gdl90Error::Length {
+                id: 20,
+                len: REPORT_LEN + 1
+            })
+        );
+    }
+}
@@ -0,0 +1,25 @@
+//! A bridge between GDL90 and openCAS. It listens for GDL90 Ownship and Traffic Reports on a UDP
+//! port, runs the HorizontalCAS and the VerticalCAS for every traffic target and sends the
+//! resulting advisories to another UDP address.
+//!
+//! # Output stream
+//!
+//! Every traffic report yields one datagram holding one line of comma separated fields:
+//!
+//! ```text
+//! <address>,<callsign>,<horizontal advisory>,<vertical advisory>
+//! ```
+//!
+//! + `address` is the 24 bit address of the target as six upper case hex digits
+//! + `callsign` is the callsign from the traffic report, which may be empty
+//! + `horizontal advisory` is the name of an [`opencas::HAdvisory`], e.g. `WeakLeft`
+//! + `vertical advisory` is the name of an [`opencas::VAdvisory`], e.g. `Climb1500`
+//!
+//! For example `AB4549,N825V,ClearOfConflict,DoNotClimb`.
+
+/// This module encodes and decodes GDL90 frames and messages.
+pub mod gdl90;
+
+/// This module runs the CAS for every traffic target and produces the output stream.
+pub mod bridge;
+pub use bridge::{Bridge, TrafficAdvisory};
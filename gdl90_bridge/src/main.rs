@@ -0,0 +1,29 @@
+use std::net::UdpSocket;
+
+use gdl90_bridge::Bridge;
+
+/// GDL90 is sent to port 4000 by convention
+const DEFAULT_LISTEN: &str = "0.0.0.0:4000";
+const DEFAULT_OUTPUT: &str = "127.0.0.1:4001";
+
+fn main() -> std::io::Result<()> {
+    let mut args = std::env::args().skip(1);
+    if args.len() > 2 {
+        eprintln!("usage: gdl90_bridge [LISTEN_ADDRESS] [OUTPUT_ADDRESS]");
+        std::process::exit(2);
+    }
+    let listen = args.next().unwrap_or_else(|| DEFAULT_LISTEN.into());
+    let output = args.next().unwrap_or_else(|| DEFAULT_OUTPUT.into());
+
+    let socket = UdpSocket::bind(&listen)?;
+    eprintln!("listening on {listen}, sending advisories to {output}");
+
+    let mut bridge = Bridge::new();
+    loop {
+        let rejected = bridge.rejected;
+        bridge.serve_one(&socket, output.as_str())?;
+        if bridge.rejected > rejected {
+            eprintln!("rejected {} frames so far", bridge.rejected);
+        }
+    }
+}
@@ -1,5 +1,6 @@
-use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
 use rand::Rng;
+use std::f32::consts::PI;
 use std::time::Duration;
 use uom::si::{
     angle::radian,
@@ -9,8 +10,12 @@ use uom::si::{
     velocity::foot_per_minute,
 };
 
+use opencas::multi::{HIntruder, VIntruder};
 use opencas::*;
 
+/// Number of geometries evaluated per iteration of the batch benchmarks
+const GEOMETRIES: usize = 1024;
+
 /// This code is used to benchmark HorizontalCAS
 fn criterion_benchmark_horizontal(c: &mut Criterion) {
     let mut group = c.benchmark_group("hcas");
@@ -89,13 +94,91 @@ fn criterion_benchmark_vertical(c: &mut Criterion) {
     }
 }
 
+/// This code compares the batched evaluation of many geometries against one call per geometry
+fn criterion_benchmark_batch(c: &mut Criterion) {
+    let mut group = c.benchmark_group("batch");
+    group.throughput(Throughput::Elements(GEOMETRIES as u64));
+
+    let mut rng = rand::thread_rng();
+
+    // genrate random geometries, all selecting the same HCAS network
+    let tau = Time::new::<second>(20.0);
+    let hcas_intruders: Vec<HIntruder> = (0..GEOMETRIES)
+        .map(|_| HIntruder {
+            tau,
+            forward_range: Length::new::<foot>(rng.gen_range(-56e3f32..56e3)),
+            left_range: Length::new::<foot>(rng.gen_range(-56e3f32..56e3)),
+            psi: Angle::new::<radian>(rng.gen_range(-PI..PI)),
+        })
+        .collect();
+    let mut hcas_scores = vec![AdvisoryScores::new([0.0; 5]); GEOMETRIES];
+    let hcas = HCas::new(HAdvisory::WeakLeft);
+
+    group.bench_function("hcas scalar", |b| {
+        b.iter(|| {
+            for (i, scores) in hcas_intruders.iter().zip(&mut hcas_scores) {
+                *scores = hcas::evaluate(
+                    hcas.last_advisory,
+                    i.tau,
+                    i.forward_range,
+                    i.left_range,
+                    i.psi,
+                );
+            }
+            black_box(&hcas_scores);
+        })
+    });
+    group.bench_function("hcas batch", |b| {
+        b.iter(|| {
+            hcas.evaluate_batch(black_box(&hcas_intruders), &mut hcas_scores);
+            black_box(&hcas_scores);
+        })
+    });
+
+    let vert_speed_homeship = Velocity::new::<foot_per_minute>(rng.gen_range(-6e3f32..6e3));
+    let vcas_intruders: Vec<VIntruder> = (0..GEOMETRIES)
+        .map(|_| VIntruder {
+            height: Length::new::<foot>(rng.gen_range(-8e3f32..8e3)),
+            vertical_speed_intruder: Velocity::new::<foot_per_minute>(rng.gen_range(-6e3f32..6e3)),
+            tau: Time::new::<second>(rng.gen_range(0f32..40.0)),
+        })
+        .collect();
+    let mut vcas_scores = vec![AdvisoryScores::new([0.0; 9]); GEOMETRIES];
+    let vcas = VCas::new(VAdvisory::DoNotClimb);
+
+    group.bench_function("vcas scalar", |b| {
+        b.iter(|| {
+            for (i, scores) in vcas_intruders.iter().zip(&mut vcas_scores) {
+                *scores = vcas::evaluate(
+                    vcas.last_advisory,
+                    i.height,
+                    vert_speed_homeship,
+                    i.vertical_speed_intruder,
+                    i.tau,
+                );
+            }
+            black_box(&vcas_scores);
+        })
+    });
+    group.bench_function("vcas batch", |b| {
+        b.iter(|| {
+            vcas.evaluate_batch(
+                vert_speed_homeship,
+                black_box(&vcas_intruders),
+                &mut vcas_scores,
+            );
+            black_box(&vcas_scores);
+        })
+    });
+}
+
 // run the benchmark on both CAS
 criterion_group! {
     name = benches;
     config = Criterion::default()
         .measurement_time(Duration::from_secs(1))
         .warm_up_time(Duration::from_secs(3));
-    targets = criterion_benchmark_horizontal, criterion_benchmark_vertical
+    targets = criterion_benchmark_horizontal, criterion_benchmark_vertical, criterion_benchmark_batch
 }
 
 criterion_main!(benches);
@@ -1,6 +1,8 @@
+extern crate alloc;
+
 use csv::Trim;
 use proc_macro2::TokenStream;
-use quote::quote;
+use quote::{format_ident, quote};
 use std::{
     env,
     fs::{self, File},
@@ -9,6 +11,11 @@ use std::{
     process::Command,
 };
 
+/// The ONNX model of `opencas::onnx`, which is independent of the rest of the crate
+#[allow(dead_code)]
+#[path = "src/onnx/model.rs"]
+mod onnx;
+
 /// This macro is simplifying some code later on.
 ///
 /// It is doing what its name promises: converts a line (string) into a vector.
@@ -35,16 +42,34 @@ macro_rules! line_to_vec {
     }};
 }
 
-/// Parse a `.nnet` file, and emits the equivalent `TokenStream` to instantiat an equal `NNet`
-/// struct
+/// The contents of a `.nnet` or `.onnx` file, with all layers split into weights and biases
 ///
-/// Returns a tuple consisting of two elements:
-/// + `TokenStream` instantiating the `NNet` struct
-/// + `TokenStream` describing the type of said `NNet` struct
-fn parse_nnet<P: AsRef<Path>>(nnet_file: P) -> (TokenStream, TokenStream) {
+/// `n_mat` and `n_neuron` only describe networks whose hidden layers share one width, while
+/// `nodes_per_layer` holds the width of every layer, from the inputs to the outputs.
+struct ParsedNNet {
+    n_input: usize,
+    n_mat: usize,
+    n_neuron: usize,
+    n_output: usize,
+    nodes_per_layer: Vec<usize>,
+    min_input: Vec<f32>,
+    max_input: Vec<f32>,
+    mean: Vec<f32>,
+    range: Vec<f32>,
+    mean_output: f32,
+    range_output: f32,
+    /// Weights of every layer, one row per output neuron
+    weights: Vec<Vec<Vec<f32>>>,
+    biases: Vec<Vec<f32>>,
+}
+
+/// Parse a `.nnet` file
+fn parse_nnet<P: AsRef<Path>>(nnet_file: P) -> ParsedNNet {
     // open the nnet file, create a buffered reader and feed everything to the csv crate
     let f = File::open(nnet_file).expect("file does not exits: {nnet_file}");
     let mut csv_reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .comment(Some(b'/'))
         .flexible(true)
         .double_quote(false)
         .trim(Trim::All)
@@ -73,10 +98,13 @@ fn parse_nnet<P: AsRef<Path>>(nnet_file: P) -> (TokenStream, TokenStream) {
                 num_layer = values[0];
                 n_input = values[1];
                 n_output = values[2];
-                n_neuron = values[3];
                 n_mat = num_layer - 2;
             }
-            2 => nodes_per_layer = line_to_vec!(line, num_layer + 1),
+            2 => {
+                nodes_per_layer = line_to_vec!(line, num_layer + 1);
+                // the header only holds the largest layer, which may as well be the input layer
+                n_neuron = nodes_per_layer[1];
+            }
             3 => {} // can be ignored
             4 => min_input = line_to_vec!(line, n_input),
             5 => max_input = line_to_vec!(line, n_input),
@@ -124,47 +152,347 @@ fn parse_nnet<P: AsRef<Path>>(nnet_file: P) -> (TokenStream, TokenStream) {
         biases.push(current_biases);
     }
 
-    //nnet file has been read by now
-    //splitting generated vectors into the right sizes for the struct NNet
-    let input_biases = biases.remove(0);
-    let input_weights = weights.remove(0);
-
-    let output_biases = biases.pop().unwrap();
-    let output_weights = weights.pop().unwrap();
-
     let mean_output = mean.pop().unwrap();
     let range_output = range.pop().unwrap();
 
-    // write the parsed data into the NNet struct form.
-    (
-        quote!(
-         NNet {
-                input_layer: Layer {
-                    a: matrix![ #( #( #input_weights ),* );* ],
-                    biases: vector![ #( #input_biases ),* ],
-                },
-                hidden_layers: [
-                    #( Layer {
+    ParsedNNet {
+        n_input,
+        n_mat,
+        n_neuron,
+        n_output,
+        nodes_per_layer,
+        min_input,
+        max_input,
+        mean,
+        range,
+        mean_output,
+        range_output,
+        weights,
+        biases,
+    }
+}
+
+/// Parse the feed-forward network of an `.onnx` file, see `onnx::Model::feed_forward`
+fn parse_onnx<P: AsRef<Path>>(onnx_file: P) -> ParsedNNet {
+    let path = onnx_file.as_ref();
+    let bytes = fs::read(path).unwrap_or_else(|e| panic!("can not read {}: {e}", path.display()));
+    let network = onnx::Model::parse(&bytes)
+        .and_then(|model| model.feed_forward())
+        .unwrap_or_else(|e| panic!("can not import {}: {e}", path.display()));
+
+    let nodes_per_layer: Vec<usize> = std::iter::once(network.weights[0].dims[1])
+        .chain(network.weights.iter().map(|w| w.dims[0]))
+        .collect();
+    let hidden = &nodes_per_layer[1..nodes_per_layer.len() - 1];
+
+    ParsedNNet {
+        n_input: nodes_per_layer[0],
+        n_mat: hidden.len().saturating_sub(1),
+        n_neuron: hidden.iter().copied().max().unwrap_or(0),
+        n_output: *nodes_per_layer.last().unwrap(),
+        min_input: network.min_input,
+        max_input: network.max_input,
+        mean: network.mean_input,
+        range: network.range_input,
+        mean_output: network.mean_output,
+        range_output: network.range_output,
+        weights: network
+            .weights
+            .iter()
+            .map(|w| w.data.chunks(w.dims[1]).map(<[f32]>::to_vec).collect())
+            .collect(),
+        biases: network.biases,
+        nodes_per_layer,
+    }
+}
+
+impl ParsedNNet {
+    /// Emits the `TokenStream` to instantiate an equal `NNet` struct
+    ///
+    /// Returns a tuple consisting of two elements:
+    /// + `TokenStream` instantiating the `NNet` struct
+    /// + `TokenStream` describing the type of said `NNet` struct
+    fn tokens(&self) -> (TokenStream, TokenStream) {
+        let ParsedNNet {
+            n_input,
+            n_mat,
+            n_neuron,
+            n_output,
+            min_input,
+            max_input,
+            mean,
+            range,
+            mean_output,
+            range_output,
+            ..
+        } = self;
+
+        //splitting the layers into the right sizes for the struct NNet
+        let (input_weights, weights) = self.weights.split_first().unwrap();
+        let (output_weights, weights) = weights.split_last().unwrap();
+        let (input_biases, biases) = self.biases.split_first().unwrap();
+        let (output_biases, biases) = biases.split_last().unwrap();
+
+        // write the parsed data into the NNet struct form.
+        (
+            quote!(
+             NNet {
+                    input_layer: Layer {
+                        a: matrix![ #( #( #input_weights ),* );* ],
+                        biases: vector![ #( #input_biases ),* ],
+                    },
+                    hidden_layers: [
+                        #( Layer {
+                            a: matrix![ #( #( #weights ),* );* ],
+                            biases: vector![ #( #biases ),* ],
+                        } ),*
+                    ],
+                    output_layer: Layer {
+                        a: matrix![ #( #( #output_weights ),* );* ],
+                        biases: vector![ #( #output_biases ),* ],
+                    },
+                    min_input: vector![ #( #min_input ),* ],
+                    max_input: vector![ #( #max_input ),* ],
+                    mean_value: vector![ #( #mean ),* ],
+                    range: vector![ #( #range ),* ],
+                    mean_output: #mean_output,
+                    range_output: #range_output,
+                }
+            ),
+            quote!(NNet<#n_input, #n_mat, #n_neuron, #n_output>),
+        )
+    }
+
+    /// Emits the `TokenStream` to instantiate the quantized `FixedNNet` struct
+    ///
+    /// Activations are Q16.16. The weights of every layer are rounded to `i32`, with 24
+    /// significant bits for the largest weight of the layer. Every input gets its own
+    /// format, with as many fractional bits as its bounds allow in an `i32`.
+    ///
+    /// Returns the same kind of tuple as [`ParsedNNet::tokens`].
+    fn fixed_tokens(&self) -> (TokenStream, TokenStream) {
+        let ParsedNNet {
+            n_input,
+            n_mat,
+            n_neuron,
+            n_output,
+            ..
+        } = *self;
+
+        let layers: Vec<TokenStream> = self
+            .weights
+            .iter()
+            .zip(&self.biases)
+            .map(|(weights, biases)| fixed_layer(weights, biases))
+            .collect();
+        let (input_layer, layers) = layers.split_first().unwrap();
+        let (output_layer, hidden_layers) = layers.split_last().unwrap();
+
+        let input_shift = self.input_shifts();
+        let quantize_inputs = |values: &[f32]| -> Vec<i32> {
+            values
+                .iter()
+                .zip(&input_shift)
+                .map(|(v, shift)| quantize_i32(*v as f64, *shift))
+                .collect()
+        };
+        let min_input = quantize_inputs(&self.min_input);
+        let max_input = quantize_inputs(&self.max_input);
+        let mean_value = quantize_inputs(&self.mean[..n_input]);
+
+        // (x - mean) / range is computed as (x - mean) * scale >> scale_shift, with scale
+        // normalized to 30 bits
+        let (scale, scale_shift): (Vec<i32>, Vec<u32>) = self.range[..n_input]
+            .iter()
+            .zip(&input_shift)
+            .map(|(range, shift)| {
+                let range = *range as f64;
+                let bits = (29.0 + range.log2()).floor() as i32;
+                let scale_shift = *shift as i32 + bits - FRAC_BITS as i32;
+                assert!(
+                    (1..63).contains(&scale_shift),
+                    "input range {range} can not be normalized in fixed point"
+                );
+                ((2f64.powi(bits) / range).round() as i32, scale_shift as u32)
+            })
+            .unzip();
+
+        let mean_output = quantize_i32(self.mean_output as f64, FRAC_BITS);
+        let range_output = quantize_i32(self.range_output as f64, FRAC_BITS);
+
+        (
+            quote!(
+                FixedNNet {
+                    input_layer: #input_layer,
+                    hidden_layers: [ #( #hidden_layers ),* ],
+                    output_layer: #output_layer,
+                    input_shift: [ #( #input_shift ),* ],
+                    min_input: [ #( #min_input ),* ],
+                    max_input: [ #( #max_input ),* ],
+                    mean_value: [ #( #mean_value ),* ],
+                    scale: [ #( #scale ),* ],
+                    scale_shift: [ #( #scale_shift ),* ],
+                    mean_output: Fixed::from_bits(#mean_output),
+                    range_output: Fixed::from_bits(#range_output),
+                }
+            ),
+            quote!(FixedNNet<#n_input, #n_mat, #n_neuron, #n_output>),
+        )
+    }
+
+    /// Emits the `TokenStream` to instantiate an equal `LayeredNNet` struct, which unlike `NNet`
+    /// allows hidden layers of different widths
+    ///
+    /// Returns the same kind of tuple as [`ParsedNNet::tokens`].
+    fn layered_tokens(&self) -> (TokenStream, TokenStream) {
+        let ParsedNNet {
+            n_input,
+            n_output,
+            min_input,
+            max_input,
+            mean,
+            range,
+            mean_output,
+            range_output,
+            ..
+        } = self;
+
+        // build the list of layers from the output layer backwards
+        let mut layers = self
+            .weights
+            .iter()
+            .zip(&self.biases)
+            .zip(self.nodes_per_layer.windows(2))
+            .rev()
+            .map(|((weights, biases), widths)| {
+                let (n_in, n_out) = (widths[0], widths[1]);
+                (
+                    quote!(Layer {
                         a: matrix![ #( #( #weights ),* );* ],
                         biases: vector![ #( #biases ),* ],
-                    } ),*
-                ],
-                output_layer: Layer {
-                    a: matrix![ #( #( #output_weights ),* );* ],
-                    biases: vector![ #( #output_biases ),* ],
-                },
-                min_input: vector![ #( #min_input ),* ],
-                max_input: vector![ #( #max_input ),* ],
-                mean_value: vector![ #( #mean ),* ],
-                range: vector![ #( #range ),* ],
-                mean_output: #mean_output,
-                range_output: #range_output,
-            }
-        ),
-        quote!(NNet<#n_input, #n_mat, #n_neuron, #n_output>),
+                    }),
+                    n_in,
+                    n_out,
+                )
+            });
+        let (output_layer, n_in, n_out) = layers.next().unwrap();
+        let (layers, layers_type) = layers.fold(
+            (output_layer, quote!(Layer<#n_in, #n_out>)),
+            |(next, next_type), (layer, n_in, n_out)| {
+                (
+                    quote!(Hidden { layer: #layer, next: #next }),
+                    quote!(Hidden<#n_in, #n_out, #next_type>),
+                )
+            },
+        );
+
+        (
+            quote!(
+                LayeredNNet {
+                    layers: #layers,
+                    min_input: vector![ #( #min_input ),* ],
+                    max_input: vector![ #( #max_input ),* ],
+                    mean_value: vector![ #( #mean ),* ],
+                    range: vector![ #( #range ),* ],
+                    mean_output: #mean_output,
+                    range_output: #range_output,
+                }
+            ),
+            quote!(LayeredNNet<#n_input, #n_output, #layers_type>),
+        )
+    }
+
+    /// Fractional bits of every input, so that its bounds fit into an `i32` with a bit to spare
+    fn input_shifts(&self) -> Vec<u32> {
+        self.min_input
+            .iter()
+            .zip(&self.max_input)
+            .map(|(min, max)| {
+                let magnitude = (min.abs().max(max.abs()) as f64).max(f64::MIN_POSITIVE);
+                (30 - magnitude.log2().ceil() as i32).clamp(0, 30) as u32
+            })
+            .collect()
+    }
+}
+
+/// Fractional bits of the Q16.16 activations of the fixed point networks
+const FRAC_BITS: u32 = 16;
+
+/// Rounds `value` to a fixed point number with `shift` fractional bits, saturating at the bounds
+/// of an `i32`
+fn quantize_i32(value: f64, shift: u32) -> i32 {
+    (value * 2f64.powi(shift as i32))
+        .round()
+        .clamp(i32::MIN as f64, i32::MAX as f64) as i32
+}
+
+/// Emits one `FixedLayer`, with `i32` weights scaled by a shift common to the whole layer
+fn fixed_layer(weights: &[Vec<f32>], biases: &[f32]) -> TokenStream {
+    let magnitude = weights
+        .iter()
+        .flatten()
+        .fold(f64::MIN_POSITIVE, |m, w| m.max(w.abs() as f64));
+    // the largest weight stays below 2^23 after scaling, which is the precision of an f32
+    let weight_shift = (22 - magnitude.log2().floor() as i32).clamp(0, 30) as u32;
+
+    let weights = weights.iter().map(|row| {
+        let row = row.iter().map(|w| {
+            (*w as f64 * 2f64.powi(weight_shift as i32))
+                .round()
+                .clamp(i32::MIN as f64, i32::MAX as f64) as i32
+        });
+        quote!([ #( #row ),* ])
+    });
+    let biases = biases.iter().map(|b| quantize_i32(*b as f64, FRAC_BITS));
+
+    quote!(
+        FixedLayer {
+            weights: [ #( #weights ),* ],
+            weight_shift: #weight_shift,
+            biases: [ #( Fixed::from_bits(#biases) ),* ],
+        }
     )
 }
 
+/// Emits the networks of one CAS, as `NNet` structs and, if enabled, as `FixedNNet` structs
+///
+/// Our expectation is, that all nnet files within one CAS have the same type (as in dimensions).
+/// Thus the TokenStreams describing their type must be equal. However, TokenStreams can not be
+/// compared. Therefore we render the TokenStreams into Strings, and compare the Strings.
+///
+/// Returns the type of the `NNet` structs, the `NNet` structs and the same for the `FixedNNet`
+/// structs if the `fixed` feature is enabled.
+#[allow(clippy::type_complexity)]
+fn nnet_family(
+    parsed: &[ParsedNNet],
+) -> (
+    TokenStream,
+    Vec<TokenStream>,
+    Option<(TokenStream, Vec<TokenStream>)>,
+) {
+    let same_type = |types: &[TokenStream]| {
+        let nnet_type = types[0].clone();
+        assert!(types.iter().all(|n| n.to_string() == nnet_type.to_string()));
+        nnet_type
+    };
+
+    let (nnets, types): (Vec<TokenStream>, Vec<TokenStream>) =
+        parsed.iter().map(ParsedNNet::tokens).unzip();
+    let nnet_type = same_type(&types);
+
+    let fixed = env::var_os("CARGO_FEATURE_FIXED").map(|_| {
+        // the fixed point front-ends expect one input format for all networks of a CAS
+        let input_shifts = parsed[0].input_shifts();
+        assert!(parsed.iter().all(|n| n.input_shifts() == input_shifts));
+
+        let (nnets, types): (Vec<TokenStream>, Vec<TokenStream>) =
+            parsed.iter().map(ParsedNNet::fixed_tokens).unzip();
+        (same_type(&types), nnets)
+    });
+
+    (nnet_type, nnets, fixed)
+}
+
 /// This will read all HCAS nnet files within the `nnet` folder and generate a TokenStream that contains all the parsed information in the NNet struct format.
 fn hcas_nnets() -> TokenStream {
     let pra_values = [0, 1, 2, 3, 4];
@@ -174,24 +502,43 @@ fn hcas_nnets() -> TokenStream {
         .iter()
         .flat_map(|pra| tau_values.iter().map(move |tau| format_name(pra, tau)));
 
-    let (parsed_nnets, parsed_nnet_types): (Vec<TokenStream>, Vec<TokenStream>) = required_nnets
+    let parsed: Vec<ParsedNNet> = required_nnets
         .map(|n| parse_nnet(PathBuf::from("nnets").join(n)))
-        .unzip();
-
-    // Our expectation is, that all nnet files withing the HCAS have the same type (as in
-    // dimensions). Thus the TokenStream describing their type must be equal. However, TokenStreams
-    // can not be compared. Therefore we render the TokenStreams into Strings, and compare the
-    // Strings.
-    let nnet_type = &parsed_nnet_types[0];
-    assert!(parsed_nnet_types
-        .iter()
-        .all(|n| n.to_string() == nnet_type.to_string()));
+        .collect();
+    let (nnet_type, parsed_nnets, fixed) = nnet_family(&parsed);
 
     let chunked_nnets = parsed_nnets.chunks(tau_values.len());
     let pra_value_count = pra_values.len();
     let tau_value_count = tau_values.len();
+    let tau_seconds = tau_values.iter().map(|tau| *tau as f32);
+
+    let fixed_tree = fixed.map(|(fixed_type, fixed_nnets)| {
+        let chunked_nnets = fixed_nnets.chunks(tau_value_count);
+        let tau_bits = tau_values.iter().map(|tau| tau << FRAC_BITS);
+
+        quote!(
+            /// Tau values of `HCAS_FIXED_NNETS`, in ascending order
+            pub const HCAS_FIXED_TAUS: [Fixed; #tau_value_count] =
+                [ #( Fixed::from_bits(#tau_bits) ),* ];
+
+            /// Fixed point versions of `HCAS_NNETS`
+            pub static HCAS_FIXED_NNETS: [ [ #fixed_type ; #tau_value_count ]; #pra_value_count ] =
+                [ #(
+                    [ #(
+                        #chunked_nnets
+                    ),* ]
+                ),* ];
+        )
+    });
 
     quote!(
+        /// Tau values the networks in `HCAS_NNETS` were trained for, in ascending order
+        pub const HCAS_TAU_SCHEDULE: TauSchedule<#tau_value_count> =
+            TauSchedule::new([ #( #tau_seconds ),* ]);
+
+        /// Type of the networks of the HorizontalCAS
+        pub type HCasNNet = #nnet_type;
+
         /// NNet structs of the HorizontalCAS
         pub static HCAS_NNETS: [ [ #nnet_type ; #tau_value_count ]; #pra_value_count ] =
             [ #(
@@ -199,6 +546,8 @@ fn hcas_nnets() -> TokenStream {
                     #chunked_nnets
                 ),* ]
             ),* ];
+
+        #fixed_tree
     )
 }
 
@@ -208,27 +557,158 @@ fn vcas_nnets() -> TokenStream {
     let format_name = |pra| format!("VertCAS_pra{pra:02}_v4_45HU_200.nnet");
     let required_nnets = pra_values.iter().map(format_name);
 
-    let (parsed_nnets, parsed_nnet_types): (Vec<TokenStream>, Vec<TokenStream>) = required_nnets
+    let parsed: Vec<ParsedNNet> = required_nnets
         .map(|n| parse_nnet(PathBuf::from("nnets").join(n)))
+        .collect();
+    let (nnet_type, parsed_nnets, fixed) = nnet_family(&parsed);
+
+    let pra_value_count = pra_values.len();
+
+    let fixed_tree = fixed.map(|(fixed_type, fixed_nnets)| {
+        quote!(
+            /// Fixed point versions of `VCAS_NNETS`
+            pub static VCAS_FIXED_NNETS: [ #fixed_type ; #pra_value_count ] =
+                [ #(
+                    #fixed_nnets
+                ),* ];
+        )
+    });
+
+    quote!(
+        /// Type of the networks of the VerticalCAS
+        pub type VCasNNet = #nnet_type;
+
+        /// NNet structs of the VerticalCAS
+        pub static VCAS_NNETS: [ #nnet_type ; #pra_value_count ] =
+            [ #(
+                #parsed_nnets
+            ),* ];
+
+        #fixed_tree
+    )
+}
+
+/// This will read all ACAS Xu nnet files within the `nnets/acasxu` folder and generate a
+/// TokenStream that contains all the parsed information in the NNet struct format.
+///
+/// The ACAS Xu networks are not distributed with this crate. If none of them are present in `dir`,
+/// `None` is returned and the ACAS Xu front-end is not compiled.
+fn acasxu_nnets(dir: &Path) -> Option<TokenStream> {
+    let pra_values = [1, 2, 3, 4, 5];
+    let tau_values = [0, 1, 5, 10, 20, 40, 60, 80, 100];
+    let format_name = |pra, tau_index| format!("ACASXU_run2a_{pra}_{tau_index}_batch_2000.nnet");
+    let required_nnets: Vec<PathBuf> = pra_values
+        .iter()
+        .flat_map(|pra| (1..=tau_values.len()).map(move |tau_index| format_name(pra, tau_index)))
+        .map(|n| dir.join(n))
+        .collect();
+
+    let missing: Vec<_> = required_nnets.iter().filter(|n| !n.is_file()).collect();
+    if missing.len() == required_nnets.len() {
+        return None;
+    }
+    assert!(
+        missing.is_empty(),
+        "incomplete set of ACAS Xu networks, missing {missing:#?}"
+    );
+
+    let (parsed_nnets, parsed_nnet_types): (Vec<TokenStream>, Vec<TokenStream>) = required_nnets
+        .iter()
+        .map(|n| parse_nnet(n).tokens())
         .unzip();
 
-    // Our expectation is, that all nnet files withing the HCAS have the same type (as in
-    // dimensions). Thus the TokenStream describing their type must be equal. However, TokenStreams
-    // can not be compared. Therefore we render the TokenStreams into Strings, and compare the
-    // Strings.
+    // same as for the HCAS, all networks must share one type
     let nnet_type = &parsed_nnet_types[0];
     assert!(parsed_nnet_types
         .iter()
         .all(|n| n.to_string() == nnet_type.to_string()));
 
+    let chunked_nnets = parsed_nnets.chunks(tau_values.len());
     let pra_value_count = pra_values.len();
+    let tau_value_count = tau_values.len();
+    let tau_seconds = tau_values.iter().map(|tau| *tau as f32);
 
-    quote!(
-        /// NNet structs of the VerticalCAS
-        pub const VCAS_NNETS: [ #nnet_type ; #pra_value_count ] =
+    Some(quote!(
+        /// Tau values the networks in `ACASXU_NNETS` were trained for, in ascending order
+        pub const ACASXU_TAU_SCHEDULE: TauSchedule<#tau_value_count> =
+            TauSchedule::new([ #( #tau_seconds ),* ]);
+
+        /// NNet structs of the ACAS Xu
+        pub static ACASXU_NNETS: [ [ #nnet_type ; #tau_value_count ]; #pra_value_count ] =
             [ #(
-                #parsed_nnets
+                [ #(
+                    #chunked_nnets
+                ),* ]
             ),* ];
+    ))
+}
+
+/// The name of the static generated from `file_name`, e.g. `TAPERED_EXAMPLE_NNET`
+///
+/// Every character which may not appear in an identifier is replaced by an underscore, and a
+/// leading digit is prefixed with `NNET_`.
+fn extra_nnet_name(file_name: &str) -> String {
+    let name = file_name
+        .to_uppercase()
+        .replace(|c: char| !c.is_ascii_alphanumeric(), "_");
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("NNET_{name}")
+    } else {
+        name
+    }
+}
+
+/// This will read all nnet and onnx files within the `nnets/extra` folder and generate a
+/// TokenStream with one `LayeredNNet` struct per file.
+///
+/// The networks are named after their files, e.g. `nnets/extra/tapered.onnx` becomes
+/// `TAPERED_ONNX`. Unlike for the CAS, the networks may have any dimensions.
+fn extra_nnets() -> TokenStream {
+    let dir = PathBuf::from("nnets").join("extra");
+    let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+        .map(|entries| entries.map(|entry| entry.unwrap().path()).collect())
+        .unwrap_or_default();
+    files.sort();
+
+    let mut names = std::collections::BTreeMap::new();
+    let nnets: Vec<TokenStream> = files
+        .iter()
+        .filter_map(|path| {
+            let parsed = match path.extension()?.to_str()? {
+                "nnet" => parse_nnet(path),
+                "onnx" => parse_onnx(path),
+                _ => return None,
+            };
+            let file_name = path.file_name()?.to_str()?;
+            let name = extra_nnet_name(file_name);
+
+            // two files may map to the same name, e.g. `a-b.nnet` and `a_b.nnet`
+            if let Some(other) = names.insert(name.clone(), file_name) {
+                let message = format!(
+                    "`nnets/extra/{other}` and `nnets/extra/{file_name}` would both be named {name}"
+                );
+                return Some(quote!(compile_error!(#message);));
+            }
+
+            let name = format_ident!("{}", name);
+            let doc = format!("Generated from `nnets/extra/{file_name}`");
+            let (nnet, nnet_type) = parsed.layered_tokens();
+
+            Some(quote!(
+                #[doc = #doc]
+                pub static #name: #nnet_type = #nnet;
+            ))
+        })
+        .collect();
+
+    quote!(
+        /// The networks generated from the `.nnet` and `.onnx` files in `nnets/extra` (see the
+        /// README there), whose layers may differ in width
+        pub mod extra {
+            use super::*;
+
+            #( #nnets )*
+        }
     )
 }
 
@@ -239,12 +719,38 @@ fn main() {
     //generate
     let hcas_tree = hcas_nnets();
     let vcas_tree = vcas_nnets();
+    let acasxu_dir = PathBuf::from("nnets").join("acasxu");
+    let acasxu_tree = acasxu_nnets(&acasxu_dir);
+    let extra_tree = extra_nnets();
+
+    // the ACAS Xu front-end is only available if its networks are present
+    println!("cargo:rustc-check-cfg=cfg(acasxu)");
+    if acasxu_tree.is_some() {
+        println!("cargo:rustc-cfg=acasxu");
+    }
+
+    // without them, the unit tests of the front-end run on a synthetic fixture
+    let acasxu_tree = acasxu_tree.unwrap_or_else(|| {
+        let fixture = acasxu_nnets(&acasxu_dir.join("fixture")).expect("missing ACAS Xu fixture");
+        quote!(
+            #[cfg(test)]
+            mod acasxu_fixture {
+                use super::*;
+
+                #fixture
+            }
+            #[cfg(test)]
+            pub use acasxu_fixture::*;
+        )
+    });
 
     let indent = format!(";\n{}", " ".repeat(20));
 
     let combined = quote!(
         #hcas_tree
         #vcas_tree
+        #acasxu_tree
+        #extra_tree
     );
 
     fs::write(&dest_path, combined.to_string().replace(';', &indent)).unwrap();
@@ -259,4 +765,5 @@ fn main() {
     }
 
     println!("cargo:rerun-if-changed=nnets");
+    println!("cargo:rerun-if-changed=src/onnx/model.rs");
 }
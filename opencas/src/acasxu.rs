@@ -0,0 +1,344 @@
+use core::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::foot;
+use uom::si::time::second;
+use uom::si::velocity::foot_per_second;
+
+use crate::error::{ensure_finite, ensure_non_negative};
+use crate::inference::Vector;
+use crate::{nnets, Advisory, AdvisoryScores, CasError, CasInput, ACASXU_TAU_SCHEDULE};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+
+/// This will store the last given advisory in order to locate the correct network in the
+/// evaluation.
+///
+/// All evaluation happens in the stateless functions of the [`crate::acasxu`] module, this struct
+/// only feeds the last advisory back into them.
+pub struct AcasXu {
+    pub last_advisory: AcasXuAdvisory,
+}
+
+/// AcasXuAdvisory stores all possible output evaluations.
+///
+/// The ACAS Xu networks output a cost per advisory, so the advisory with the lowest score wins.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AcasXuAdvisory {
+    #[default]
+    ClearOfConflict = 0,
+    WeakLeft = 1,
+    WeakRight = 2,
+    StrongLeft = 3,
+    StrongRight = 4,
+}
+
+impl TryFrom<u8> for AcasXuAdvisory {
+    type Error = CasError;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => Self::ClearOfConflict,
+            1 => Self::WeakLeft,
+            2 => Self::WeakRight,
+            3 => Self::StrongLeft,
+            4 => Self::StrongRight,
+            _ => return Err(CasError::InvalidAdvisory(v)),
+        })
+    }
+}
+
+impl Advisory for AcasXuAdvisory {
+    type Scores = [f32; 5];
+
+    const ALL: &'static [Self] = &[
+        Self::ClearOfConflict,
+        Self::WeakLeft,
+        Self::WeakRight,
+        Self::StrongLeft,
+        Self::StrongRight,
+    ];
+
+    const MINIMIZE: bool = true;
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Stateless version of [`AcasXu::process`]
+///
+/// The network is selected by `tau` and the explicitly given previous advisory `pra`.
+pub fn evaluate(
+    pra: AcasXuAdvisory,
+    tau: Time,
+    rho: Length,
+    theta: Angle,
+    psi: Angle,
+    speed_homeship: Velocity,
+    speed_intruder: Velocity,
+) -> AdvisoryScores<AcasXuAdvisory> {
+    // match the value of tau to the corresponding tau trained networks
+    let index = ACASXU_TAU_SCHEDULE.index(tau);
+
+    // find the correct network by using the last given advisory and the tau index
+    let nnet = &nnets::ACASXU_NNETS[pra as usize][index];
+
+    let inputs: Vector<5> = nalgebra::vector![
+        rho.get::<foot>(),
+        theta.get::<radian>(),
+        psi.get::<radian>(),
+        speed_homeship.get::<foot_per_second>(),
+        speed_intruder.get::<foot_per_second>()
+    ];
+
+    AdvisoryScores::new(nnet.eval(inputs).into())
+}
+
+/// Stateless version of [`AcasXu::try_process`]
+pub fn try_evaluate(
+    pra: AcasXuAdvisory,
+    tau: Time,
+    rho: Length,
+    theta: Angle,
+    psi: Angle,
+    speed_homeship: Velocity,
+    speed_intruder: Velocity,
+) -> Result<AdvisoryScores<AcasXuAdvisory>, CasError> {
+    ensure_non_negative(tau.get::<second>(), CasInput::Tau)?;
+    ensure_non_negative(rho.get::<foot>(), CasInput::Range)?;
+    ensure_finite(theta.get::<radian>(), CasInput::Theta)?;
+    ensure_finite(psi.get::<radian>(), CasInput::Psi)?;
+    ensure_non_negative(
+        speed_homeship.get::<foot_per_second>(),
+        CasInput::SpeedHomeship,
+    )?;
+    ensure_non_negative(
+        speed_intruder.get::<foot_per_second>(),
+        CasInput::SpeedIntruder,
+    )?;
+
+    let scores = evaluate(pra, tau, rho, theta, psi, speed_homeship, speed_intruder);
+    if !scores.is_finite() {
+        return Err(CasError::NonFiniteOutput);
+    }
+
+    Ok(scores)
+}
+
+impl AcasXu {
+    /// Creates a new ACAS Xu with the given last advisory
+    pub const fn new(last_advisory: AcasXuAdvisory) -> Self {
+        Self { last_advisory }
+    }
+
+    /// ACAS Xu consists of 45 different neural networks. The splitting parameters are:
+    ///
+    /// + time until loss of vertical separation: tau [sec]
+    /// + previous given advisory: pra [-]
+    ///
+    /// [`ACASXU_TAU_SCHEDULE`] tells which network a given tau selects.
+    ///
+    /// ACAS Xu needs five inputs:
+    /// + `rho` [ft]: absolute distance between homeship and intruder
+    /// + `theta` [rad]: angle from homeships heading to intruder (counterclockwise)
+    /// + `psi` [rad]: heading of intruder relative to the homeships heading (counterclockwise)
+    /// + `speed_homeship` [ft/s]
+    /// + `speed_intruder` [ft/s]
+    ///
+    /// See [Julian et al.](https://arxiv.org/abs/1810.04240) for details. The scores of all
+    /// advisories are returned. The advisory with the lowest cost is stored as the new
+    /// `last_advisory`.
+    pub fn process(
+        &mut self,
+        tau: Time,
+        rho: Length,
+        theta: Angle,
+        psi: Angle,
+        speed_homeship: Velocity,
+        speed_intruder: Velocity,
+    ) -> AdvisoryScores<AcasXuAdvisory> {
+        self.update(evaluate(
+            self.last_advisory,
+            tau,
+            rho,
+            theta,
+            psi,
+            speed_homeship,
+            speed_intruder,
+        ))
+    }
+
+    /// Same as [`AcasXu::process`], but rejects inputs the networks can not handle
+    ///
+    /// Every input must be finite, and neither `tau`, `rho` nor the speeds may be negative. A
+    /// `tau` beyond the largest trained tau is accepted and selects the networks trained for the
+    /// largest one, see [`crate::ACASXU_TAU_SCHEDULE`]. If an error is returned, `last_advisory`
+    /// is left untouched.
+    pub fn try_process(
+        &mut self,
+        tau: Time,
+        rho: Length,
+        theta: Angle,
+        psi: Angle,
+        speed_homeship: Velocity,
+        speed_intruder: Velocity,
+    ) -> Result<AdvisoryScores<AcasXuAdvisory>, CasError> {
+        try_evaluate(
+            self.last_advisory,
+            tau,
+            rho,
+            theta,
+            psi,
+            speed_homeship,
+            speed_intruder,
+        )
+        .and_then(|s| self.try_update(s))
+    }
+
+    /// Stores the advisory with the lowest cost and passes the scores on
+    fn update(&mut self, scores: AdvisoryScores<AcasXuAdvisory>) -> AdvisoryScores<AcasXuAdvisory> {
+        self.last_advisory = scores.advisory();
+        scores
+    }
+
+    /// Same as [`AcasXu::update`], without panicking
+    fn try_update(
+        &mut self,
+        scores: AdvisoryScores<AcasXuAdvisory>,
+    ) -> Result<AdvisoryScores<AcasXuAdvisory>, CasError> {
+        self.last_advisory = scores.try_advisory()?;
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn encounter() -> (Time, Length, Angle, Angle, Velocity, Velocity) {
+        (
+            Time::new::<second>(0.0),
+            Length::new::<foot>(8000.0),
+            Angle::new::<radian>(0.3),
+            Angle::new::<radian>(-2.8),
+            Velocity::new::<foot_per_second>(700.0),
+            Velocity::new::<foot_per_second>(600.0),
+        )
+    }
+
+    #[test]
+    pub fn stateless_matches_stateful() {
+        let (tau, rho, theta, psi, v_own, v_int) = encounter();
+
+        for &pra in AcasXuAdvisory::ALL {
+            let mut acasxu = AcasXu::new(pra);
+            let scores = evaluate(pra, tau, rho, theta, psi, v_own, v_int);
+
+            assert_eq!(acasxu.process(tau, rho, theta, psi, v_own, v_int), scores);
+            assert_eq!(acasxu.last_advisory, scores.advisory());
+            assert!(scores.iter().all(|(_, score)| score >= scores.best_score()));
+        }
+    }
+
+    /// The synthetic networks turn away from close intruders, see `nnets/acasxu/fixture`
+    #[cfg(not(acasxu))]
+    #[test]
+    pub fn fixture_turns_away() {
+        let (tau, _, _, psi, v_own, v_int) = encounter();
+        let advise = |rho: f32, theta: f32| {
+            evaluate(
+                AcasXuAdvisory::ClearOfConflict,
+                tau,
+                Length::new::<foot>(rho),
+                Angle::new::<radian>(theta),
+                psi,
+                v_own,
+                v_int,
+            )
+        };
+
+        assert_eq!(
+            advise(20000.0, 0.5).advisory(),
+            AcasXuAdvisory::ClearOfConflict
+        );
+
+        let scores = advise(6000.0, 0.5);
+        assert_eq!(scores.advisory(), AcasXuAdvisory::WeakRight);
+        assert!((scores.best_score() - 0.5).abs() < 1e-6);
+        assert!((scores.score(AcasXuAdvisory::ClearOfConflict) - 0.811).abs() < 1e-6);
+
+        let scores = advise(1000.0, -0.5);
+        assert_eq!(scores.advisory(), AcasXuAdvisory::StrongLeft);
+        assert!(scores.best_score().abs() < 1e-6);
+    }
+
+    /// Every synthetic network offsets clear of conflict by `0.001 * (10 * pra + tau)`, counting
+    /// both from one as in the file names
+    #[cfg(not(acasxu))]
+    #[test]
+    pub fn fixture_selects_network() {
+        let (_, _, theta, psi, v_own, v_int) = encounter();
+        let rho = Length::new::<foot>(20000.0);
+
+        for (p, &pra) in AcasXuAdvisory::ALL.iter().enumerate() {
+            for (t, tau) in [0.0, 1.0, 5.0, 10.0, 20.0, 40.0, 60.0, 80.0, 100.0]
+                .into_iter()
+                .enumerate()
+            {
+                let tau = Time::new::<second>(tau);
+                let scores = evaluate(pra, tau, rho, theta, psi, v_own, v_int);
+                let offset = 0.001 * (10 * (p + 1) + t + 1) as f32;
+                assert!(
+                    (scores.score(AcasXuAdvisory::ClearOfConflict) - offset).abs() < 1e-6,
+                    "wrong network for pra {pra:?} and tau {tau:?}"
+                );
+            }
+        }
+    }
+
+    /// Property 3 of [Katz et al.](https://arxiv.org/abs/1702.01135): an intruder straight ahead
+    /// and flying towards the homeship is never clear of conflict
+    #[cfg(acasxu)]
+    #[test]
+    pub fn head_on_intruder_alerts() {
+        let scores = evaluate(
+            AcasXuAdvisory::ClearOfConflict,
+            Time::new::<second>(0.0),
+            Length::new::<foot>(1650.0),
+            Angle::new::<radian>(0.0),
+            Angle::new::<radian>(3.12),
+            Velocity::new::<foot_per_second>(1000.0),
+            Velocity::new::<foot_per_second>(1000.0),
+        );
+        assert_ne!(scores.advisory(), AcasXuAdvisory::ClearOfConflict);
+    }
+
+    #[test]
+    pub fn reject_garbage_inputs() {
+        let (tau, rho, theta, psi, v_own, _) = encounter();
+        let mut acasxu = AcasXu::new(AcasXuAdvisory::StrongLeft);
+
+        assert_eq!(
+            acasxu.try_process(
+                tau,
+                rho,
+                theta,
+                psi,
+                v_own,
+                Velocity::new::<foot_per_second>(-1.0)
+            ),
+            Err(CasError::OutOfDomain(CasInput::SpeedIntruder))
+        );
+        assert_eq!(
+            acasxu.try_process(tau, Length::new::<foot>(f32::NAN), theta, psi, v_own, v_own),
+            Err(CasError::NonFiniteInput(CasInput::Range))
+        );
+        assert_eq!(acasxu.last_advisory, AcasXuAdvisory::StrongLeft);
+    }
+}
@@ -0,0 +1,747 @@
+use core::fmt;
+
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::angle::{degree, radian};
+use uom::si::f32::*;
+use uom::si::length::{foot, meter};
+use uom::si::time::second;
+use uom::si::velocity::{foot_per_minute, knot, meter_per_second};
+
+use crate::encounter::{AircraftState, Frame};
+use crate::geodesy::{Geodetic, LocalFrame};
+use crate::tracking::Report;
+
+/// Generator polynomial of the Mode S parity, including the leading bit
+const CRC24_GENERATOR: u32 = 0x1FF_F409;
+
+/// Number of latitude zones between equator and pole
+const NZ: f64 = 15.0;
+
+/// Maximum time between an even and an odd frame for global decoding, in seconds
+const MAX_PAIR_AGE: f32 = 10.0;
+
+/// Maximum age of the last position to be used as reference for local decoding, in seconds
+const MAX_REFERENCE_AGE: f32 = 30.0;
+
+/// Errors reported when parsing a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AdsbError {
+    /// The message contains a character which is not a hex digit
+    InvalidHex,
+
+    /// The message has the given number of bytes instead of 14
+    InvalidLength(usize),
+
+    /// The parity does not match, with the given remainder of the CRC
+    Checksum(u32),
+
+    /// The message has the given downlink format, which is not an extended squitter
+    UnsupportedFormat(u8),
+}
+
+impl fmt::Display for AdsbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidHex => write!(f, "message is not valid hex"),
+            Self::InvalidLength(n) => write!(f, "message has {n} bytes instead of 14"),
+            Self::Checksum(r) => write!(f, "parity mismatch with remainder {r:06X}"),
+            Self::UnsupportedFormat(df) => write!(f, "downlink format {df} is not supported"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AdsbError {}
+
+/// Computes the Mode S CRC-24 of `data`
+///
+/// The CRC over a complete message, including its parity, is zero for an undamaged message.
+pub fn crc24(data: &[u8]) -> u32 {
+    let mut crc = 0u32;
+    for &byte in data {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x100_0000 != 0 {
+                crc ^= CRC24_GENERATOR;
+            }
+        }
+    }
+    crc & 0xFF_FFFF
+}
+
+/// A decoded extended squitter, downlink format 17 or 18
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Message {
+    pub downlink_format: u8,
+
+    /// The 24 bit address of the sender
+    pub icao: u32,
+
+    pub payload: Payload,
+}
+
+impl Message {
+    /// Parses and checks the 14 bytes of a message
+    pub fn parse(bytes: &[u8]) -> Result<Self, AdsbError> {
+        let bytes: &[u8; 14] = bytes
+            .try_into()
+            .map_err(|_| AdsbError::InvalidLength(bytes.len()))?;
+
+        let downlink_format = bytes[0] >> 3;
+        if !matches!(downlink_format, 17 | 18) {
+            return Err(AdsbError::UnsupportedFormat(downlink_format));
+        }
+
+        match crc24(bytes) {
+            0 => {}
+            remainder => return Err(AdsbError::Checksum(remainder)),
+        }
+
+        let icao = u32::from_be_bytes([0, bytes[1], bytes[2], bytes[3]]);
+        let me = bytes[4..11]
+            .iter()
+            .fold(0u64, |me, &byte| (me << 8) | byte as u64);
+
+        Ok(Self {
+            downlink_format,
+            icao,
+            payload: Payload::decode(me),
+        })
+    }
+
+    /// Parses a message given as 28 hex digits
+    ///
+    /// The `*` and `;` delimiting raw messages in the AVR format are accepted as well.
+    pub fn from_hex(hex: &str) -> Result<Self, AdsbError> {
+        let hex = hex.trim();
+        let hex = hex.strip_prefix('*').unwrap_or(hex);
+        let hex = hex.strip_suffix(';').unwrap_or(hex).as_bytes();
+
+        let mut bytes = [0u8; 14];
+        if hex.len() != 2 * bytes.len() {
+            return Err(AdsbError::InvalidLength(hex.len() / 2));
+        }
+        for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+            *byte = (nibble(pair[0])? << 4) | nibble(pair[1])?;
+        }
+
+        Self::parse(&bytes)
+    }
+}
+
+fn nibble(digit: u8) -> Result<u8, AdsbError> {
+    (digit as char)
+        .to_digit(16)
+        .map(|d| d as u8)
+        .ok_or(AdsbError::InvalidHex)
+}
+
+/// Extracts `len` bits of the 56 bit message field, starting at the 1-based bit `start`
+fn bits(me: u64, start: u32, len: u32) -> u32 {
+    ((me >> (57 - start - len)) & ((1 << len) - 1)) as u32
+}
+
+/// The content of an extended squitter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Payload {
+    Identification(Identification),
+    AirbornePosition(AirbornePosition),
+    AirborneVelocity(AirborneVelocity),
+
+    /// A message with the given type code, which is not decoded
+    Other {
+        type_code: u8,
+    },
+}
+
+impl Payload {
+    fn decode(me: u64) -> Self {
+        let type_code = bits(me, 1, 5) as u8;
+        match type_code {
+            1..=4 => Self::Identification(Identification::decode(me)),
+            9..=18 | 20..=22 => Self::AirbornePosition(AirbornePosition::decode(me)),
+            19 => AirborneVelocity::decode(me)
+                .map(Self::AirborneVelocity)
+                .unwrap_or(Self::Other { type_code }),
+            _ => Self::Other { type_code },
+        }
+    }
+
+    /// The type code of the message
+    pub fn type_code(&self) -> u8 {
+        match self {
+            Self::Identification(id) => id.type_code,
+            Self::AirbornePosition(position) => position.type_code,
+            Self::AirborneVelocity(_) => 19,
+            Self::Other { type_code } => *type_code,
+        }
+    }
+}
+
+/// Character set of the callsign
+const CALLSIGN_CHARS: &[u8; 64] =
+    b"#ABCDEFGHIJKLMNOPQRSTUVWXYZ##### ###############0123456789######";
+
+/// Aircraft identification and category, type codes 1 to 4
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Identification {
+    pub type_code: u8,
+    pub category: u8,
+    callsign: [u8; 8],
+}
+
+impl Identification {
+    fn decode(me: u64) -> Self {
+        let mut callsign = [0; 8];
+        for (i, c) in callsign.iter_mut().enumerate() {
+            *c = CALLSIGN_CHARS[bits(me, 9 + 6 * i as u32, 6) as usize];
+        }
+
+        Self {
+            type_code: bits(me, 1, 5) as u8,
+            category: bits(me, 6, 3) as u8,
+            callsign,
+        }
+    }
+
+    /// The callsign, without trailing spaces
+    pub fn callsign(&self) -> &str {
+        // the character set is plain ASCII
+        core::str::from_utf8(&self.callsign)
+            .unwrap_or_default()
+            .trim_end()
+    }
+}
+
+/// Which of the two alternating CPR encodings a position uses
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CprFormat {
+    Even,
+    Odd,
+}
+
+/// Airborne position, type codes 9 to 18 with barometric and 20 to 22 with GNSS altitude
+///
+/// The position is encoded in the compact position reporting (CPR) format. It is decoded either
+/// globally from an even and an odd message, or locally relative to a nearby reference position.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AirbornePosition {
+    pub type_code: u8,
+
+    /// Altitude, `None` if unavailable or Gillham coded
+    pub altitude: Option<Length>,
+
+    pub format: CprFormat,
+
+    /// Encoded latitude, 17 bits
+    pub lat_cpr: u32,
+
+    /// Encoded longitude, 17 bits
+    pub lon_cpr: u32,
+}
+
+impl AirbornePosition {
+    fn decode(me: u64) -> Self {
+        let type_code = bits(me, 1, 5) as u8;
+        let alt = bits(me, 9, 12);
+
+        let altitude = match (type_code, alt) {
+            (_, 0) => None,
+            (20..=22, alt) => Some(Length::new::<meter>(alt as f32)),
+            // the Q bit selects 25 ft increments, Gillham coded altitudes are not supported
+            (_, alt) if alt & 0x10 != 0 => {
+                let n = ((alt & 0xFE0) >> 1) | (alt & 0xF);
+                Some(Length::new::<foot>(n as f32 * 25.0 - 1000.0))
+            }
+            _ => None,
+        };
+
+        Self {
+            type_code,
+            altitude,
+            format: if bits(me, 22, 1) == 0 {
+                CprFormat::Even
+            } else {
+                CprFormat::Odd
+            },
+            lat_cpr: bits(me, 23, 17),
+            lon_cpr: bits(me, 40, 17),
+        }
+    }
+
+    /// Encoded latitude and longitude as fractions of a zone
+    fn cpr(&self) -> (f64, f64) {
+        let scale = (1u32 << 17) as f64;
+        (self.lat_cpr as f64 / scale, self.lon_cpr as f64 / scale)
+    }
+
+    fn offset(&self) -> f64 {
+        match self.format {
+            CprFormat::Even => 0.0,
+            CprFormat::Odd => 1.0,
+        }
+    }
+
+    /// Decodes the position relative to `reference`, which must be within 180 NM
+    pub fn decode_local(&self, reference: Geodetic) -> Geodetic {
+        let (lat_cpr, lon_cpr) = self.cpr();
+        let (lat_ref, lon_ref) = (
+            reference.latitude.get::<degree>() as f64,
+            reference.longitude.get::<degree>() as f64,
+        );
+
+        let d_lat = 360.0 / (4.0 * NZ - self.offset());
+        let j =
+            (lat_ref / d_lat).floor() + (modulo(lat_ref, d_lat) / d_lat - lat_cpr + 0.5).floor();
+        let lat = d_lat * (j + lat_cpr);
+
+        let d_lon = 360.0 / (number_of_zones(lat) - self.offset()).max(1.0);
+        let m =
+            (lon_ref / d_lon).floor() + (modulo(lon_ref, d_lon) / d_lon - lon_cpr + 0.5).floor();
+        let lon = d_lon * (m + lon_cpr);
+
+        self.geodetic(lat, lon)
+    }
+
+    /// Decodes the position globally from `self` and `other`, which must be of the other format
+    ///
+    /// The position of `self` is returned, so it should be the newer message. Returns `None` if
+    /// both messages have the same format, or lie in different latitude zones.
+    pub fn decode_global(&self, other: &Self) -> Option<Geodetic> {
+        let (even, odd) = match (self.format, other.format) {
+            (CprFormat::Even, CprFormat::Odd) => (self, other),
+            (CprFormat::Odd, CprFormat::Even) => (other, self),
+            _ => return None,
+        };
+        let ((lat_even, lon_even), (lat_odd, lon_odd)) = (even.cpr(), odd.cpr());
+
+        let (d_lat_even, d_lat_odd) = (360.0 / (4.0 * NZ), 360.0 / (4.0 * NZ - 1.0));
+        let j = (59.0 * lat_even - 60.0 * lat_odd + 0.5).floor();
+        let southern = |lat: f64| if lat >= 270.0 { lat - 360.0 } else { lat };
+        let lat_even = southern(d_lat_even * (modulo(j, 60.0) + lat_even));
+        let lat_odd = southern(d_lat_odd * (modulo(j, 59.0) + lat_odd));
+
+        let nl = number_of_zones(lat_even);
+        if nl != number_of_zones(lat_odd) {
+            return None;
+        }
+
+        let (lat, lon_cpr) = match self.format {
+            CprFormat::Even => (lat_even, lon_even),
+            CprFormat::Odd => (lat_odd, lon_odd),
+        };
+        let ni = (nl - self.offset()).max(1.0);
+        let m = (lon_even * (nl - 1.0) - lon_odd * nl + 0.5).floor();
+        let lon = 360.0 / ni * (modulo(m, ni) + lon_cpr);
+
+        Some(self.geodetic(lat, lon))
+    }
+
+    fn geodetic(&self, lat: f64, lon: f64) -> Geodetic {
+        let lon = if lon >= 180.0 { lon - 360.0 } else { lon };
+        Geodetic::new(
+            Angle::new::<degree>(lat as f32),
+            Angle::new::<degree>(lon as f32),
+            self.altitude.unwrap_or_default(),
+        )
+    }
+}
+
+/// Modulo which is never negative for a positive `b`
+fn modulo(a: f64, b: f64) -> f64 {
+    a - b * (a / b).floor()
+}
+
+/// Number of longitude zones at `lat` in degrees
+fn number_of_zones(lat: f64) -> f64 {
+    let lat = lat.abs();
+    if lat == 0.0 {
+        return 59.0;
+    } else if lat == 87.0 {
+        return 2.0;
+    } else if lat > 87.0 {
+        return 1.0;
+    }
+
+    let pi = core::f64::consts::PI;
+    let a = 1.0 - (pi / (2.0 * NZ)).cos();
+    let b = (pi / 180.0 * lat).cos().powi(2);
+    (2.0 * pi / (1.0 - a / b).acos()).floor()
+}
+
+/// The horizontal part of an airborne velocity
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HorizontalVelocity {
+    /// Velocity over ground, subtypes 1 and 2
+    Ground { east: Velocity, north: Velocity },
+
+    /// Heading and airspeed, subtypes 3 and 4
+    Air {
+        heading: Option<Angle>,
+        airspeed: Option<Velocity>,
+        true_airspeed: bool,
+    },
+}
+
+/// Airborne velocity, type code 19
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AirborneVelocity {
+    /// `None` if the horizontal velocity is unavailable
+    pub horizontal: Option<HorizontalVelocity>,
+
+    /// Vertical rate, positive when climbing
+    pub vertical_rate: Option<Velocity>,
+}
+
+impl AirborneVelocity {
+    fn decode(me: u64) -> Option<Self> {
+        let subtype = bits(me, 6, 3);
+        let factor = match subtype {
+            1 | 3 => 1.0,
+            2 | 4 => 4.0,
+            _ => return None,
+        };
+
+        // a zero means unavailable, any other value is offset by one
+        let signed = |sign: u32, value: u32, unit: f32| {
+            (value != 0).then(|| {
+                let v = (value - 1) as f32 * unit;
+                if sign == 1 {
+                    -v
+                } else {
+                    v
+                }
+            })
+        };
+
+        let horizontal = if subtype <= 2 {
+            let east = signed(bits(me, 14, 1), bits(me, 15, 10), factor);
+            let north = signed(bits(me, 25, 1), bits(me, 26, 10), factor);
+            east.zip(north)
+                .map(|(east, north)| HorizontalVelocity::Ground {
+                    east: Velocity::new::<knot>(east),
+                    north: Velocity::new::<knot>(north),
+                })
+        } else {
+            let heading = (bits(me, 14, 1) == 1)
+                .then(|| Angle::new::<degree>(bits(me, 15, 10) as f32 * 360.0 / 1024.0));
+            let airspeed = signed(0, bits(me, 26, 10), factor).map(Velocity::new::<knot>);
+            Some(HorizontalVelocity::Air {
+                heading,
+                airspeed,
+                true_airspeed: bits(me, 25, 1) == 1,
+            })
+        };
+
+        Some(Self {
+            horizontal,
+            vertical_rate: signed(bits(me, 37, 1), bits(me, 38, 9), 64.0)
+                .map(Velocity::new::<foot_per_minute>),
+        })
+    }
+
+    /// Ground speed and track angle, clockwise from north
+    ///
+    /// Without a velocity over ground, the airspeed and heading are used instead, neglecting the
+    /// wind.
+    pub fn speed_and_track(&self) -> Option<(Velocity, Angle)> {
+        match self.horizontal? {
+            HorizontalVelocity::Ground { east, north } => {
+                let (east, north) = (east.get::<knot>(), north.get::<knot>());
+                let track = east.atan2(north).to_degrees();
+                Some((
+                    Velocity::new::<knot>(east.hypot(north)),
+                    Angle::new::<degree>(if track < 0.0 { track + 360.0 } else { track }),
+                ))
+            }
+            HorizontalVelocity::Air {
+                heading, airspeed, ..
+            } => Some((airspeed?, heading?)),
+        }
+    }
+}
+
+/// Collects the messages of one aircraft into its state
+///
+/// Positions are decoded globally from the first pair of even and odd messages received within
+/// 10 s, and locally relative to the last position afterwards. Altitudes are used as they are
+/// reported, so barometric altitudes must not be mixed with ellipsoid heights.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Target {
+    pub icao: u32,
+    pub identification: Option<Identification>,
+    even: Option<(Time, AirbornePosition)>,
+    odd: Option<(Time, AirbornePosition)>,
+    position: Option<(Time, Geodetic)>,
+    velocity: Option<(Time, AirborneVelocity)>,
+}
+
+impl Target {
+    /// Creates a new target without any message
+    pub const fn new(icao: u32) -> Self {
+        Self {
+            icao,
+            identification: None,
+            even: None,
+            odd: None,
+            position: None,
+            velocity: None,
+        }
+    }
+
+    /// Incorporates a message received at `time`, messages of other aircraft are ignored
+    pub fn update(&mut self, time: Time, message: &Message) {
+        if message.icao != self.icao {
+            return;
+        }
+
+        match message.payload {
+            Payload::Identification(id) => self.identification = Some(id),
+            Payload::AirborneVelocity(velocity) => self.velocity = Some((time, velocity)),
+            Payload::AirbornePosition(position) => self.update_position(time, position),
+            Payload::Other { .. } => {}
+        }
+    }
+
+    fn update_position(&mut self, time: Time, position: AirbornePosition) {
+        let max_reference_age = Time::new::<second>(MAX_REFERENCE_AGE);
+        let max_pair_age = Time::new::<second>(MAX_PAIR_AGE);
+
+        let other = match position.format {
+            CprFormat::Even => {
+                self.even = Some((time, position));
+                self.odd
+            }
+            CprFormat::Odd => {
+                self.odd = Some((time, position));
+                self.even
+            }
+        };
+
+        let decoded = match self.position {
+            Some((last, reference)) if time - last <= max_reference_age => {
+                Some(position.decode_local(reference))
+            }
+            _ => other
+                .filter(|(t, _)| time - *t <= max_pair_age)
+                .and_then(|(_, other)| position.decode_global(&other)),
+        };
+
+        if let Some(decoded) = decoded {
+            self.position = Some((time, decoded));
+        }
+    }
+
+    /// The last decoded position and the time it was received
+    pub fn position(&self) -> Option<(Time, Geodetic)> {
+        self.position
+    }
+
+    /// The last velocity and the time it was received
+    pub fn velocity(&self) -> Option<(Time, AirborneVelocity)> {
+        self.velocity
+    }
+
+    /// The last position as a report for a [`crate::tracking::Tracker`]
+    pub fn report(&self, local: &LocalFrame, frame: Frame) -> Option<Report> {
+        let (time, position) = self.position?;
+        Some(Report {
+            time,
+            position: local.position(frame, position),
+        })
+    }
+
+    /// The state of the aircraft in the given local frame, ready to be used in an
+    /// [`crate::encounter::Encounter`]
+    ///
+    /// Returns `None` until both a position and a velocity were received. The position is not
+    /// extrapolated to the time of the velocity.
+    pub fn aircraft_state(&self, local: &LocalFrame, frame: Frame) -> Option<AircraftState> {
+        let (_, position) = self.position?;
+        let (_, velocity) = self.velocity?;
+        let (speed, track) = velocity.speed_and_track()?;
+
+        let speed = speed.get::<meter_per_second>();
+        let track_rad = track.get::<radian>();
+        let (east, north) = (speed * track_rad.sin(), speed * track_rad.cos());
+        let up = velocity
+            .vertical_rate
+            .map(|v| v.get::<meter_per_second>())
+            .unwrap_or(0.0);
+
+        let velocity = match frame {
+            Frame::Enu => [east, north, up],
+            Frame::Ned => [north, east, -up],
+        };
+        Some(local.aircraft_state(
+            frame,
+            position,
+            velocity.map(Velocity::new::<meter_per_second>),
+            track,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const SAMPLES: &str = include_str!("../data/adsb_samples.txt");
+
+    /// Decodes all samples in the order they appear in the sample file
+    fn samples() -> Vec<Message> {
+        SAMPLES
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| Message::from_hex(line).unwrap())
+            .collect()
+    }
+
+    fn degrees(angle: Angle) -> f32 {
+        angle.get::<degree>()
+    }
+
+    fn s(seconds: f32) -> Time {
+        Time::new::<second>(seconds)
+    }
+
+    #[test]
+    pub fn crc_and_errors() {
+        let valid = "8D4840D6202CC371C32CE0576098";
+        assert!(Message::from_hex(valid).is_ok());
+        assert!(Message::from_hex("*8D4840D6202CC371C32CE0576098;").is_ok());
+
+        // a single flipped bit is detected
+        let flipped = "8D4840D6202CC371C32CE0576099";
+        assert!(matches!(
+            Message::from_hex(flipped),
+            Err(AdsbError::Checksum(_))
+        ));
+        assert_eq!(
+            Message::from_hex("8D4840D6202CC371C32CE05760"),
+            Err(AdsbError::InvalidLength(13))
+        );
+        assert_eq!(
+            Message::from_hex("8D4840D6202CC371C32CE057609G"),
+            Err(AdsbError::InvalidHex)
+        );
+        assert_eq!(
+            Message::from_hex("5D4840D6202CC371C32CE0576098"),
+            Err(AdsbError::UnsupportedFormat(11))
+        );
+    }
+
+    #[test]
+    pub fn identification() {
+        let message = samples()[0];
+        assert_eq!(message.icao, 0x4840D6);
+
+        let Payload::Identification(id) = message.payload else {
+            panic!("{message:?} is no identification");
+        };
+        assert_eq!(id.type_code, 4);
+        assert_eq!(id.callsign(), "KLM1023");
+    }
+
+    #[test]
+    pub fn cpr_global_and_local() {
+        let samples = samples();
+        let (Payload::AirbornePosition(even), Payload::AirbornePosition(odd)) =
+            (samples[1].payload, samples[2].payload)
+        else {
+            panic!("samples are no airborne positions");
+        };
+        assert_eq!((even.format, odd.format), (CprFormat::Even, CprFormat::Odd));
+        assert_eq!(even.altitude, Some(Length::new::<foot>(38_000.0)));
+
+        let global = even.decode_global(&odd).unwrap();
+        assert!((degrees(global.latitude) - 52.257_202).abs() < 1e-5);
+        assert!((degrees(global.longitude) - 3.919_373).abs() < 1e-5);
+        assert_eq!(even.decode_global(&even), None);
+
+        let reference = Geodetic::new(
+            Angle::new::<degree>(52.258),
+            Angle::new::<degree>(3.918),
+            Length::default(),
+        );
+        let local = even.decode_local(reference);
+        assert!((degrees(local.latitude) - 52.257_202).abs() < 1e-5);
+        assert!((degrees(local.longitude) - 3.919_373).abs() < 1e-5);
+    }
+
+    #[test]
+    pub fn velocities() {
+        let samples = samples();
+
+        let Payload::AirborneVelocity(ground) = samples[3].payload else {
+            panic!("sample is no airborne velocity");
+        };
+        let (speed, track) = ground.speed_and_track().unwrap();
+        assert!((speed.get::<knot>() - 159.20).abs() < 0.01);
+        assert!((degrees(track) - 182.88).abs() < 0.01);
+        assert_eq!(
+            ground.vertical_rate,
+            Some(Velocity::new::<foot_per_minute>(-832.0))
+        );
+
+        let Payload::AirborneVelocity(air) = samples[4].payload else {
+            panic!("sample is no airborne velocity");
+        };
+        let Some(HorizontalVelocity::Air {
+            heading: Some(heading),
+            airspeed: Some(airspeed),
+            true_airspeed: true,
+        }) = air.horizontal
+        else {
+            panic!("{air:?} has no true airspeed");
+        };
+        assert!((degrees(heading) - 243.98).abs() < 0.01);
+        assert_eq!(airspeed, Velocity::new::<knot>(375.0));
+        assert_eq!(
+            air.vertical_rate,
+            Some(Velocity::new::<foot_per_minute>(-2304.0))
+        );
+    }
+
+    #[test]
+    pub fn target_collects_state() {
+        let samples = samples();
+        let mut target = Target::new(0x40621D);
+
+        // the other aircraft and a single frame do not give a position
+        target.update(s(0.0), &samples[0]);
+        target.update(s(0.0), &samples[2]);
+        assert_eq!(target.position(), None);
+
+        // the even frame is newer, so its position is taken
+        target.update(s(2.0), &samples[1]);
+        let (time, position) = target.position().unwrap();
+        assert_eq!(time, s(2.0));
+        assert!((degrees(position.latitude) - 52.257_202).abs() < 1e-5);
+
+        // a velocity of another aircraft, rewritten to this one
+        let velocity = Message {
+            icao: 0x40621D,
+            ..samples[3]
+        };
+        target.update(s(3.0), &velocity);
+
+        let local = LocalFrame::new(Geodetic::new(
+            Angle::new::<degree>(52.2),
+            Angle::new::<degree>(3.9),
+            Length::new::<foot>(37_000.0),
+        ));
+        let state = target.aircraft_state(&local, Frame::Enu).unwrap();
+        let [east, north, up] = state.position.map(|p| p.get::<meter>());
+        assert!(east > 0.0 && north > 6000.0);
+        assert!((up - 304.8).abs() < 10.0);
+        assert!((degrees(state.heading) - 182.88).abs() < 0.01);
+        assert!(state.velocity[1].get::<knot>() < -150.0);
+
+        let report = target.report(&local, Frame::Enu).unwrap();
+        assert_eq!(report.position, state.position);
+    }
+}
@@ -0,0 +1,632 @@
+use core::fmt;
+use core::ops::{Add, Div, Mul, Sub};
+
+use crate::inference::{Layer, NNet};
+
+/// A closed interval of `f32` values
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub lower: f32,
+    pub upper: f32,
+}
+
+impl Interval {
+    /// Creates the interval `lower..=upper`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `lower > upper` or either bound is NaN.
+    pub fn new(lower: f32, upper: f32) -> Self {
+        assert!(lower <= upper, "invalid interval {lower}..={upper}");
+        Self { lower, upper }
+    }
+
+    /// The interval holding just `value`
+    pub const fn point(value: f32) -> Self {
+        Self {
+            lower: value,
+            upper: value,
+        }
+    }
+
+    pub fn width(&self) -> f32 {
+        self.upper - self.lower
+    }
+
+    pub fn contains(&self, value: f32) -> bool {
+        self.lower <= value && value <= self.upper
+    }
+
+    /// Whether `other` lies completely within `self`
+    pub fn encloses(&self, other: &Self) -> bool {
+        self.lower <= other.lower && other.upper <= self.upper
+    }
+
+    /// The intersection of both intervals, `None` if they are disjoint
+    pub fn intersect(&self, other: &Self) -> Option<Self> {
+        let (lower, upper) = (self.lower.max(other.lower), self.upper.min(other.upper));
+        (lower <= upper).then_some(Self { lower, upper })
+    }
+
+    /// Both bounds clamped to `min..=max`
+    pub fn clamp(self, min: f32, max: f32) -> Self {
+        Self {
+            lower: self.lower.max(min).min(max),
+            upper: self.upper.max(min).min(max),
+        }
+    }
+
+    /// The image under the ReLU, `max(0, x)`
+    pub fn relu(self) -> Self {
+        Self {
+            lower: self.lower.max(0.0),
+            upper: self.upper.max(0.0),
+        }
+    }
+}
+
+/// Outward rounded sum
+impl Add for Interval {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            lower: next_down(self.lower + other.lower),
+            upper: next_up(self.upper + other.upper),
+        }
+    }
+}
+
+/// Outward rounded difference
+impl Sub for Interval {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        self + Self {
+            lower: -other.upper,
+            upper: -other.lower,
+        }
+    }
+}
+
+/// Outward rounded product
+impl Mul for Interval {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            self.lower * other.lower,
+            self.lower * other.upper,
+            self.upper * other.lower,
+            self.upper * other.upper,
+        ];
+        Self {
+            lower: next_down(products.into_iter().fold(f32::INFINITY, f32::min)),
+            upper: next_up(products.into_iter().fold(f32::NEG_INFINITY, f32::max)),
+        }
+    }
+}
+
+/// Outward rounded product with a scalar
+impl Mul<f32> for Interval {
+    type Output = Self;
+
+    fn mul(self, factor: f32) -> Self {
+        self * Self::point(factor)
+    }
+}
+
+/// Outward rounded quotient by a scalar, which must not be zero
+impl Div<f32> for Interval {
+    type Output = Self;
+
+    fn div(self, divisor: f32) -> Self {
+        let (a, b) = (self.lower / divisor, self.upper / divisor);
+        Self {
+            lower: next_down(a.min(b)),
+            upper: next_up(a.max(b)),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}, {}]", self.lower, self.upper)
+    }
+}
+
+/// The next larger `f32`, used to round upper bounds outward
+pub(crate) fn next_up(x: f32) -> f32 {
+    if x.is_nan() || x == f32::INFINITY {
+        x
+    } else if x == 0.0 {
+        f32::from_bits(1)
+    } else if x > 0.0 {
+        f32::from_bits(x.to_bits() + 1)
+    } else {
+        f32::from_bits(x.to_bits() - 1)
+    }
+}
+
+/// The next smaller `f32`, used to round lower bounds outward
+pub(crate) fn next_down(x: f32) -> f32 {
+    -next_up(-x)
+}
+
+/// How the bounds are propagated through the layers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// Interval bound propagation: every neuron is bounded by an interval, computed from the
+    /// intervals of the previous layer. Fast, but the bounds loosen quickly with depth.
+    Interval,
+
+    /// Symbolic linear bounds: every neuron is bounded from below and above by a linear function
+    /// of the normalized inputs. ReLUs which may be active or inactive are relaxed linearly, with
+    /// the upper bound being the line through the corners of the relaxation and the lower bound
+    /// either zero or the identity, whichever is closer (as in CROWN). The concrete bounds of
+    /// every neuron are intersected with the ones from interval bound propagation.
+    #[default]
+    Symbolic,
+}
+
+/// Sound bounds of every output of `nnet`, for all inputs within `inputs`
+///
+/// `inputs` are given in the same units as for [`NNet::eval`], and are clamped and normalized
+/// the same way.
+pub fn output_bounds<
+    const N_INPUT: usize,
+    const N_MAT: usize,
+    const N_NEURON: usize,
+    const N_OUTPUT: usize,
+>(
+    nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+    inputs: &[Interval; N_INPUT],
+    method: Method,
+) -> [Interval; N_OUTPUT] {
+    propagate(nnet, inputs, method).outputs()
+}
+
+/// Propagates the box `inputs` through `nnet`, see [`Bounds`]
+///
+/// `inputs` are given in the same units as for [`NNet::eval`], and are clamped and normalized
+/// the same way.
+pub fn propagate<
+    const N_INPUT: usize,
+    const N_MAT: usize,
+    const N_NEURON: usize,
+    const N_OUTPUT: usize,
+>(
+    nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+    inputs: &[Interval; N_INPUT],
+    method: Method,
+) -> Bounds<N_INPUT, N_OUTPUT> {
+    let normalized = normalize(nnet, inputs);
+
+    let (linear, outputs) = match method {
+        Method::Interval => {
+            let mut bounds = affine_intervals(&nnet.input_layer, &normalized).map(Interval::relu);
+            for layer in &nnet.hidden_layers {
+                bounds = affine_intervals(layer, &bounds).map(Interval::relu);
+            }
+            (None, affine_intervals(&nnet.output_layer, &bounds))
+        }
+        Method::Symbolic => {
+            let identity = core::array::from_fn(Linear::input);
+            let (mut lower, mut upper, mut bounds) = affine_symbolic(
+                &nnet.input_layer,
+                &identity,
+                &identity,
+                &normalized,
+                &normalized,
+            );
+            relu_symbolic(&mut lower, &mut upper, &mut bounds);
+
+            for layer in &nnet.hidden_layers {
+                (lower, upper, bounds) =
+                    affine_symbolic(layer, &lower, &upper, &bounds, &normalized);
+                relu_symbolic(&mut lower, &mut upper, &mut bounds);
+            }
+            let (lower, upper, outputs) =
+                affine_symbolic(&nnet.output_layer, &lower, &upper, &bounds, &normalized);
+            (Some((lower, upper)), outputs)
+        }
+    };
+
+    Bounds {
+        normalized,
+        linear,
+        outputs,
+        mean_output: nnet.mean_output,
+        range_output: nnet.range_output,
+    }
+}
+
+/// The result of propagating a box of inputs through a network
+///
+/// Besides the bounds of every output, this allows to bound the difference of two outputs. With
+/// [`Method::Symbolic`], this is usually much tighter than the difference of their bounds, as
+/// the dependencies of both outputs on the inputs cancel out.
+#[derive(Debug, Clone)]
+pub struct Bounds<const N_INPUT: usize, const N_OUTPUT: usize> {
+    /// The box of normalized inputs
+    normalized: [Interval; N_INPUT],
+    /// Lower and upper linear bounds of the normalized outputs, for [`Method::Symbolic`]
+    linear: Option<([Linear<N_INPUT>; N_OUTPUT], [Linear<N_INPUT>; N_OUTPUT])>,
+    /// Concrete bounds of the normalized outputs
+    outputs: [Interval; N_OUTPUT],
+    mean_output: f32,
+    range_output: f32,
+}
+
+impl<const N_INPUT: usize, const N_OUTPUT: usize> Bounds<N_INPUT, N_OUTPUT> {
+    /// Bounds of output `i`
+    pub fn output(&self, i: usize) -> Interval {
+        self.outputs[i] * self.range_output + Interval::point(self.mean_output)
+    }
+
+    /// Bounds of every output
+    pub fn outputs(&self) -> [Interval; N_OUTPUT] {
+        core::array::from_fn(|i| self.output(i))
+    }
+
+    /// Bounds of output `a` minus output `b`
+    pub fn difference(&self, a: usize, b: usize) -> Interval {
+        let intervals = self.outputs[a] - self.outputs[b];
+        let normalized = match &self.linear {
+            Some((lower, upper)) => {
+                let symbolic = Interval {
+                    lower: lower[a].sub(&upper[b]).concretize(&self.normalized).lower,
+                    upper: upper[a].sub(&lower[b]).concretize(&self.normalized).upper,
+                };
+                // both are sound, so they always overlap
+                symbolic.intersect(&intervals).unwrap_or(intervals)
+            }
+            None => intervals,
+        };
+
+        // the mean cancels out
+        normalized * self.range_output
+    }
+
+    /// Bounds of the sum of every output weighted by `coefficients`
+    ///
+    /// This generalizes [`Bounds::difference`], with the same advantage for [`Method::Symbolic`].
+    pub fn combination(&self, coefficients: &[f32; N_OUTPUT]) -> Interval {
+        let intervals = self
+            .outputs
+            .iter()
+            .zip(coefficients)
+            .fold(Interval::point(0.0), |sum, (o, c)| sum + *o * *c);
+        let normalized = match &self.linear {
+            Some((lower, upper)) => {
+                let (mut l, mut u) = (Linear::ZERO, Linear::ZERO);
+                for (i, &c) in coefficients.iter().enumerate() {
+                    // a negative coefficient swaps the bounds, like a negative weight
+                    let (low, high) = if c >= 0.0 {
+                        (&lower[i], &upper[i])
+                    } else {
+                        (&upper[i], &lower[i])
+                    };
+                    l = l.add(&low.scale(c));
+                    u = u.add(&high.scale(c));
+                }
+                let symbolic = Interval {
+                    lower: l.concretize(&self.normalized).lower,
+                    upper: u.concretize(&self.normalized).upper,
+                };
+                symbolic.intersect(&intervals).unwrap_or(intervals)
+            }
+            None => intervals,
+        };
+
+        let weight = coefficients
+            .iter()
+            .fold(Interval::point(0.0), |sum, c| sum + Interval::point(*c));
+        normalized * self.range_output + weight * self.mean_output
+    }
+}
+
+/// Clamps and normalizes the input box, like `NNet::normalize`
+fn normalize<
+    const N_INPUT: usize,
+    const N_MAT: usize,
+    const N_NEURON: usize,
+    const N_OUTPUT: usize,
+>(
+    nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+    inputs: &[Interval; N_INPUT],
+) -> [Interval; N_INPUT] {
+    core::array::from_fn(|i| {
+        (inputs[i].clamp(nnet.min_input[i], nnet.max_input[i])
+            - Interval::point(nnet.mean_value[i]))
+            / nnet.range[i]
+    })
+}
+
+/// Bounds of `a * x + biases` for all `x` within `inputs`
+fn affine_intervals<const I: usize, const O: usize>(
+    layer: &Layer<I, O>,
+    inputs: &[Interval; I],
+) -> [Interval; O] {
+    core::array::from_fn(|row| {
+        inputs
+            .iter()
+            .enumerate()
+            .fold(Interval::point(layer.biases[row]), |sum, (col, x)| {
+                sum + *x * layer.a[(row, col)]
+            })
+    })
+}
+
+/// A linear function of the `N` normalized inputs, with interval coefficients to absorb the
+/// rounding errors
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Linear<const N: usize> {
+    coefficients: [Interval; N],
+    constant: Interval,
+}
+
+impl<const N: usize> Linear<N> {
+    const ZERO: Self = Self {
+        coefficients: [Interval::point(0.0); N],
+        constant: Interval::point(0.0),
+    };
+
+    /// The function returning input `i`
+    fn input(i: usize) -> Self {
+        let mut linear = Self::ZERO;
+        linear.coefficients[i] = Interval::point(1.0);
+        linear
+    }
+
+    fn add(&self, other: &Self) -> Self {
+        Self {
+            coefficients: core::array::from_fn(|i| self.coefficients[i] + other.coefficients[i]),
+            constant: self.constant + other.constant,
+        }
+    }
+
+    fn sub(&self, other: &Self) -> Self {
+        self.add(&other.scale(-1.0))
+    }
+
+    fn scale(&self, factor: f32) -> Self {
+        Self {
+            coefficients: self.coefficients.map(|c| c * factor),
+            constant: self.constant * factor,
+        }
+    }
+
+    /// The range of the function over the box `inputs`
+    fn concretize(&self, inputs: &[Interval; N]) -> Interval {
+        self.coefficients
+            .iter()
+            .zip(inputs)
+            .fold(self.constant, |sum, (c, x)| sum + *c * *x)
+    }
+}
+
+/// Symbolic bounds of `a * x + biases`, with `x` being bounded by `lower` and `upper` from below
+/// and above and by `bounds` concretely
+///
+/// Returns the lower and upper linear bounds of every output, and their concrete bounds
+/// intersected with the result of interval bound propagation.
+#[allow(clippy::type_complexity)]
+fn affine_symbolic<const I: usize, const O: usize, const N: usize>(
+    layer: &Layer<I, O>,
+    lower: &[Linear<N>; I],
+    upper: &[Linear<N>; I],
+    bounds: &[Interval; I],
+    inputs: &[Interval; N],
+) -> ([Linear<N>; O], [Linear<N>; O], [Interval; O]) {
+    let mut output_lower = [Linear::ZERO; O];
+    let mut output_upper = [Linear::ZERO; O];
+
+    for row in 0..O {
+        let bias = Interval::point(layer.biases[row]);
+        let (mut l, mut u) = (Linear::ZERO, Linear::ZERO);
+        (l.constant, u.constant) = (bias, bias);
+
+        for col in 0..I {
+            let weight = layer.a[(row, col)];
+            // a positive weight keeps the direction of the bounds, a negative one swaps them
+            let (low, high) = if weight >= 0.0 {
+                (&lower[col], &upper[col])
+            } else {
+                (&upper[col], &lower[col])
+            };
+            l = l.add(&low.scale(weight));
+            u = u.add(&high.scale(weight));
+        }
+
+        output_lower[row] = l;
+        output_upper[row] = u;
+    }
+
+    let intervals = affine_intervals(layer, bounds);
+    let concrete = core::array::from_fn(|row| {
+        let symbolic = Interval {
+            lower: output_lower[row].concretize(inputs).lower,
+            upper: output_upper[row].concretize(inputs).upper,
+        };
+        // both are sound, so they always overlap
+        symbolic
+            .intersect(&intervals[row])
+            .unwrap_or(intervals[row])
+    });
+
+    (output_lower, output_upper, concrete)
+}
+
+/// Applies the linear relaxation of the ReLU to the symbolic and concrete bounds of a layer
+fn relu_symbolic<const O: usize, const N: usize>(
+    lower: &mut [Linear<N>; O],
+    upper: &mut [Linear<N>; O],
+    bounds: &mut [Interval; O],
+) {
+    for ((lower, upper), bounds) in lower
+        .iter_mut()
+        .zip(upper.iter_mut())
+        .zip(bounds.iter_mut())
+    {
+        let Interval { lower: l, upper: u } = *bounds;
+
+        if u <= 0.0 {
+            // always inactive
+            *lower = Linear::ZERO;
+            *upper = Linear::ZERO;
+        } else if l < 0.0 {
+            // relu(x) <= u / (u - l) * (x - l), the slope is rounded up to stay above
+            let slope = next_up(u / (Interval::point(u) - Interval::point(l)).lower);
+            let mut shifted = *upper;
+            shifted.constant = shifted.constant - Interval::point(l);
+            *upper = shifted.scale(slope);
+
+            // relu(x) >= x or relu(x) >= 0, whichever loses less area
+            if u <= -l {
+                *lower = Linear::ZERO;
+            }
+        }
+
+        *bounds = bounds.relu();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::hcas::{self, HAdvisory};
+    use crate::vcas::{self, VAdvisory};
+    use nalgebra::{matrix, vector};
+    use uom::si::f32::Time;
+    use uom::si::time::second;
+
+    #[test]
+    pub fn outward_rounding() {
+        let third = Interval::point(1.0) / 3.0;
+        assert!(third.lower < third.upper);
+        assert!(third.lower as f64 <= 1.0 / 3.0 && 1.0 / 3.0 <= third.upper as f64);
+
+        let sum = Interval::point(0.1) + Interval::point(0.2);
+        assert!(sum.lower as f64 <= 0.1f32 as f64 + 0.2f32 as f64);
+        assert!(sum.upper as f64 >= 0.1f32 as f64 + 0.2f32 as f64);
+
+        assert_eq!(next_up(-0.0), f32::from_bits(1));
+        assert_eq!(next_down(0.0), -f32::from_bits(1));
+        assert_eq!(next_up(f32::MAX), f32::INFINITY);
+        assert_eq!(next_up(-f32::from_bits(1)), -0.0);
+    }
+
+    /// y = relu(x0 + x1) - relu(x0 - x1), whose range over [-1, 1]^2 is exactly [-2, 2]
+    #[test]
+    pub fn symbolic_beats_intervals() {
+        let nnet = NNet {
+            input_layer: Layer {
+                a: matrix![
+                    1.0, 1.0;
+                    1.0, -1.0
+                ],
+                biases: vector![0.0, 0.0],
+            },
+            hidden_layers: [],
+            output_layer: Layer {
+                a: matrix![1.0, -1.0],
+                biases: vector![0.0],
+            },
+            min_input: vector![-10.0, -10.0],
+            max_input: vector![10.0, 10.0],
+            mean_value: vector![0.0, 0.0],
+            range: vector![1.0, 1.0],
+            mean_output: 0.0,
+            range_output: 1.0,
+        };
+        let inputs = [Interval::new(-1.0, 1.0); 2];
+
+        let [intervals] = output_bounds(&nnet, &inputs, Method::Interval);
+        let [symbolic] = output_bounds(&nnet, &inputs, Method::Symbolic);
+        assert!(intervals.encloses(&Interval::new(-2.0, 2.0)));
+        assert!(symbolic.encloses(&Interval::new(-2.0, 2.0)));
+        assert!(intervals.encloses(&symbolic));
+
+        // the dependencies on the inputs cancel out in the difference of an output with itself
+        let naive = propagate(&nnet, &inputs, Method::Interval).difference(0, 0);
+        let difference = propagate(&nnet, &inputs, Method::Symbolic).difference(0, 0);
+        assert!(difference.contains(0.0));
+        assert!(difference.width() < naive.width());
+    }
+
+    /// Checks the bounds against a grid of network evaluations within the box
+    fn check_sampled<const I: usize, const M: usize, const N: usize, const O: usize>(
+        nnet: &NNet<I, M, N, O>,
+        inputs: [Interval; I],
+    ) {
+        let intervals = output_bounds(nnet, &inputs, Method::Interval);
+        let symbolic = output_bounds(nnet, &inputs, Method::Symbolic);
+        for (i, s) in intervals.iter().zip(&symbolic) {
+            assert!(i.encloses(s), "{s} is looser than {i}");
+        }
+        let differences = propagate(nnet, &inputs, Method::Symbolic);
+
+        let steps: usize = 5;
+        for sample in 0..steps.pow(I as u32) {
+            let point = core::array::from_fn(|i| {
+                let step = (sample / steps.pow(i as u32)) % steps;
+                inputs[i].lower + inputs[i].width() * step as f32 / (steps - 1) as f32
+            });
+            let outputs = nnet.eval(point.into());
+            for (bounds, output) in symbolic.iter().zip(outputs.iter()) {
+                assert!(
+                    bounds.contains(*output),
+                    "{output} at {point:?} not in {bounds}"
+                );
+            }
+            for a in 0..O {
+                let difference = differences.difference(a, 0);
+                assert!(
+                    difference.contains(outputs[a] - outputs[0]),
+                    "difference {a} at {point:?} not in {difference}"
+                );
+            }
+            let coefficients = core::array::from_fn(|i| i as f32 - 1.5);
+            let combination = differences.combination(&coefficients);
+            let sum: f32 = outputs.iter().zip(&coefficients).map(|(o, c)| o * c).sum();
+            assert!(
+                combination.contains(sum),
+                "combination at {point:?} not in {combination}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn bounds_contain_samples() {
+        let hcas = hcas::network(HAdvisory::ClearOfConflict, Time::new::<second>(20.0));
+        check_sampled(
+            hcas,
+            [
+                Interval::new(4000.0, 5000.0),
+                Interval::new(-500.0, 500.0),
+                Interval::new(-3.1, -2.9),
+            ],
+        );
+        // partially outside of the clamped input range
+        check_sampled(
+            hcas,
+            [
+                Interval::new(50e3, 70e3),
+                Interval::new(-1e3, 1e3),
+                Interval::new(0.0, 0.1),
+            ],
+        );
+
+        let vcas = vcas::network(VAdvisory::DoNotClimb);
+        check_sampled(
+            vcas,
+            [
+                Interval::new(-200.0, 0.0),
+                Interval::new(-10.0, 10.0),
+                Interval::new(20.0, 30.0),
+                Interval::new(14.0, 16.0),
+            ],
+        );
+    }
+}
@@ -0,0 +1,367 @@
+use uom::si::f32::*;
+
+use crate::multi::{HIntruder, VIntruder};
+use crate::nnets::{HCAS_NNETS, VCAS_NNETS};
+use crate::{Advisory, AdvisoryScores, CasError, HAdvisory, HCas, VAdvisory, VCas};
+
+/// Decides which maneuver is flown if both the HorizontalCAS and the VerticalCAS alert
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum Policy {
+    /// Always fly the horizontal advisory
+    Horizontal,
+
+    /// Always fly the vertical advisory
+    Vertical,
+
+    /// Fly both advisories at once
+    Both,
+
+    /// Fly the vertical advisory at or above the given homeship altitude, and the horizontal one
+    /// below it
+    VerticalAbove(Length),
+
+    /// Fly the advisory which improves the most on clear of conflict
+    ///
+    /// The networks de-normalise their outputs with different ranges, so the margin of each
+    /// advisory over clear of conflict is divided by the output range of its network first.
+    #[default]
+    LowestCost,
+}
+
+/// The maneuver to be flown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Maneuver {
+    /// Neither system alerts
+    None,
+    Horizontal,
+    Vertical,
+    Both,
+}
+
+impl Maneuver {
+    /// Whether the horizontal advisory is to be flown
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Self::Horizontal | Self::Both)
+    }
+
+    /// Whether the vertical advisory is to be flown
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Self::Vertical | Self::Both)
+    }
+}
+
+/// Why a [`Maneuver`] was chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reason {
+    /// Both systems advise clear of conflict
+    ClearOfConflict,
+
+    /// Only one of the systems alerts, so its advisory is flown
+    SingleAlert,
+
+    /// Both systems alert, and the policy always picks the chosen maneuver
+    Policy,
+
+    /// Both systems alert, and the homeship is at or above the threshold altitude
+    AboveThreshold,
+
+    /// Both systems alert, and the homeship is below the threshold altitude
+    BelowThreshold,
+
+    /// Both systems alert, and the chosen advisory improves more on clear of conflict
+    LowerCost,
+}
+
+/// The coordinated result of both systems
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CombinedAdvisory {
+    pub horizontal: AdvisoryScores<HAdvisory>,
+    pub vertical: AdvisoryScores<VAdvisory>,
+    pub maneuver: Maneuver,
+    pub reason: Reason,
+}
+
+/// Runs the HorizontalCAS and the VerticalCAS for the same encounter, and picks one coordinated
+/// resolution according to `policy`
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct CombinedCas {
+    pub hcas: HCas,
+    pub vcas: VCas,
+    pub policy: Policy,
+}
+
+impl CombinedCas {
+    /// Creates a new CombinedCAS, with both systems starting clear of conflict
+    pub const fn new(policy: Policy) -> Self {
+        Self {
+            hcas: HCas::new(HAdvisory::ClearOfConflict),
+            vcas: VCas::new(VAdvisory::ClearOfConflict),
+            policy,
+        }
+    }
+
+    /// Evaluates both systems and picks the maneuver
+    ///
+    /// `altitude` is the altitude of the homeship, which is only used by
+    /// [`Policy::VerticalAbove`]. Both systems keep their own `last_advisory`, regardless of the
+    /// chosen maneuver.
+    pub fn process(
+        &mut self,
+        altitude: Length,
+        horizontal: HIntruder,
+        vertical_speed_homeship: Velocity,
+        vertical: VIntruder,
+    ) -> CombinedAdvisory {
+        let h = self.hcas.process_cartesian(
+            horizontal.tau,
+            horizontal.forward_range,
+            horizontal.left_range,
+            horizontal.psi,
+        );
+        let v = self.vcas.process(
+            vertical.height,
+            vertical_speed_homeship,
+            vertical.vertical_speed_intruder,
+            vertical.tau,
+        );
+
+        decide(self.policy, altitude, h, v)
+    }
+
+    /// Same as [`CombinedCas::process`], but rejects inputs the networks can not handle
+    ///
+    /// If an error is returned, the `last_advisory` of both systems is left untouched.
+    pub fn try_process(
+        &mut self,
+        altitude: Length,
+        horizontal: HIntruder,
+        vertical_speed_homeship: Velocity,
+        vertical: VIntruder,
+    ) -> Result<CombinedAdvisory, CasError> {
+        let (mut hcas, mut vcas) = (self.hcas, self.vcas);
+        let h = hcas.try_process_cartesian(
+            horizontal.tau,
+            horizontal.forward_range,
+            horizontal.left_range,
+            horizontal.psi,
+        )?;
+        let v = vcas.try_process(
+            vertical.height,
+            vertical_speed_homeship,
+            vertical.vertical_speed_intruder,
+            vertical.tau,
+        )?;
+
+        (self.hcas, self.vcas) = (hcas, vcas);
+        Ok(decide(self.policy, altitude, h, v))
+    }
+}
+
+/// Picks the maneuver for the given scores of both systems
+fn decide(
+    policy: Policy,
+    altitude: Length,
+    horizontal: AdvisoryScores<HAdvisory>,
+    vertical: AdvisoryScores<VAdvisory>,
+) -> CombinedAdvisory {
+    let h_alert = horizontal.advisory() != HAdvisory::ClearOfConflict;
+    let v_alert = vertical.advisory() != VAdvisory::ClearOfConflict;
+
+    let (maneuver, reason) = match (h_alert, v_alert) {
+        (false, false) => (Maneuver::None, Reason::ClearOfConflict),
+        (true, false) => (Maneuver::Horizontal, Reason::SingleAlert),
+        (false, true) => (Maneuver::Vertical, Reason::SingleAlert),
+        (true, true) => match policy {
+            Policy::Horizontal => (Maneuver::Horizontal, Reason::Policy),
+            Policy::Vertical => (Maneuver::Vertical, Reason::Policy),
+            Policy::Both => (Maneuver::Both, Reason::Policy),
+            Policy::VerticalAbove(threshold) if altitude >= threshold => {
+                (Maneuver::Vertical, Reason::AboveThreshold)
+            }
+            Policy::VerticalAbove(_) => (Maneuver::Horizontal, Reason::BelowThreshold),
+            Policy::LowestCost
+                if normalised_gain(
+                    &vertical,
+                    VAdvisory::ClearOfConflict,
+                    VCAS_NNETS[0].range_output,
+                ) > normalised_gain(
+                    &horizontal,
+                    HAdvisory::ClearOfConflict,
+                    HCAS_NNETS[0][0].range_output,
+                ) =>
+            {
+                (Maneuver::Vertical, Reason::LowerCost)
+            }
+            Policy::LowestCost => (Maneuver::Horizontal, Reason::LowerCost),
+        },
+    };
+
+    CombinedAdvisory {
+        horizontal,
+        vertical,
+        maneuver,
+        reason,
+    }
+}
+
+/// Margin of the best advisory over clear of conflict, in units of the normalised network output
+///
+/// All networks of one CAS share their output normalisation, so `range_output` of any of them
+/// will do.
+fn normalised_gain<A: Advisory>(
+    scores: &AdvisoryScores<A>,
+    clear_of_conflict: A,
+    range_output: f32,
+) -> f32 {
+    let gain = (scores.best_score() - scores.score(clear_of_conflict)) / range_output;
+    if A::MINIMIZE {
+        -gain
+    } else {
+        gain
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hcas, vcas};
+    use uom::si::angle::radian;
+    use uom::si::length::foot;
+    use uom::si::time::second;
+    use uom::si::velocity::foot_per_minute;
+
+    fn scores(h_best: usize, h: f32, v_best: usize, v: f32) -> CombinedAdvisory {
+        let mut h_scores = [0.0; 5];
+        h_scores[h_best] = h;
+        let mut v_scores = [0.0; 9];
+        v_scores[v_best] = v;
+
+        CombinedAdvisory {
+            horizontal: AdvisoryScores::new(h_scores),
+            vertical: AdvisoryScores::new(v_scores),
+            maneuver: Maneuver::None,
+            reason: Reason::ClearOfConflict,
+        }
+    }
+
+    fn decision(policy: Policy, altitude: f32, s: CombinedAdvisory) -> (Maneuver, Reason) {
+        let d = decide(
+            policy,
+            Length::new::<foot>(altitude),
+            s.horizontal,
+            s.vertical,
+        );
+        (d.maneuver, d.reason)
+    }
+
+    #[test]
+    pub fn single_or_no_alert_ignores_policy() {
+        for policy in [Policy::Horizontal, Policy::Vertical, Policy::Both] {
+            assert_eq!(
+                decision(policy, 0.0, scores(0, 1.0, 0, 1.0)),
+                (Maneuver::None, Reason::ClearOfConflict)
+            );
+            assert_eq!(
+                decision(policy, 0.0, scores(3, 1.0, 0, 1.0)),
+                (Maneuver::Horizontal, Reason::SingleAlert)
+            );
+            assert_eq!(
+                decision(policy, 0.0, scores(0, 1.0, 4, 1.0)),
+                (Maneuver::Vertical, Reason::SingleAlert)
+            );
+        }
+    }
+
+    #[test]
+    pub fn policy_resolves_double_alert() {
+        let both = scores(1, 2.0, 6, 3.0);
+
+        assert_eq!(
+            decision(Policy::Both, 0.0, both),
+            (Maneuver::Both, Reason::Policy)
+        );
+        let threshold = Policy::VerticalAbove(Length::new::<foot>(1e3));
+        assert_eq!(
+            decision(threshold, 1e3, both),
+            (Maneuver::Vertical, Reason::AboveThreshold)
+        );
+        assert_eq!(
+            decision(threshold, 999.0, both),
+            (Maneuver::Horizontal, Reason::BelowThreshold)
+        );
+        assert_eq!(
+            decision(Policy::LowestCost, 0.0, both),
+            (Maneuver::Vertical, Reason::LowerCost)
+        );
+        assert_eq!(
+            decision(Policy::LowestCost, 0.0, scores(1, 20.0, 6, 3.0)),
+            (Maneuver::Horizontal, Reason::LowerCost)
+        );
+    }
+
+    #[test]
+    pub fn lowest_cost_compares_normalised_scores() {
+        let (h_range, v_range) = (HCAS_NNETS[0][0].range_output, VCAS_NNETS[0].range_output);
+        assert!(h_range > 5.0 * v_range);
+
+        // the raw horizontal score is higher, but relative to its output range the vertical
+        // advisory improves far more on clear of conflict
+        let s = scores(3, 5.0, 4, 3.0);
+        assert!(s.horizontal.best_score() > s.vertical.best_score());
+        assert!(3.0 / v_range > 5.0 / h_range);
+        assert_eq!(
+            decision(Policy::LowestCost, 0.0, s),
+            (Maneuver::Vertical, Reason::LowerCost)
+        );
+
+        // the gain is measured from clear of conflict, not from zero
+        let mut s = scores(3, 5.0, 4, 3.0);
+        s.vertical = AdvisoryScores::new([2.9, 0.0, 0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0]);
+        assert_eq!(
+            decision(Policy::LowestCost, 0.0, s),
+            (Maneuver::Horizontal, Reason::LowerCost)
+        );
+    }
+
+    #[test]
+    pub fn runs_both_systems() {
+        let horizontal = HIntruder {
+            tau: Time::new::<second>(15.0),
+            forward_range: Length::new::<foot>(3e3),
+            left_range: Length::new::<foot>(200.0),
+            psi: Angle::new::<radian>(3.0),
+        };
+        let vertical = VIntruder {
+            height: Length::new::<foot>(100.0),
+            vertical_speed_intruder: Velocity::new::<foot_per_minute>(-500.0),
+            tau: Time::new::<second>(15.0),
+        };
+        let vs_own = Velocity::new::<foot_per_minute>(0.0);
+
+        let mut cas = CombinedCas::new(Policy::LowestCost);
+        let combined = cas.process(Length::new::<foot>(5e3), horizontal, vs_own, vertical);
+
+        assert_eq!(
+            combined.horizontal,
+            hcas::evaluate(
+                HAdvisory::ClearOfConflict,
+                horizontal.tau,
+                horizontal.forward_range,
+                horizontal.left_range,
+                horizontal.psi
+            )
+        );
+        assert_eq!(
+            combined.vertical,
+            vcas::evaluate(
+                VAdvisory::ClearOfConflict,
+                vertical.height,
+                vs_own,
+                vertical.vertical_speed_intruder,
+                vertical.tau
+            )
+        );
+        assert_eq!(cas.hcas.last_advisory, combined.horizontal.advisory());
+        assert_eq!(cas.vcas.last_advisory, combined.vertical.advisory());
+    }
+}
@@ -0,0 +1,385 @@
+#[allow(unused_imports)]
+use num::Float;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::meter;
+use uom::si::velocity::meter_per_second;
+
+use crate::multi::{HIntruder, VIntruder};
+use crate::{tau, AdvisoryScores, HAdvisory, HCas, VAdvisory, VCas};
+
+/// The local frame positions and velocities are given in
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Frame {
+    /// East, north, up
+    #[default]
+    Enu,
+
+    /// North, east, down
+    Ned,
+}
+
+/// Position, velocity and heading of one aircraft
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AircraftState {
+    /// Position, ordered like the axes of the [`Frame`]
+    pub position: [Length; 3],
+
+    /// Velocity, ordered like the axes of the [`Frame`]
+    pub velocity: [Velocity; 3],
+
+    /// Heading, clockwise from north
+    pub heading: Angle,
+}
+
+impl AircraftState {
+    /// Position as east, north, up in meters
+    fn enu_position(&self, frame: Frame) -> [f32; 3] {
+        to_enu(frame, self.position.map(|p| p.get::<meter>()))
+    }
+
+    /// Velocity as east, north, up in meters per second
+    fn enu_velocity(&self, frame: Frame) -> [f32; 3] {
+        to_enu(frame, self.velocity.map(|v| v.get::<meter_per_second>()))
+    }
+}
+
+/// Reorders a vector from `frame` to east, north, up
+fn to_enu(frame: Frame, v: [f32; 3]) -> [f32; 3] {
+    match frame {
+        Frame::Enu => v,
+        Frame::Ned => [v[1], v[0], -v[2]],
+    }
+}
+
+/// An encounter of the homeship with one intruder
+///
+/// This derives the inputs of the HorizontalCAS and VerticalCAS with the sign conventions the
+/// networks were trained with:
+///
+/// + `forward_range` is positive in front of the homeship
+/// + `left_range` is positive to the left of the homeship
+/// + `psi` is the heading of the intruder relative to the homeships heading, counterclockwise
+/// + `height` is the altitude of the intruder minus the altitude of the homeship
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Encounter {
+    pub frame: Frame,
+    pub ownship: AircraftState,
+    pub intruder: AircraftState,
+}
+
+impl Encounter {
+    /// Creates a new encounter in the given frame
+    pub const fn new(frame: Frame, ownship: AircraftState, intruder: AircraftState) -> Self {
+        Self {
+            frame,
+            ownship,
+            intruder,
+        }
+    }
+
+    /// Relative position of the intruder as east, north, up in meters
+    fn relative_position(&self) -> [f32; 3] {
+        let (own, int) = (
+            self.ownship.enu_position(self.frame),
+            self.intruder.enu_position(self.frame),
+        );
+        [int[0] - own[0], int[1] - own[1], int[2] - own[2]]
+    }
+
+    /// Relative velocity of the intruder as east, north, up in meters per second
+    fn relative_velocity(&self) -> [f32; 3] {
+        let (own, int) = (
+            self.ownship.enu_velocity(self.frame),
+            self.intruder.enu_velocity(self.frame),
+        );
+        [int[0] - own[0], int[1] - own[1], int[2] - own[2]]
+    }
+
+    /// Distance to the intruder along the homeships heading
+    pub fn forward_range(&self) -> Length {
+        let [east, north, _] = self.relative_position();
+        let heading = self.ownship.heading.get::<radian>();
+        Length::new::<meter>(east * heading.sin() + north * heading.cos())
+    }
+
+    /// Distance to the intruder left of the homeships heading
+    pub fn left_range(&self) -> Length {
+        let [east, north, _] = self.relative_position();
+        let heading = self.ownship.heading.get::<radian>();
+        Length::new::<meter>(-east * heading.cos() + north * heading.sin())
+    }
+
+    /// Horizontal distance between homeship and intruder
+    pub fn range(&self) -> Length {
+        let [east, north, _] = self.relative_position();
+        Length::new::<meter>(east.hypot(north))
+    }
+
+    /// Angle from the homeships heading to the intruder, counterclockwise in `-pi..=pi`
+    pub fn theta(&self) -> Angle {
+        let (forward, left) = (self.forward_range(), self.left_range());
+        Angle::new::<radian>(left.get::<meter>().atan2(forward.get::<meter>()))
+    }
+
+    /// Heading of the intruder relative to the homeships heading, counterclockwise in
+    /// `-pi..=pi`
+    pub fn psi(&self) -> Angle {
+        let psi = self.ownship.heading.get::<radian>() - self.intruder.heading.get::<radian>();
+        Angle::new::<radian>(wrap(psi))
+    }
+
+    /// Altitude of the intruder relative to the homeship
+    pub fn height(&self) -> Length {
+        Length::new::<meter>(self.relative_position()[2])
+    }
+
+    /// Vertical speed of the homeship, positive when climbing
+    pub fn vertical_speed_homeship(&self) -> Velocity {
+        Velocity::new::<meter_per_second>(self.ownship.enu_velocity(self.frame)[2])
+    }
+
+    /// Vertical speed of the intruder, positive when climbing
+    pub fn vertical_speed_intruder(&self) -> Velocity {
+        Velocity::new::<meter_per_second>(self.intruder.enu_velocity(self.frame)[2])
+    }
+
+    /// Rate of change of the horizontal range, negative when closing in
+    pub fn range_rate(&self) -> Velocity {
+        let [east, north, _] = self.relative_position();
+        let [v_east, v_north, _] = self.relative_velocity();
+
+        // the projection of the relative velocity onto the line of sight
+        let range = east.hypot(north);
+        let rate = if range == 0.0 {
+            0.0
+        } else {
+            (east * v_east + north * v_north) / range
+        };
+        Velocity::new::<meter_per_second>(rate)
+    }
+
+    /// Time until the horizontal range closes to zero, from the current closure rate
+    ///
+    /// This is [`tau::modified_tau`] without a DMOD. Returns `None` if the intruder is not closing
+    /// in.
+    pub fn horizontal_tau(&self) -> Option<Time> {
+        self.modified_tau(Length::new::<meter>(0.0))
+    }
+
+    /// Modified tau with the given `dmod`, see [`tau::modified_tau`]
+    pub fn modified_tau(&self, dmod: Length) -> Option<Time> {
+        tau::modified_tau(self.range(), self.range_rate(), dmod)
+    }
+
+    /// Time until the closest point of approach, see [`tau::time_to_cpa`]
+    pub fn time_to_cpa(&self) -> Option<Time> {
+        let [east, north, _] = self.relative_position();
+        let [v_east, v_north, _] = self.relative_velocity();
+
+        tau::time_to_cpa(
+            [east, north].map(Length::new::<meter>),
+            [v_east, v_north].map(Velocity::new::<meter_per_second>),
+        )
+    }
+
+    /// Time until homeship and intruder are at the same altitude, see
+    /// [`tau::time_to_co_altitude`]
+    pub fn vertical_tau(&self) -> Option<Time> {
+        tau::time_to_co_altitude(
+            self.height(),
+            Velocity::new::<meter_per_second>(self.relative_velocity()[2]),
+        )
+    }
+
+    /// Inputs for [`crate::HCas::process_cartesian`], with the vertical tau
+    ///
+    /// Returns `None` if the altitudes are not converging, as then there is no threat.
+    pub fn hcas_inputs(&self) -> Option<HIntruder> {
+        Some(HIntruder {
+            tau: self.vertical_tau()?,
+            forward_range: self.forward_range(),
+            left_range: self.left_range(),
+            psi: self.psi(),
+        })
+    }
+
+    /// Inputs for [`crate::VCas::process`], with the horizontal tau
+    ///
+    /// The vertical speed of the homeship is given by
+    /// [`Encounter::vertical_speed_homeship`]. Returns `None` if the intruder is not closing in
+    /// horizontally, as then there is no threat.
+    pub fn vcas_inputs(&self) -> Option<VIntruder> {
+        Some(VIntruder {
+            height: self.height(),
+            vertical_speed_intruder: self.vertical_speed_intruder(),
+            tau: self.horizontal_tau()?,
+        })
+    }
+}
+
+/// The HorizontalCAS and the VerticalCAS, running for one intruder
+///
+/// A system without a threat, see [`Encounter::hcas_inputs`] and [`Encounter::vcas_inputs`], is
+/// not evaluated and falls back to clear of conflict.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IntruderCas {
+    pub hcas: HCas,
+    pub vcas: VCas,
+}
+
+impl IntruderCas {
+    /// Evaluates both systems on `encounter` and stores the issued advisories
+    ///
+    /// The scores of a system are `None` if the encounter is no threat to it.
+    pub fn evaluate(
+        &mut self,
+        encounter: &Encounter,
+    ) -> (
+        Option<AdvisoryScores<HAdvisory>>,
+        Option<AdvisoryScores<VAdvisory>>,
+    ) {
+        let horizontal = encounter.hcas_inputs().map(|h| {
+            self.hcas
+                .process_cartesian(h.tau, h.forward_range, h.left_range, h.psi)
+        });
+        let vertical = encounter.vcas_inputs().map(|v| {
+            self.vcas.process(
+                v.height,
+                encounter.vertical_speed_homeship(),
+                v.vertical_speed_intruder,
+                v.tau,
+            )
+        });
+
+        self.hcas.last_advisory = horizontal.map(|s| s.advisory()).unwrap_or_default();
+        self.vcas.last_advisory = vertical.map(|s| s.advisory()).unwrap_or_default();
+        (horizontal, vertical)
+    }
+
+    /// Evaluates both systems on the encounter of `ownship` with `intruder` and returns the
+    /// issued advisories
+    pub fn step(
+        &mut self,
+        frame: Frame,
+        ownship: AircraftState,
+        intruder: AircraftState,
+    ) -> (HAdvisory, VAdvisory) {
+        self.evaluate(&Encounter::new(frame, ownship, intruder));
+        (self.hcas.last_advisory, self.vcas.last_advisory)
+    }
+}
+
+/// Wraps an angle in radians into `-pi..=pi`
+fn wrap(angle: f32) -> f32 {
+    angle.sin().atan2(angle.cos())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uom::si::angle::degree;
+    use uom::si::length::foot;
+    use uom::si::time::second;
+    use uom::si::velocity::foot_per_minute;
+
+    fn state(position: [f32; 3], velocity: [f32; 3], heading: f32) -> AircraftState {
+        AircraftState {
+            position: position.map(Length::new::<meter>),
+            velocity: velocity.map(Velocity::new::<meter_per_second>),
+            heading: Angle::new::<degree>(heading),
+        }
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3 * (1.0 + b.abs())
+    }
+
+    #[test]
+    pub fn intruder_left_and_crossing() {
+        // homeship heading north, intruder 1 km ahead and 500 m west, flying west
+        let encounter = Encounter::new(
+            Frame::Enu,
+            state([0.0; 3], [0.0, 100.0, 0.0], 0.0),
+            state([-500.0, 1000.0, 30.0], [-100.0, 0.0, -2.0], 270.0),
+        );
+
+        assert!(close(encounter.forward_range().get::<meter>(), 1000.0));
+        assert!(close(encounter.left_range().get::<meter>(), 500.0));
+        assert!(close(encounter.psi().get::<degree>(), 90.0));
+        assert!(encounter.theta().get::<degree>() > 0.0);
+        assert!(close(encounter.height().get::<meter>(), 30.0));
+        assert!(close(
+            encounter.vertical_tau().unwrap().get::<second>(),
+            15.0
+        ));
+        assert!(close(
+            encounter.vertical_speed_intruder().get::<foot_per_minute>(),
+            -2.0 / 0.00508
+        ));
+    }
+
+    #[test]
+    pub fn ned_matches_enu() {
+        let enu = Encounter::new(
+            Frame::Enu,
+            state([10.0, 20.0, 1000.0], [50.0, 50.0, 1.0], 45.0),
+            state([2010.0, -980.0, 900.0], [-80.0, 20.0, 3.0], 285.0),
+        );
+        let swap = |s: AircraftState| AircraftState {
+            position: [s.position[1], s.position[0], -s.position[2]],
+            velocity: [s.velocity[1], s.velocity[0], -s.velocity[2]],
+            heading: s.heading,
+        };
+        let ned = Encounter::new(Frame::Ned, swap(enu.ownship), swap(enu.intruder));
+
+        assert_eq!(enu.hcas_inputs(), ned.hcas_inputs());
+        assert_eq!(enu.vcas_inputs(), ned.vcas_inputs());
+        assert_eq!(enu.vertical_speed_homeship(), ned.vertical_speed_homeship());
+        assert!(close(enu.psi().get::<degree>(), 120.0));
+    }
+
+    #[test]
+    pub fn tau_from_closure_rate() {
+        // head on, 6 km apart, closing with 200 m/s
+        let head_on = Encounter::new(
+            Frame::Enu,
+            state([0.0; 3], [100.0, 0.0, 0.0], 90.0),
+            state([6000.0, 0.0, 0.0], [-100.0, 0.0, 0.0], 270.0),
+        );
+        assert!(close(
+            head_on.horizontal_tau().unwrap().get::<second>(),
+            30.0
+        ));
+        assert!(close(head_on.time_to_cpa().unwrap().get::<second>(), 30.0));
+        assert!(close(
+            head_on
+                .modified_tau(Length::new::<meter>(1200.0))
+                .unwrap()
+                .get::<second>(),
+            28.8
+        ));
+        assert!(close(
+            head_on.forward_range().get::<foot>(),
+            6000.0 / 0.3048
+        ));
+        assert!(close(head_on.left_range().get::<meter>(), 0.0));
+        assert!(close(head_on.psi().get::<degree>().abs(), 180.0));
+        assert_eq!(head_on.vertical_tau(), Some(Time::new::<second>(0.0)));
+
+        // diverging intruders are no threat
+        let diverging = Encounter::new(
+            Frame::Enu,
+            state([0.0, 0.0, 0.0], [-100.0, 0.0, 1.0], 270.0),
+            state([6000.0, 0.0, 100.0], [100.0, 0.0, 0.0], 90.0),
+        );
+        assert_eq!(diverging.horizontal_tau(), None);
+        assert_eq!(diverging.vcas_inputs(), None);
+        assert!(diverging.hcas_inputs().is_some());
+    }
+}
@@ -0,0 +1,116 @@
+use core::fmt;
+
+/// Names the inputs of the CAS front-ends, so that an error can tell which one was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CasInput {
+    /// Time until loss of separation
+    Tau,
+    /// Absolute distance between homeship and intruder
+    Range,
+    /// Angle from homeships heading to intruder
+    Theta,
+    /// Distance to the intruder along the homeships heading
+    ForwardRange,
+    /// Distance to the intruder left of the homeships heading
+    LeftRange,
+    /// Heading of the intruder relative to the homeships heading
+    Psi,
+    /// Relative intruder altitude
+    Height,
+    /// Vertical speed of the homeship
+    VerticalSpeedHomeship,
+    /// Vertical speed of the intruder
+    VerticalSpeedIntruder,
+    /// Ground speed of the homeship
+    SpeedHomeship,
+    /// Ground speed of the intruder
+    SpeedIntruder,
+}
+
+impl fmt::Display for CasInput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Tau => "tau",
+            Self::Range => "range",
+            Self::Theta => "theta",
+            Self::ForwardRange => "forward range",
+            Self::LeftRange => "left range",
+            Self::Psi => "psi",
+            Self::Height => "height",
+            Self::VerticalSpeedHomeship => "vertical speed of homeship",
+            Self::VerticalSpeedIntruder => "vertical speed of intruder",
+            Self::SpeedHomeship => "speed of homeship",
+            Self::SpeedIntruder => "speed of intruder",
+        })
+    }
+}
+
+/// Errors reported by the fallible `try_*` functions of this crate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CasError {
+    /// The given number does not correspond to any advisory
+    InvalidAdvisory(u8),
+
+    /// The given input is NaN or infinite
+    NonFiniteInput(CasInput),
+
+    /// The given input is finite, but outside of the domain the networks are defined on
+    ///
+    /// Inputs beyond the range the networks were trained on, like a large tau, are not rejected.
+    /// The networks clamp them to the trained range, or the network trained for the nearest tau
+    /// is selected.
+    OutOfDomain(CasInput),
+
+    /// The network evaluated to a NaN or infinite score
+    NonFiniteOutput,
+
+    /// More intruders were given than the front-end was sized for
+    TooManyIntruders {
+        /// Maximum number of intruders
+        max: usize,
+        /// Number of intruders given
+        found: usize,
+    },
+
+    /// No intruder was given, so there is nothing to resolve
+    NoIntruder,
+}
+
+impl fmt::Display for CasError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidAdvisory(v) => write!(f, "{v} does not correspond to any advisory"),
+            Self::NonFiniteInput(input) => write!(f, "{input} is not a finite number"),
+            Self::OutOfDomain(input) => write!(f, "{input} is out of the networks domain"),
+            Self::NonFiniteOutput => write!(f, "network evaluated to a non-finite score"),
+            Self::TooManyIntruders { max, found } => {
+                write!(
+                    f,
+                    "{found} intruders given, but at most {max} are supported"
+                )
+            }
+            Self::NoIntruder => write!(f, "no intruder given"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for CasError {}
+
+/// Checks that `value` is neither NaN nor infinite
+pub(crate) fn ensure_finite(value: f32, input: CasInput) -> Result<f32, CasError> {
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(CasError::NonFiniteInput(input))
+    }
+}
+
+/// Checks that `value` is finite and not negative
+pub(crate) fn ensure_non_negative(value: f32, input: CasInput) -> Result<f32, CasError> {
+    if ensure_finite(value, input)? >= 0.0 {
+        Ok(value)
+    } else {
+        Err(CasError::OutOfDomain(input))
+    }
+}
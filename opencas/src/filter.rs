@@ -0,0 +1,364 @@
+use uom::si::f32::*;
+
+use crate::{hcas, vcas, Advisory, AdvisoryScores, HAdvisory, VAdvisory};
+
+/// The direction an advisory commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Sense {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Advisories that command a sense with a certain strength
+pub trait Directional: Advisory {
+    /// The sense of the advisory, or `None` for clear of conflict
+    fn sense(self) -> Option<Sense>;
+
+    /// The strength of the advisory, with zero being clear of conflict
+    fn strength(self) -> u8;
+}
+
+impl Directional for HAdvisory {
+    fn sense(self) -> Option<Sense> {
+        match self {
+            Self::ClearOfConflict => None,
+            Self::WeakLeft | Self::StrongLeft => Some(Sense::Left),
+            Self::WeakRight | Self::StrongRight => Some(Sense::Right),
+        }
+    }
+
+    fn strength(self) -> u8 {
+        match self {
+            Self::ClearOfConflict => 0,
+            Self::WeakLeft | Self::WeakRight => 1,
+            Self::StrongLeft | Self::StrongRight => 2,
+        }
+    }
+}
+
+impl Directional for VAdvisory {
+    fn sense(self) -> Option<Sense> {
+        match self {
+            Self::ClearOfConflict => None,
+            Self::DoNotDescend
+            | Self::Climb1500
+            | Self::StrengthenClimb1500
+            | Self::StrengthenClimb2500 => Some(Sense::Up),
+            Self::DoNotClimb
+            | Self::Descend1500
+            | Self::StrengthenDescend1500
+            | Self::StrengthenDescend2500 => Some(Sense::Down),
+        }
+    }
+
+    fn strength(self) -> u8 {
+        match self {
+            Self::ClearOfConflict => 0,
+            Self::DoNotClimb | Self::DoNotDescend => 1,
+            Self::Descend1500 | Self::Climb1500 => 2,
+            Self::StrengthenDescend1500 | Self::StrengthenClimb1500 => 3,
+            Self::StrengthenDescend2500 | Self::StrengthenClimb2500 => 4,
+        }
+    }
+}
+
+/// Parameters of an [`AdvisoryFilter`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilterConfig {
+    /// Minimum time an advisory is held before it may weaken or clear
+    pub min_duration: Time,
+
+    /// Maximum number of sense reversals until the encounter is clear of conflict again, `None`
+    /// allows any number of reversals
+    pub max_reversals: Option<u8>,
+
+    /// While tau is at or below this value, an advisory may only be kept or strengthened, `None`
+    /// disables this mode
+    pub strengthen_only_tau: Option<Time>,
+}
+
+/// Filters the advisories chosen by a network, to keep them from chattering between senses
+///
+/// The filter is driven by explicit timestamps, so it is fully deterministic. Of all advisories
+/// the filter permits, the one with the best score is chosen. The current advisory is always
+/// permitted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdvisoryFilter<A: Directional> {
+    pub config: FilterConfig,
+    advisory: A,
+    since: Time,
+    reversals: u8,
+}
+
+impl<A: Directional> AdvisoryFilter<A> {
+    /// Creates a new filter, starting with `advisory` issued at `now`
+    pub fn new(config: FilterConfig, advisory: A, now: Time) -> Self {
+        Self {
+            config,
+            advisory,
+            since: now,
+            reversals: 0,
+        }
+    }
+
+    /// The current filtered advisory
+    pub fn advisory(&self) -> A {
+        self.advisory
+    }
+
+    /// Number of sense reversals since the last clear of conflict
+    pub fn reversals(&self) -> u8 {
+        self.reversals
+    }
+
+    /// Picks the best permitted advisory from `scores` and makes it the current advisory
+    ///
+    /// `now` is the timestamp of the evaluation, and must not decrease between calls. `tau` is
+    /// the tau the scores were evaluated with.
+    pub fn apply(&mut self, now: Time, tau: Time, scores: &AdvisoryScores<A>) -> A {
+        let next = scores
+            .ranked()
+            .map(|(advisory, _)| advisory)
+            .find(|&advisory| self.permits(now, tau, advisory))
+            .unwrap_or(self.advisory);
+
+        if next != self.advisory {
+            if next.sense().is_none() {
+                self.reversals = 0;
+            } else if is_reversal(self.advisory, next) {
+                self.reversals = self.reversals.saturating_add(1);
+            }
+            self.advisory = next;
+            self.since = now;
+        }
+
+        self.advisory
+    }
+
+    /// Checks whether switching from the current advisory to `next` is permitted
+    fn permits(&self, now: Time, tau: Time, next: A) -> bool {
+        let current = self.advisory;
+        if next == current {
+            return true;
+        }
+
+        let weakens = next.sense().is_none()
+            || (next.sense() == current.sense() && next.strength() < current.strength());
+        if weakens && now - self.since < self.config.min_duration {
+            return false;
+        }
+
+        let reverses = is_reversal(current, next);
+        if let Some(max) = self.config.max_reversals {
+            if reverses && self.reversals >= max {
+                return false;
+            }
+        }
+
+        if let Some(strengthen_only_tau) = self.config.strengthen_only_tau {
+            if tau <= strengthen_only_tau && current.sense().is_some() && (weakens || reverses) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Whether going from `current` to `next` reverses the sense
+fn is_reversal<A: Directional>(current: A, next: A) -> bool {
+    matches!((current.sense(), next.sense()), (Some(a), Some(b)) if a != b)
+}
+
+/// HorizontalCAS with an [`AdvisoryFilter`]
+///
+/// The filtered advisory is fed back into the network selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilteredHCas {
+    pub filter: AdvisoryFilter<HAdvisory>,
+}
+
+impl FilteredHCas {
+    /// Creates a new filtered HorizontalCAS, starting clear of conflict at `now`
+    pub fn new(config: FilterConfig, now: Time) -> Self {
+        Self {
+            filter: AdvisoryFilter::new(config, HAdvisory::ClearOfConflict, now),
+        }
+    }
+
+    /// Same as [`crate::HCas::process_cartesian`], but returns the filtered advisory together
+    /// with the unfiltered scores
+    pub fn process_cartesian(
+        &mut self,
+        now: Time,
+        tau: Time,
+        forward_range: Length,
+        left_range: Length,
+        psi: Angle,
+    ) -> (HAdvisory, AdvisoryScores<HAdvisory>) {
+        let scores = hcas::evaluate(self.filter.advisory(), tau, forward_range, left_range, psi);
+        (self.filter.apply(now, tau, &scores), scores)
+    }
+}
+
+/// VerticalCAS with an [`AdvisoryFilter`]
+///
+/// The filtered advisory is fed back into the network selection.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FilteredVCas {
+    pub filter: AdvisoryFilter<VAdvisory>,
+}
+
+impl FilteredVCas {
+    /// Creates a new filtered VerticalCAS, starting clear of conflict at `now`
+    pub fn new(config: FilterConfig, now: Time) -> Self {
+        Self {
+            filter: AdvisoryFilter::new(config, VAdvisory::ClearOfConflict, now),
+        }
+    }
+
+    /// Same as [`crate::VCas::process`], but returns the filtered advisory together with the
+    /// unfiltered scores
+    pub fn process(
+        &mut self,
+        now: Time,
+        height: Length,
+        vertical_speed_homeship: Velocity,
+        vertical_speed_intruder: Velocity,
+        tau: Time,
+    ) -> (VAdvisory, AdvisoryScores<VAdvisory>) {
+        let scores = vcas::evaluate(
+            self.filter.advisory(),
+            height,
+            vertical_speed_homeship,
+            vertical_speed_intruder,
+            tau,
+        );
+        (self.filter.apply(now, tau, &scores), scores)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uom::si::length::foot;
+    use uom::si::time::second;
+    use uom::si::velocity::foot_per_minute;
+
+    fn config(min_duration: f32, max_reversals: Option<u8>, tau: Option<f32>) -> FilterConfig {
+        FilterConfig {
+            min_duration: Time::new::<second>(min_duration),
+            max_reversals,
+            strengthen_only_tau: tau.map(Time::new::<second>),
+        }
+    }
+
+    /// Scores preferring `best` over everything else
+    fn prefer(best: HAdvisory) -> AdvisoryScores<HAdvisory> {
+        let mut scores = [0.0; 5];
+        scores[best as usize] = 1.0;
+        AdvisoryScores::new(scores)
+    }
+
+    fn s(seconds: f32) -> Time {
+        Time::new::<second>(seconds)
+    }
+
+    #[test]
+    pub fn hold_for_minimum_duration() {
+        let mut filter =
+            AdvisoryFilter::new(config(5.0, None, None), HAdvisory::ClearOfConflict, s(0.0));
+        let tau = s(30.0);
+
+        assert_eq!(
+            filter.apply(s(1.0), tau, &prefer(HAdvisory::StrongLeft)),
+            HAdvisory::StrongLeft
+        );
+        // neither weakening nor clearing before the minimum duration passed
+        let weaken = AdvisoryScores::new([0.5, 1.0, -1.0, 0.75, -1.0]);
+        assert_eq!(filter.apply(s(3.0), tau, &weaken), HAdvisory::StrongLeft);
+        let clear = AdvisoryScores::new([1.0, 0.0, -1.0, 0.5, -1.0]);
+        assert_eq!(filter.apply(s(5.5), tau, &clear), HAdvisory::StrongLeft);
+        assert_eq!(
+            filter.apply(s(6.0), tau, &prefer(HAdvisory::ClearOfConflict)),
+            HAdvisory::ClearOfConflict
+        );
+    }
+
+    #[test]
+    pub fn limit_reversals() {
+        let mut filter =
+            AdvisoryFilter::new(config(0.0, Some(1), None), HAdvisory::WeakLeft, s(0.0));
+        let tau = s(30.0);
+
+        assert_eq!(
+            filter.apply(s(1.0), tau, &prefer(HAdvisory::WeakRight)),
+            HAdvisory::WeakRight
+        );
+        assert_eq!(filter.reversals(), 1);
+
+        // the second reversal is refused, and the current advisory outranks the rest
+        let mut scores = [-2.0, 0.5, -1.0, 1.0, -1.0];
+        assert_eq!(
+            filter.apply(s(2.0), tau, &AdvisoryScores::new(scores)),
+            HAdvisory::WeakRight
+        );
+        scores[0] = 0.75;
+        assert_eq!(
+            filter.apply(s(3.0), tau, &AdvisoryScores::new(scores)),
+            HAdvisory::ClearOfConflict
+        );
+        assert_eq!(filter.reversals(), 0);
+    }
+
+    #[test]
+    pub fn strengthen_only_within_tau() {
+        let mut filter =
+            AdvisoryFilter::new(config(0.0, None, Some(10.0)), HAdvisory::WeakRight, s(0.0));
+
+        assert_eq!(
+            filter.apply(s(1.0), s(8.0), &prefer(HAdvisory::StrongLeft)),
+            HAdvisory::WeakRight
+        );
+        assert_eq!(
+            filter.apply(s(2.0), s(7.0), &prefer(HAdvisory::StrongRight)),
+            HAdvisory::StrongRight
+        );
+        assert_eq!(
+            filter.apply(s(3.0), s(6.0), &prefer(HAdvisory::ClearOfConflict)),
+            HAdvisory::StrongRight
+        );
+        assert_eq!(
+            filter.apply(s(4.0), s(12.0), &prefer(HAdvisory::ClearOfConflict)),
+            HAdvisory::ClearOfConflict
+        );
+    }
+
+    #[test]
+    pub fn filtered_vcas_feeds_back_advisory() {
+        let mut vcas = FilteredVCas::new(config(2.0, Some(0), Some(5.0)), s(0.0));
+        let (height, vs) = (
+            Length::new::<foot>(100.0),
+            Velocity::new::<foot_per_minute>(0.0),
+        );
+
+        for step in 0..20 {
+            let tau = s(20.0 - step as f32);
+            let (advisory, scores) = vcas.process(s(step as f32), height, vs, vs, tau);
+            assert_eq!(advisory, vcas.filter.advisory());
+
+            if step < 19 {
+                assert_eq!(scores.rank(advisory), 0, "step {step}");
+            } else {
+                // the networks already prefer clear of conflict, but below a tau of 5 s the
+                // advisory may only be strengthened
+                assert_eq!(scores.advisory(), VAdvisory::ClearOfConflict);
+                assert_eq!(advisory, VAdvisory::StrengthenDescend2500);
+                assert_eq!(scores.rank(advisory), 1);
+            }
+        }
+        assert_eq!(vcas.filter.reversals(), 0);
+    }
+}
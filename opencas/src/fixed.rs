@@ -0,0 +1,408 @@
+use core::fmt;
+
+#[allow(unused_imports)]
+use num::Float;
+
+use crate::nnets::{HCAS_FIXED_NNETS, HCAS_FIXED_TAUS, VCAS_FIXED_NNETS};
+use crate::{Advisory, HAdvisory, VAdvisory};
+
+/// A signed Q16.16 fixed point number
+///
+/// All arithmetic saturates at the bounds of the underlying `i32` instead of wrapping around.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fixed(i32);
+
+impl Fixed {
+    /// Number of fractional bits
+    pub const FRAC_BITS: u32 = 16;
+
+    pub const ZERO: Self = Self(0);
+    pub const ONE: Self = Self(1 << Self::FRAC_BITS);
+    pub const MIN: Self = Self(i32::MIN);
+    pub const MAX: Self = Self(i32::MAX);
+
+    /// Creates a number from its raw bits, that is `value * 2^16`
+    pub const fn from_bits(bits: i32) -> Self {
+        Self(bits)
+    }
+
+    /// The raw bits, that is `self * 2^16`
+    pub const fn to_bits(self) -> i32 {
+        self.0
+    }
+
+    /// Creates a number from an integer, saturating if it is out of range
+    pub const fn from_int(value: i32) -> Self {
+        Self(saturate((value as i64) << Self::FRAC_BITS))
+    }
+
+    /// Rounds `value` to the nearest representable number, saturating if it is out of range
+    ///
+    /// NaN is mapped to zero.
+    pub fn from_f32(value: f32) -> Self {
+        Self(quantize(value, Self::FRAC_BITS))
+    }
+
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / Self::ONE.0 as f32
+    }
+
+    pub const fn saturating_add(self, rhs: Self) -> Self {
+        Self(self.0.saturating_add(rhs.0))
+    }
+
+    pub const fn saturating_sub(self, rhs: Self) -> Self {
+        Self(self.0.saturating_sub(rhs.0))
+    }
+
+    /// Multiplies two numbers, rounding to the nearest representable number
+    pub const fn saturating_mul(self, rhs: Self) -> Self {
+        Self(saturate(round_shift(
+            self.0 as i64 * rhs.0 as i64,
+            Self::FRAC_BITS,
+        )))
+    }
+}
+
+impl fmt::Display for Fixed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_f32())
+    }
+}
+
+/// Shifts `value` right by `shift` bits, rounding half away from negative infinity
+const fn round_shift(value: i64, shift: u32) -> i64 {
+    if shift == 0 {
+        value
+    } else {
+        (value + (1 << (shift - 1))) >> shift
+    }
+}
+
+/// Clamps `value` to the range of an `i32`
+const fn saturate(value: i64) -> i32 {
+    if value > i32::MAX as i64 {
+        i32::MAX
+    } else if value < i32::MIN as i64 {
+        i32::MIN
+    } else {
+        value as i32
+    }
+}
+
+/// Rounds `value` to a fixed point number with `shift` fractional bits, saturating at the bounds
+/// of an `i32`
+fn quantize(value: f32, shift: u32) -> i32 {
+    // the cast saturates, and maps NaN to zero
+    (value as f64 * (1u64 << shift) as f64).round() as i32
+}
+
+/// A quantized neuronal network, the fixed point equivalent of [`crate::inference::NNet`]
+///
+/// The networks are generated by the build script from the same `.nnet` files as the `f32`
+/// networks. All activations are [`Fixed`] (Q16.16), so the evaluation needs no floating point
+/// unit:
+///
+/// + Every input `i` is an `i32` with `input_shift[i]` fractional bits. The format is chosen by the
+///   build script so that the bounds of the input fit, and is shared by all networks of one CAS.
+/// + The inputs are clamped to `min_input..=max_input` and normalized to Q16.16 as
+///   `(x - mean_value) * scale >> scale_shift`.
+/// + Every layer multiplies with `i32` weights, which carry `weight_shift` fractional bits chosen
+///   per layer so that its largest weight keeps the 24 bit precision of an `f32`. The products are
+///   accumulated in an `i64`, a single multiply-accumulate instruction (`SMLAL`) on 32 bit Arm
+///   microcontrollers, and then rounded and saturated back to Q16.16.
+/// + The output is scaled back as `y * range_output + mean_output`.
+#[derive(Debug)]
+pub struct FixedNNet<
+    const N_INPUT: usize,
+    const N_MAT: usize,
+    const N_NEURON: usize,
+    const N_OUTPUT: usize,
+> {
+    pub input_layer: FixedLayer<N_INPUT, N_NEURON>,
+    pub hidden_layers: [FixedLayer<N_NEURON, N_NEURON>; N_MAT],
+    pub output_layer: FixedLayer<N_NEURON, N_OUTPUT>,
+    pub input_shift: [u32; N_INPUT],
+    pub min_input: [i32; N_INPUT],
+    pub max_input: [i32; N_INPUT],
+    pub mean_value: [i32; N_INPUT],
+    pub scale: [i32; N_INPUT],
+    pub scale_shift: [u32; N_INPUT],
+    pub mean_output: Fixed,
+    pub range_output: Fixed,
+}
+
+/// One layer of a quantized neuronal network
+///
+/// The weight of real value `w` is stored as `w * 2^weight_shift`, rounded to an `i32`.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedLayer<const INPUT_NEURONS: usize, const OUTPUT_NEURONS: usize> {
+    pub weights: [[i32; INPUT_NEURONS]; OUTPUT_NEURONS],
+    pub weight_shift: u32,
+    pub biases: [Fixed; OUTPUT_NEURONS],
+}
+
+impl<const INPUT_NEURONS: usize, const OUTPUT_NEURONS: usize>
+    FixedLayer<INPUT_NEURONS, OUTPUT_NEURONS>
+{
+    /// Computes `weights * inputs + biases`, saturating every output
+    fn apply(&self, inputs: &[Fixed; INPUT_NEURONS]) -> [Fixed; OUTPUT_NEURONS] {
+        core::array::from_fn(|row| {
+            let accumulator = self.weights[row].iter().zip(inputs).fold(
+                (self.biases[row].0 as i64) << self.weight_shift,
+                |acc, (w, x)| acc + *w as i64 * x.0 as i64,
+            );
+            Fixed(saturate(round_shift(accumulator, self.weight_shift)))
+        })
+    }
+}
+
+impl<const N_INPUT: usize, const N_MAT: usize, const N_NEURON: usize, const N_OUTPUT: usize>
+    FixedNNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>
+{
+    /// Evaluates the network, see [`FixedNNet`] for the format of the inputs
+    pub fn eval(&self, inputs: [i32; N_INPUT]) -> [Fixed; N_OUTPUT] {
+        let relu = |layer: [Fixed; N_NEURON]| layer.map(|x| x.max(Fixed::ZERO));
+
+        let mut accumulator = relu(self.input_layer.apply(&self.normalize(inputs)));
+        for layer in &self.hidden_layers {
+            accumulator = relu(layer.apply(&accumulator));
+        }
+
+        self.output_layer.apply(&accumulator).map(|y| {
+            y.saturating_mul(self.range_output)
+                .saturating_add(self.mean_output)
+        })
+    }
+
+    /// Clamps the inputs to their bounds and normalizes them to Q16.16
+    fn normalize(&self, inputs: [i32; N_INPUT]) -> [Fixed; N_INPUT] {
+        core::array::from_fn(|i| {
+            let x = inputs[i].clamp(self.min_input[i], self.max_input[i]);
+            let centered = x as i64 - self.mean_value[i] as i64;
+            Fixed(saturate(round_shift(
+                centered * self.scale[i] as i64,
+                self.scale_shift[i],
+            )))
+        })
+    }
+
+    /// Converts inputs in the units of the `.nnet` file into the format expected by
+    /// [`FixedNNet::eval`]
+    ///
+    /// This is meant for hosts with a floating point unit, e.g. to compare against the `f32`
+    /// networks. Inputs out of range saturate.
+    pub fn quantize_inputs(&self, inputs: [f32; N_INPUT]) -> [i32; N_INPUT] {
+        core::array::from_fn(|i| quantize(inputs[i], self.input_shift[i]))
+    }
+}
+
+/// Fractional bits of the inputs of [`hcas_scores`]: forward range [ft], left range [ft] and
+/// psi [rad]
+pub fn hcas_input_shift() -> [u32; 3] {
+    HCAS_FIXED_NNETS[0][0].input_shift
+}
+
+/// Fractional bits of the inputs of [`vcas_scores`]: relative intruder altitude [ft], vertical
+/// speed of homeship [ft/min], vertical speed of intruder [ft/min] and tau [s]
+pub fn vcas_input_shift() -> [u32; 4] {
+    VCAS_FIXED_NNETS[0].input_shift
+}
+
+/// Fixed point equivalent of [`crate::hcas::evaluate`]
+///
+/// `tau` selects the network like [`crate::HCAS_TAU_SCHEDULE`] does. `inputs` are forward range,
+/// left range and psi, in the format given by [`hcas_input_shift`].
+pub fn hcas_scores(pra: HAdvisory, tau: Fixed, inputs: [i32; 3]) -> [Fixed; 5] {
+    let index = HCAS_FIXED_TAUS.iter().rposition(|&t| t <= tau).unwrap_or(0);
+    HCAS_FIXED_NNETS[pra as usize][index].eval(inputs)
+}
+
+/// Fixed point equivalent of [`crate::vcas::evaluate`]
+///
+/// `inputs` are relative intruder altitude, vertical speed of homeship, vertical speed of
+/// intruder and tau, in the format given by [`vcas_input_shift`].
+pub fn vcas_scores(pra: VAdvisory, inputs: [i32; 4]) -> [Fixed; 9] {
+    VCAS_FIXED_NNETS[pra as usize].eval(inputs)
+}
+
+/// The advisory with the best score, with the same tie breaking as
+/// [`crate::AdvisoryScores::advisory`]
+///
+/// # Panics
+///
+/// Panics if `scores` does not hold one score per advisory.
+pub fn best<A: Advisory>(scores: &[Fixed]) -> A {
+    assert_eq!(
+        scores.len(),
+        A::ALL.len(),
+        "one score per advisory expected"
+    );
+
+    let better = |a: Fixed, b: Fixed| if A::MINIMIZE { a < b } else { a > b };
+    let mut best = 0;
+    for (i, score) in scores.iter().enumerate().skip(1) {
+        if better(*score, scores[best]) {
+            best = i;
+        }
+    }
+    A::ALL[best]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nnets::{HCAS_NNETS, VCAS_NNETS};
+    use crate::AdvisoryScores;
+    use uom::si::angle::radian;
+    use uom::si::f32::*;
+    use uom::si::length::foot;
+    use uom::si::time::second;
+    use uom::si::velocity::foot_per_minute;
+
+    #[test]
+    pub fn saturating_arithmetic() {
+        let half = Fixed::from_f32(0.5);
+        assert_eq!(half.saturating_mul(half), Fixed::from_f32(0.25));
+        assert_eq!(Fixed::from_int(3).saturating_mul(half).to_f32(), 1.5);
+        assert_eq!(
+            Fixed::from_int(-2).saturating_add(Fixed::ONE).to_f32(),
+            -1.0
+        );
+
+        assert_eq!(Fixed::MAX.saturating_add(Fixed::ONE), Fixed::MAX);
+        assert_eq!(
+            Fixed::from_int(300).saturating_mul(Fixed::from_int(300)),
+            Fixed::MAX
+        );
+        assert_eq!(
+            Fixed::from_int(-300).saturating_mul(Fixed::from_int(300)),
+            Fixed::MIN
+        );
+        assert_eq!(Fixed::from_int(1 << 20), Fixed::MAX);
+        assert_eq!(Fixed::from_f32(1e10), Fixed::MAX);
+        assert_eq!(Fixed::from_f32(f32::NAN), Fixed::ZERO);
+    }
+
+    /// Outcome of comparing fixed point networks against their `f32` originals
+    ///
+    /// The largest score errors occur where the networks are steep, as the normalized inputs are
+    /// only resolved to 2^-16. Advisories disagree where two scores are about equal.
+    #[derive(Debug, Default)]
+    struct Comparison {
+        evaluations: usize,
+        max_score_error: f32,
+        /// Inputs for which the best advisories differ
+        disagreements: Vec<Vec<f32>>,
+    }
+
+    impl Comparison {
+        fn record<A: Advisory>(
+            &mut self,
+            expected: AdvisoryScores<A>,
+            actual: &[Fixed],
+            inputs: &[f32],
+        ) {
+            self.evaluations += 1;
+            for (e, a) in expected.scores().iter().zip(actual) {
+                self.max_score_error = self.max_score_error.max((e - a.to_f32()).abs());
+            }
+            if best::<A>(actual) != expected.advisory() {
+                self.disagreements.push(inputs.to_vec());
+            }
+        }
+
+        /// Prints the report and checks it against the given bounds
+        fn check(&self, name: &str, max_score_error: f32, max_disagreement_rate: f32) {
+            let rate = self.disagreements.len() as f32 / self.evaluations as f32;
+            println!(
+                "{name}: {} evaluations, max score error {}, {} advisory disagreements ({:.3} %)",
+                self.evaluations,
+                self.max_score_error,
+                self.disagreements.len(),
+                rate * 100.0
+            );
+            for inputs in self.disagreements.iter().take(10) {
+                println!("  disagreement at {inputs:?}");
+            }
+
+            assert!(self.max_score_error <= max_score_error);
+            assert!(rate <= max_disagreement_rate);
+        }
+    }
+
+    fn grid(min: f32, max: f32, steps: usize) -> impl Iterator<Item = f32> + Clone {
+        (0..steps).map(move |i| min + (max - min) * i as f32 / (steps - 1) as f32)
+    }
+
+    #[test]
+    pub fn hcas_matches_f32() {
+        let mut comparison = Comparison::default();
+        for (nnets, fixed_nnets) in HCAS_NNETS.iter().zip(&HCAS_FIXED_NNETS) {
+            for (nnet, fixed) in nnets.iter().zip(fixed_nnets) {
+                for x in grid(-8_000.0, 8_000.0, 17) {
+                    for y in grid(-8_000.0, 8_000.0, 17) {
+                        for psi in grid(-3.0, 3.0, 7) {
+                            let inputs = [x, y, psi];
+                            let expected = AdvisoryScores::new(nnet.eval(inputs.into()).into());
+                            let actual = fixed.eval(fixed.quantize_inputs(inputs));
+                            comparison.record::<HAdvisory>(expected, &actual, &inputs);
+                        }
+                    }
+                }
+            }
+        }
+        comparison.check("HCAS", 0.05, 0.002);
+    }
+
+    #[test]
+    pub fn vcas_matches_f32() {
+        let mut comparison = Comparison::default();
+        for (nnet, fixed) in VCAS_NNETS.iter().zip(&VCAS_FIXED_NNETS) {
+            for h in grid(-4000.0, 4000.0, 17) {
+                for vs_own in grid(-100.0, 100.0, 5) {
+                    for vs_int in grid(-100.0, 100.0, 5) {
+                        for tau in grid(0.0, 40.0, 9) {
+                            let inputs = [h, vs_own, vs_int, tau];
+                            let expected = AdvisoryScores::new(nnet.eval(inputs.into()).into());
+                            let actual = fixed.eval(fixed.quantize_inputs(inputs));
+                            comparison.record::<VAdvisory>(expected, &actual, &inputs);
+                        }
+                    }
+                }
+            }
+        }
+        comparison.check("VCAS", 0.1, 0.002);
+    }
+
+    #[test]
+    pub fn front_ends_match_f32() {
+        let shift = hcas_input_shift();
+        let inputs = [3000.0, -500.0, 1.0];
+        let fixed_inputs = core::array::from_fn(|i| quantize(inputs[i], shift[i]));
+        for tau in [0, 7, 25, 99] {
+            let scores = hcas_scores(HAdvisory::WeakLeft, Fixed::from_int(tau), fixed_inputs);
+            let expected = crate::hcas::evaluate(
+                HAdvisory::WeakLeft,
+                Time::new::<second>(tau as f32),
+                Length::new::<foot>(inputs[0]),
+                Length::new::<foot>(inputs[1]),
+                Angle::new::<radian>(inputs[2]),
+            );
+            assert_eq!(best::<HAdvisory>(&scores), expected.advisory());
+        }
+
+        let shift = vcas_input_shift();
+        let inputs = [200.0, 0.0, -20.0, 15.0];
+        let fixed_inputs = core::array::from_fn(|i| quantize(inputs[i], shift[i]));
+        let scores = vcas_scores(VAdvisory::ClearOfConflict, fixed_inputs);
+        let expected = crate::vcas::evaluate(
+            VAdvisory::ClearOfConflict,
+            Length::new::<foot>(inputs[0]),
+            Velocity::new::<foot_per_minute>(inputs[1]),
+            Velocity::new::<foot_per_minute>(inputs[2]),
+            Time::new::<second>(inputs[3]),
+        );
+        assert_eq!(best::<VAdvisory>(&scores), expected.advisory());
+    }
+}
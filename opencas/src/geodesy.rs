@@ -0,0 +1,290 @@
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::meter;
+
+use crate::encounter::{AircraftState, Frame};
+
+/// Semi-major axis of the WGS84 ellipsoid in meters
+pub const WGS84_A: f64 = 6_378_137.0;
+
+/// Flattening of the WGS84 ellipsoid
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// Squared first eccentricity of the WGS84 ellipsoid
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+/// A position given by latitude, longitude and altitude above the WGS84 ellipsoid
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Geodetic {
+    pub latitude: Angle,
+    pub longitude: Angle,
+    pub altitude: Length,
+}
+
+impl Geodetic {
+    /// Creates a new geodetic position
+    pub fn new(latitude: Angle, longitude: Angle, altitude: Length) -> Self {
+        Self {
+            latitude,
+            longitude,
+            altitude,
+        }
+    }
+
+    /// Converts to earth-centered, earth-fixed coordinates
+    ///
+    /// The result is rounded to `f32`, which limits its precision to about half a meter. Use a
+    /// [`LocalFrame`] to get precise relative positions.
+    pub fn to_ecef(&self) -> [Length; 3] {
+        to_lengths(self.ecef())
+    }
+
+    /// Converts from earth-centered, earth-fixed coordinates
+    pub fn from_ecef(ecef: [Length; 3]) -> Self {
+        Self::from_ecef_meters(ecef.map(|l| l.get::<meter>() as f64))
+    }
+
+    /// Latitude, longitude in radians and altitude in meters
+    fn radians(&self) -> (f64, f64, f64) {
+        (
+            self.latitude.get::<radian>() as f64,
+            self.longitude.get::<radian>() as f64,
+            self.altitude.get::<meter>() as f64,
+        )
+    }
+
+    /// Earth-centered, earth-fixed coordinates in meters
+    fn ecef(&self) -> [f64; 3] {
+        let (lat, lon, alt) = self.radians();
+        let n = prime_vertical_radius(lat);
+
+        [
+            (n + alt) * lat.cos() * lon.cos(),
+            (n + alt) * lat.cos() * lon.sin(),
+            (n * (1.0 - WGS84_E2) + alt) * lat.sin(),
+        ]
+    }
+
+    fn from_ecef_meters([x, y, z]: [f64; 3]) -> Self {
+        let p = x.hypot(y);
+        let lon = y.atan2(x);
+
+        // fixed point iteration on the latitude, which converges to well below a millimeter
+        let mut lat = z.atan2(p * (1.0 - WGS84_E2));
+        for _ in 0..5 {
+            let n = prime_vertical_radius(lat);
+            lat = (z + WGS84_E2 * n * lat.sin()).atan2(p);
+        }
+
+        // this form of the altitude is stable at the poles as well
+        let alt =
+            p * lat.cos() + z * lat.sin() - WGS84_A * (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt();
+
+        Self {
+            latitude: Angle::new::<radian>(lat as f32),
+            longitude: Angle::new::<radian>(lon as f32),
+            altitude: Length::new::<meter>(alt as f32),
+        }
+    }
+}
+
+/// Radius of curvature in the prime vertical at latitude `lat` in radians
+fn prime_vertical_radius(lat: f64) -> f64 {
+    WGS84_A / (1.0 - WGS84_E2 * lat.sin().powi(2)).sqrt()
+}
+
+fn to_lengths(v: [f64; 3]) -> [Length; 3] {
+    v.map(|m| Length::new::<meter>(m as f32))
+}
+
+/// A local tangent plane, placed at an origin (usually the homeship)
+///
+/// All conversions are done in `f64`, only the results are rounded to `f32`, so relative positions
+/// do not suffer from the huge absolute coordinates. The remaining error stems from latitude and
+/// longitude being `f32` angles, which resolve a position to about half a meter.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LocalFrame {
+    origin: Geodetic,
+    origin_ecef: [f64; 3],
+
+    /// Rows are the east, north and up unit vectors in ECEF
+    rotation: [[f64; 3]; 3],
+}
+
+impl LocalFrame {
+    /// Creates a local tangent plane at `origin`
+    pub fn new(origin: Geodetic) -> Self {
+        let (lat, lon, _) = origin.radians();
+        let (sin_lat, cos_lat, sin_lon, cos_lon) = (lat.sin(), lat.cos(), lon.sin(), lon.cos());
+
+        Self {
+            origin,
+            origin_ecef: origin.ecef(),
+            rotation: [
+                [-sin_lon, cos_lon, 0.0],
+                [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat],
+                [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat],
+            ],
+        }
+    }
+
+    /// The origin of the frame
+    pub fn origin(&self) -> Geodetic {
+        self.origin
+    }
+
+    /// Position of `point` in the given local frame, relative to the origin
+    pub fn position(&self, frame: Frame, point: Geodetic) -> [Length; 3] {
+        let ecef = point.ecef();
+        let delta = [
+            ecef[0] - self.origin_ecef[0],
+            ecef[1] - self.origin_ecef[1],
+            ecef[2] - self.origin_ecef[2],
+        ];
+        let [e, n, u] = self.rotation.map(|row| dot(row, delta));
+
+        to_lengths(match frame {
+            Frame::Enu => [e, n, u],
+            Frame::Ned => [n, e, -u],
+        })
+    }
+
+    /// East, north, up position of `point`
+    pub fn enu(&self, point: Geodetic) -> [Length; 3] {
+        self.position(Frame::Enu, point)
+    }
+
+    /// North, east, down position of `point`
+    pub fn ned(&self, point: Geodetic) -> [Length; 3] {
+        self.position(Frame::Ned, point)
+    }
+
+    /// Geodetic position of a point given in the local frame
+    pub fn geodetic(&self, frame: Frame, position: [Length; 3]) -> Geodetic {
+        let [a, b, c] = position.map(|l| l.get::<meter>() as f64);
+        let enu = match frame {
+            Frame::Enu => [a, b, c],
+            Frame::Ned => [b, a, -c],
+        };
+
+        // the rotation is orthonormal, so its transpose is its inverse
+        let r = &self.rotation;
+        let ecef = [0, 1, 2]
+            .map(|i| self.origin_ecef[i] + r[0][i] * enu[0] + r[1][i] * enu[1] + r[2][i] * enu[2]);
+        Geodetic::from_ecef_meters(ecef)
+    }
+
+    /// State of an aircraft at `point`, ready to be used in an [`crate::encounter::Encounter`]
+    ///
+    /// `velocity` must be given in the local frame already.
+    pub fn aircraft_state(
+        &self,
+        frame: Frame,
+        point: Geodetic,
+        velocity: [Velocity; 3],
+        heading: Angle,
+    ) -> AircraftState {
+        AircraftState {
+            position: self.position(frame, point),
+            velocity,
+            heading,
+        }
+    }
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uom::si::angle::degree;
+    use uom::si::length::foot;
+
+    fn geodetic(lat: f32, lon: f32, alt: f32) -> Geodetic {
+        Geodetic::new(
+            Angle::new::<degree>(lat),
+            Angle::new::<degree>(lon),
+            Length::new::<meter>(alt),
+        )
+    }
+
+    fn meters(v: [Length; 3]) -> [f32; 3] {
+        v.map(|l| l.get::<meter>())
+    }
+
+    fn assert_close(actual: [f32; 3], expected: [f32; 3], tolerance: f32) {
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() <= tolerance,
+                "{actual:?} differs from {expected:?} by more than {tolerance}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn ecef_reference_points() {
+        assert_close(
+            meters(geodetic(0.0, 0.0, 0.0).to_ecef()),
+            [6_378_137.0, 0.0, 0.0],
+            0.5,
+        );
+        assert_close(
+            meters(geodetic(0.0, 90.0, 0.0).to_ecef()),
+            [0.0, 6_378_137.0, 0.0],
+            0.5,
+        );
+
+        // the semi-minor axis, checked in full precision
+        let [_, _, z] = geodetic(90.0, 0.0, 0.0).ecef();
+        assert!((z - 6_356_752.314_245).abs() < 1e-3);
+
+        let pole = Geodetic::from_ecef_meters([0.0, 0.0, 6_356_852.314_245]);
+        assert!((pole.latitude.get::<degree>() - 90.0).abs() < 1e-5);
+        assert!((pole.altitude.get::<meter>() - 100.0).abs() < 0.5);
+    }
+
+    #[test]
+    pub fn enu_reference_point() {
+        // Matterhorn seen from Zermatt, as in the documentation of MATLABs `geodetic2enu`
+        let zermatt = LocalFrame::new(geodetic(46.017, 7.750, 1673.0));
+        let matterhorn = geodetic(45.976, 7.658, 4531.0);
+
+        assert_close(
+            meters(zermatt.enu(matterhorn)),
+            [-7134.8, -4556.3, 2852.4],
+            0.5,
+        );
+        assert_close(
+            meters(zermatt.ned(matterhorn)),
+            [-4556.3, -7134.8, -2852.4],
+            0.5,
+        );
+    }
+
+    #[test]
+    pub fn accuracy_at_hcas_range_limit() {
+        let range = Length::new::<foot>(56_000.0).get::<meter>();
+        let origin = geodetic(53.3, 10.0, 3000.0);
+        let frame = LocalFrame::new(origin);
+
+        for step in 0..16 {
+            let bearing = step as f32 * core::f32::consts::PI / 8.0;
+            let enu = [range * bearing.sin(), range * bearing.cos(), 0.0];
+
+            // going to geodetic and back only loses the resolution of the f32 angles
+            let point = frame.geodetic(Frame::Enu, enu.map(Length::new::<meter>));
+            assert_close(meters(frame.enu(point)), enu, 1.0);
+
+            // a point at the same altitude drops below the tangent plane by about r^2 / 2R
+            let level = Geodetic::new(point.latitude, point.longitude, origin.altitude);
+            let [_, _, up] = meters(frame.enu(level));
+            let drop = range * range / (2.0 * 6_371_000.0);
+            assert!((up + drop).abs() < 0.5, "drop of {up} instead of {drop}");
+        }
+    }
+}
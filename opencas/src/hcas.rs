@@ -0,0 +1,556 @@
+use core::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::foot;
+use uom::si::time::second;
+
+use crate::error::{ensure_finite, ensure_non_negative};
+use crate::inference::Vector;
+use crate::multi::HIntruder;
+pub use crate::nnets::HCasNNet;
+use crate::{nnets, Advisory, AdvisoryScores, CasError, CasInput, HCAS_TAU_SCHEDULE};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+
+/// This will store the last given advisory in order to locate the correct network in the
+/// evaluation.
+///
+/// All evaluation happens in the stateless functions of the [`crate::hcas`] module, this struct
+/// only feeds the last advisory back into them.
+pub struct HCas {
+    pub last_advisory: HAdvisory,
+}
+
+/// HAdvisory stores all possible output evaluations.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HAdvisory {
+    #[default]
+    ClearOfConflict = 0,
+    WeakLeft = 1,
+    WeakRight = 2,
+    StrongLeft = 3,
+    StrongRight = 4,
+}
+
+impl TryFrom<u8> for HAdvisory {
+    type Error = CasError;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => Self::ClearOfConflict,
+            1 => Self::WeakLeft,
+            2 => Self::WeakRight,
+            3 => Self::StrongLeft,
+            4 => Self::StrongRight,
+            _ => return Err(CasError::InvalidAdvisory(v)),
+        })
+    }
+}
+
+impl Advisory for HAdvisory {
+    type Scores = [f32; 5];
+
+    const ALL: &'static [Self] = &[
+        Self::ClearOfConflict,
+        Self::WeakLeft,
+        Self::WeakRight,
+        Self::StrongLeft,
+        Self::StrongRight,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Stateless version of [`HCas::process_polar`]
+///
+/// The network is selected by `tau` and the explicitly given previous advisory `pra`.
+pub fn evaluate_polar(
+    pra: HAdvisory,
+    tau: Time,
+    range: Length,
+    theta: Angle,
+    psi: Angle,
+) -> AdvisoryScores<HAdvisory> {
+    evaluate(
+        pra,
+        tau,
+        range * (theta.get::<radian>().cos()),
+        range * (theta.get::<radian>().sin()),
+        psi,
+    )
+}
+
+/// Stateless version of [`HCas::try_process_polar`]
+pub fn try_evaluate_polar(
+    pra: HAdvisory,
+    tau: Time,
+    range: Length,
+    theta: Angle,
+    psi: Angle,
+) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+    ensure_non_negative(range.get::<foot>(), CasInput::Range)?;
+    let theta = ensure_finite(theta.get::<radian>(), CasInput::Theta)?;
+
+    try_evaluate(pra, tau, range * theta.cos(), range * theta.sin(), psi)
+}
+
+/// Stateless version of [`HCas::process_cartesian`]
+///
+/// The network is selected by `tau` and the explicitly given previous advisory `pra`.
+pub fn evaluate(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> AdvisoryScores<HAdvisory> {
+    AdvisoryScores::new(network_scores(pra, tau, forward_range, left_range, psi).into())
+}
+
+/// Stateless version of [`HCas::try_process_cartesian`]
+pub fn try_evaluate(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+    check_cartesian_inputs(tau, forward_range, left_range, psi)?;
+
+    finite(network_scores(pra, tau, forward_range, left_range, psi))
+}
+
+/// Stateless version of [`HCas::process_cartesian_interpolated`]
+pub fn evaluate_interpolated(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> AdvisoryScores<HAdvisory> {
+    AdvisoryScores::new(
+        network_scores_interpolated(pra, tau, forward_range, left_range, psi).into(),
+    )
+}
+
+/// Stateless version of [`HCas::try_process_cartesian_interpolated`]
+pub fn try_evaluate_interpolated(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+    check_cartesian_inputs(tau, forward_range, left_range, psi)?;
+
+    finite(network_scores_interpolated(
+        pra,
+        tau,
+        forward_range,
+        left_range,
+        psi,
+    ))
+}
+
+/// Rejects non-finite inputs and a negative `tau`
+fn check_cartesian_inputs(
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> Result<(), CasError> {
+    ensure_non_negative(tau.get::<second>(), CasInput::Tau)?;
+    ensure_finite(forward_range.get::<foot>(), CasInput::ForwardRange)?;
+    ensure_finite(left_range.get::<foot>(), CasInput::LeftRange)?;
+    ensure_finite(psi.get::<radian>(), CasInput::Psi)?;
+    Ok(())
+}
+
+/// Wraps the network output, but rejects non-finite scores
+fn finite(evaluated: Vector<5>) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+    let scores = AdvisoryScores::new(evaluated.into());
+    if !scores.is_finite() {
+        return Err(CasError::NonFiniteOutput);
+    }
+
+    Ok(scores)
+}
+
+/// Batched version of [`evaluate`], for many geometries at once
+///
+/// Every intruder in `intruders` is evaluated with the previous advisory `pra` and the network
+/// selected by its own `tau`. The scores are written to the same index of `scores`. Intruders
+/// selecting the same network are evaluated together, see [`crate::inference::BATCH`].
+///
+/// # Panics
+///
+/// Panics if `intruders` and `scores` differ in length.
+pub fn evaluate_batch(
+    pra: HAdvisory,
+    intruders: &[HIntruder],
+    scores: &mut [AdvisoryScores<HAdvisory>],
+) {
+    assert_eq!(
+        intruders.len(),
+        scores.len(),
+        "one score vector per intruder expected"
+    );
+
+    for (index, nnet) in nnets::HCAS_NNETS[pra as usize].iter().enumerate() {
+        let inputs = intruders
+            .iter()
+            .enumerate()
+            .filter(|(_, i)| HCAS_TAU_SCHEDULE.index(i.tau) == index)
+            .map(|(k, i)| (k, network_inputs(i.forward_range, i.left_range, i.psi)));
+
+        nnet.eval_keyed(inputs, |k, output| {
+            scores[k] = AdvisoryScores::new(output.into())
+        });
+    }
+}
+
+/// The network [`evaluate`] selects for `tau` and the previous advisory `pra`
+///
+/// Its inputs are forward range [ft], left range [ft] and psi [rad], its outputs are the scores
+/// of the advisories.
+pub fn network(pra: HAdvisory, tau: Time) -> &'static HCasNNet {
+    // match the value of tau to the corresponding tau trained networks, and find the correct
+    // network by using the last given advisory and the tau index
+    &nnets::HCAS_NNETS[pra as usize][HCAS_TAU_SCHEDULE.index(tau)]
+}
+
+/// Selects the network matching `tau` and the previous advisory and evaluates it
+fn network_scores(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> Vector<5> {
+    // do the actual evalutaion (see inference.rs)
+    network(pra, tau).eval(network_inputs(forward_range, left_range, psi))
+}
+
+/// Evaluates the two networks surrounding `tau` and blends their scores
+fn network_scores_interpolated(
+    pra: HAdvisory,
+    tau: Time,
+    forward_range: Length,
+    left_range: Length,
+    psi: Angle,
+) -> Vector<5> {
+    let (lower, upper, weight) = HCAS_TAU_SCHEDULE.bracket(tau);
+    let nnets = &nnets::HCAS_NNETS[pra as usize];
+    let inputs = network_inputs(forward_range, left_range, psi);
+
+    let lower_scores = nnets[lower].eval(inputs);
+    if weight == 0.0 {
+        return lower_scores;
+    }
+
+    lower_scores * (1.0 - weight) + nnets[upper].eval(inputs) * weight
+}
+
+/// Generates the network inputs as a vector [x,y,psi]
+fn network_inputs(forward_range: Length, left_range: Length, psi: Angle) -> Vector<3> {
+    nalgebra::vector![
+        forward_range.get::<foot>(),
+        left_range.get::<foot>(),
+        psi.get::<radian>()
+    ]
+}
+
+impl HCas {
+    /// Creates a new HorizontalCAS with the given last advisory
+    pub const fn new(last_advisory: HAdvisory) -> Self {
+        Self { last_advisory }
+    }
+
+    /// Evaluates many geometries at once with the networks selected by `last_advisory`, see
+    /// [`evaluate_batch`]
+    ///
+    /// As the geometries are unrelated, `last_advisory` is not updated.
+    pub fn evaluate_batch(
+        &self,
+        intruders: &[HIntruder],
+        scores: &mut [AdvisoryScores<HAdvisory>],
+    ) {
+        evaluate_batch(self.last_advisory, intruders, scores)
+    }
+
+    /// HorizontalCAS consists of 40 different neural networks (smaller network = les runtime). The
+    /// splitting parameters are:
+    ///
+    /// + time until impact: tau [sec]
+    /// + previous given advisory: pra [-]
+    ///
+    /// Based on those parameters the right network is chosen. [`HCAS_TAU_SCHEDULE`] tells which
+    /// network a given tau selects.
+    ///
+    /// HorizontalCAS, as we use it, needs three inputs:
+    /// + `range` [ft]: absolute distance between homeship and intruder
+    /// + `theta`[rad]: angle from homeships heading to intruder in the mathematical sense
+    ///   (counterclockwise)
+    /// + `psi` [rad]:  angle of intruder relative to flight direction of the homeship
+    ///   (mathematical sense => counterclockwise)
+    ///
+    /// Example:
+    ///
+    /// `theta` = 5° => intruder is slidely on the left of the homeships heading.
+    /// `psi` = 90° / pi/2 => intruder is flying to the left perpendicular to the homeships
+    /// heading. See [figure 3](https://arxiv.org/pdf/1912.07084.pdf).
+    ///
+    /// The scores of all advisories are returned. The advisory with the highest score is stored
+    /// as the new `last_advisory`.
+    pub fn process_polar(
+        &mut self,
+        tau: Time,
+        range: Length,
+        theta: Angle,
+        psi: Angle,
+    ) -> AdvisoryScores<HAdvisory> {
+        self.update(evaluate_polar(self.last_advisory, tau, range, theta, psi))
+    }
+
+    /// Same as [`HCas::process_polar`], but rejects inputs the networks can not handle
+    ///
+    /// Every input must be finite, and neither `tau` nor `range` may be negative. A `tau` beyond
+    /// the largest trained tau is accepted and selects the network trained for the largest one,
+    /// see [`crate::HCAS_TAU_SCHEDULE`]. If an error is returned, `last_advisory` is left
+    /// untouched.
+    pub fn try_process_polar(
+        &mut self,
+        tau: Time,
+        range: Length,
+        theta: Angle,
+        psi: Angle,
+    ) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+        try_evaluate_polar(self.last_advisory, tau, range, theta, psi)
+            .and_then(|s| self.try_update(s))
+    }
+
+    pub fn process_cartesian(
+        &mut self,
+        tau: Time,
+        forward_range: Length,
+        left_range: Length,
+        psi: Angle,
+    ) -> AdvisoryScores<HAdvisory> {
+        self.update(evaluate(
+            self.last_advisory,
+            tau,
+            forward_range,
+            left_range,
+            psi,
+        ))
+    }
+
+    /// Same as [`HCas::process_cartesian`], but rejects inputs the networks can not handle
+    ///
+    /// Every input must be finite, and `tau` may not be negative. A `tau` beyond the largest
+    /// trained tau is accepted and selects the network trained for the largest one, see
+    /// [`crate::HCAS_TAU_SCHEDULE`]. If an error is returned, `last_advisory` is left untouched.
+    pub fn try_process_cartesian(
+        &mut self,
+        tau: Time,
+        forward_range: Length,
+        left_range: Length,
+        psi: Angle,
+    ) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+        try_evaluate(self.last_advisory, tau, forward_range, left_range, psi)
+            .and_then(|s| self.try_update(s))
+    }
+
+    /// Like [`HCas::process_cartesian`], but blends the networks trained for the two tau values
+    /// surrounding `tau`
+    ///
+    /// Both networks are evaluated, and their score vectors are linearly interpolated by `tau`
+    /// before the advisory is chosen. This avoids the jumps in the advisory that occur when `tau`
+    /// crosses from one trained tau to the next. Outside of the trained tau values, the nearest
+    /// network is used as is.
+    pub fn process_cartesian_interpolated(
+        &mut self,
+        tau: Time,
+        forward_range: Length,
+        left_range: Length,
+        psi: Angle,
+    ) -> AdvisoryScores<HAdvisory> {
+        self.update(evaluate_interpolated(
+            self.last_advisory,
+            tau,
+            forward_range,
+            left_range,
+            psi,
+        ))
+    }
+
+    /// Same as [`HCas::process_cartesian_interpolated`], but rejects inputs the networks can not
+    /// handle, see [`HCas::try_process_cartesian`]
+    pub fn try_process_cartesian_interpolated(
+        &mut self,
+        tau: Time,
+        forward_range: Length,
+        left_range: Length,
+        psi: Angle,
+    ) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+        try_evaluate_interpolated(self.last_advisory, tau, forward_range, left_range, psi)
+            .and_then(|s| self.try_update(s))
+    }
+
+    /// Stores the advisory with the highest score and passes the scores on
+    fn update(&mut self, scores: AdvisoryScores<HAdvisory>) -> AdvisoryScores<HAdvisory> {
+        self.last_advisory = scores.advisory();
+        scores
+    }
+
+    /// Same as [`HCas::update`], without panicking
+    fn try_update(
+        &mut self,
+        scores: AdvisoryScores<HAdvisory>,
+    ) -> Result<AdvisoryScores<HAdvisory>, CasError> {
+        self.last_advisory = scores.try_advisory()?;
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn stateless_matches_stateful() {
+        let (tau, x, y, psi) = (
+            Time::new::<second>(12.0),
+            Length::new::<foot>(3e3),
+            Length::new::<foot>(500.0),
+            Angle::new::<radian>(-2.5),
+        );
+
+        for &pra in HAdvisory::ALL {
+            let mut hcas = HCas::new(pra);
+            let scores = evaluate(pra, tau, x, y, psi);
+
+            assert_eq!(hcas.process_cartesian(tau, x, y, psi), scores);
+            assert_eq!(hcas.last_advisory, scores.advisory());
+        }
+    }
+
+    #[test]
+    pub fn batch_matches_scalar() {
+        // spans all tau values, and more than one batch per network
+        let intruders: Vec<HIntruder> = (0..200)
+            .map(|i| HIntruder {
+                tau: Time::new::<second>((i % 70) as f32),
+                forward_range: Length::new::<foot>(i as f32 * 100.0 - 5e3),
+                left_range: Length::new::<foot>(2e3 - i as f32 * 30.0),
+                psi: Angle::new::<radian>(i as f32 * 0.1),
+            })
+            .collect();
+
+        let hcas = HCas::new(HAdvisory::StrongRight);
+        let mut scores = vec![AdvisoryScores::new([0.0; 5]); intruders.len()];
+        hcas.evaluate_batch(&intruders, &mut scores);
+
+        for (i, scores) in intruders.iter().zip(scores) {
+            let expected = evaluate(
+                hcas.last_advisory,
+                i.tau,
+                i.forward_range,
+                i.left_range,
+                i.psi,
+            );
+            for (a, b) in scores.scores().iter().zip(expected.scores()) {
+                assert!((a - b).abs() <= 1e-4 * b.abs().max(1.0), "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn try_process_matches_process() {
+        let (tau, x, y, psi) = (
+            Time::new::<second>(20.0),
+            Length::new::<foot>(4e3),
+            Length::new::<foot>(-1e3),
+            Angle::new::<radian>(1.5),
+        );
+        let mut hcas = HCas {
+            last_advisory: HAdvisory::WeakLeft,
+        };
+        let mut try_hcas = hcas;
+        assert_eq!(
+            try_hcas.try_process_cartesian(tau, x, y, psi),
+            Ok(hcas.process_cartesian(tau, x, y, psi))
+        );
+        assert_eq!(try_hcas, hcas);
+    }
+
+    #[test]
+    pub fn reject_garbage_inputs() {
+        let mut hcas = HCas {
+            last_advisory: HAdvisory::StrongRight,
+        };
+        let zero = Length::new::<foot>(0.0);
+        let tau = Time::new::<second>(10.0);
+        let psi = Angle::new::<radian>(0.0);
+
+        assert_eq!(
+            hcas.try_process_cartesian(Time::new::<second>(f32::NAN), zero, zero, psi),
+            Err(CasError::NonFiniteInput(CasInput::Tau))
+        );
+        assert_eq!(
+            hcas.try_process_cartesian(Time::new::<second>(-1.0), zero, zero, psi),
+            Err(CasError::OutOfDomain(CasInput::Tau))
+        );
+        assert_eq!(
+            hcas.try_process_cartesian(tau, Length::new::<foot>(f32::INFINITY), zero, psi),
+            Err(CasError::NonFiniteInput(CasInput::ForwardRange))
+        );
+        assert_eq!(
+            hcas.try_process_polar(tau, Length::new::<foot>(-5.0), psi, psi),
+            Err(CasError::OutOfDomain(CasInput::Range))
+        );
+        assert_eq!(
+            hcas.try_process_polar(tau, zero, psi, Angle::new::<radian>(f32::NAN)),
+            Err(CasError::NonFiniteInput(CasInput::Psi))
+        );
+        assert_eq!(hcas.last_advisory, HAdvisory::StrongRight);
+    }
+
+    #[test]
+    pub fn interpolation_blends_adjacent_networks() {
+        let (x, y, psi) = (
+            Length::new::<foot>(6e3),
+            Length::new::<foot>(1.5e3),
+            Angle::new::<radian>(-2.0),
+        );
+        let pra = HAdvisory::ClearOfConflict;
+
+        // on a trained tau, interpolation changes nothing
+        for tau in HCAS_TAU_SCHEDULE.iter() {
+            assert_eq!(
+                network_scores_interpolated(pra, tau, x, y, psi),
+                network_scores(pra, tau, x, y, psi)
+            );
+        }
+
+        // half way between two trained taus, both networks contribute equally
+        let lower = network_scores(pra, Time::new::<second>(20.0), x, y, psi);
+        let upper = network_scores(pra, Time::new::<second>(30.0), x, y, psi);
+        let blended = network_scores_interpolated(pra, Time::new::<second>(25.0), x, y, psi);
+        assert!((blended - (lower + upper) / 2.0).amax() < 1e-3);
+
+        // approaching the next trained tau converges to its network
+        let almost = network_scores_interpolated(pra, Time::new::<second>(29.999), x, y, psi);
+        assert!((almost - upper).amax() < 1e-2);
+    }
+}
@@ -1,8 +1,11 @@
-use nalgebra::base::{SMatrix, SVector};
+use nalgebra::base::{DMatrixView, SMatrix, SVector};
 
 pub type Matrix<const ROWS: usize, const COLS: usize> = SMatrix<f32, ROWS, COLS>;
 pub type Vector<const ROWS: usize> = SVector<f32, ROWS>;
 
+/// Number of input vectors [`NNet::eval_keyed`] evaluates at once
+pub const BATCH: usize = 16;
+
 /// A simple Neuronal Network
 ///
 /// + `N_INPUT` is the count of input variables
@@ -14,9 +17,9 @@ pub type Vector<const ROWS: usize> = SVector<f32, ROWS>;
 /// The struct fields contain all information about the network.
 ///
 /// + `input_layer` holds the weight matrix and bias vector for calculating the transitions
-///    from the input neurons the the neurons of the first hidden layer.
+///   from the input neurons the the neurons of the first hidden layer.
 /// + `hidden_layers` hold all `N_MAT` matrices and vectors that are necessary for the transitions from
-///    hidden layer 1 to hidden layer n.
+///   hidden layer 1 to hidden layer n.
 /// + `output_layer` contains the weight matrix and bias vector to transition to the output neurons.
 /// + `min_input`, `max_input`, `mean_value` and `range` are necessary to perform input normalization.
 /// + `mean_output` and `range_output` are used to undo normalization for output values.
@@ -84,6 +87,71 @@ impl<const N_INPUT: usize, const N_MAT: usize, const N_NEURON: usize, const N_OU
         output
     }
 
+    /// Evaluates a batch of `N_BATCH` input vectors, given as the columns of `inputs`
+    ///
+    /// Every layer is computed as one matrix-matrix product, which makes much better use of the
+    /// vector units than `N_BATCH` separate calls to [`NNet::eval`]. Column `i` of the result
+    /// equals `self.eval(inputs.column(i))`, up to rounding.
+    pub fn eval_batch<const N_BATCH: usize>(
+        &self,
+        mut inputs: Matrix<N_INPUT, N_BATCH>,
+    ) -> Matrix<N_OUTPUT, N_BATCH> {
+        for mut column in inputs.column_iter_mut() {
+            let mut normalized = column.clone_owned();
+            self.normalize(&mut normalized);
+            column.copy_from(&normalized);
+        }
+
+        let mut accumulator = self.input_layer.apply(&inputs).sup(&Matrix::zeros());
+        for layer in &self.hidden_layers {
+            accumulator = layer.apply(&accumulator).sup(&Matrix::zeros());
+        }
+
+        let mut output = self.output_layer.apply(&accumulator);
+        for mut column in output.column_iter_mut() {
+            let mut undone = column.clone_owned();
+            self.undo_normalize(&mut undone);
+            column.copy_from(&undone);
+        }
+
+        output
+    }
+
+    /// Evaluates every input vector yielded by `inputs`, [`BATCH`] at a time
+    ///
+    /// `inputs` yields pairs of an arbitrary key and the input vector. For every pair, `output`
+    /// is called with the key and the output vector, in the same order.
+    pub fn eval_keyed<K: Copy + Default>(
+        &self,
+        inputs: impl IntoIterator<Item = (K, Vector<N_INPUT>)>,
+        mut output: impl FnMut(K, Vector<N_OUTPUT>),
+    ) {
+        let mut keys = [K::default(); BATCH];
+        let mut batch = Matrix::<N_INPUT, BATCH>::zeros();
+        let mut len = 0;
+
+        let mut flush = |keys: &[K], batch: &Matrix<N_INPUT, BATCH>| {
+            let outputs = self.eval_batch(*batch);
+            for (key, column) in keys.iter().zip(outputs.column_iter()) {
+                output(*key, column.into_owned());
+            }
+        };
+
+        for (key, input) in inputs {
+            keys[len] = key;
+            batch.set_column(len, &input);
+            len += 1;
+
+            if len == BATCH {
+                flush(&keys, &batch);
+                len = 0;
+            }
+        }
+        if len > 0 {
+            flush(&keys[..len], &batch);
+        }
+    }
+
     /// Normalize network inputs:
     ///
     /// The network can only function for values in between -1 and 1.
@@ -106,6 +174,101 @@ impl<const N_INPUT: usize, const N_MAT: usize, const N_NEURON: usize, const N_OU
     }
 }
 
+/// A stack of layers, transforming `I` inputs into `O` outputs
+///
+/// Unlike the layers of [`NNet`], the layers of a stack may differ in width. A stack is built
+/// as a list of [`Hidden`] layers, which is terminated by the output [`Layer`].
+pub trait LayerStack<const I: usize, const O: usize> {
+    /// Passes `inputs` through all layers, with a ReLU activation on every layer but the last
+    fn eval(&self, inputs: Vector<I>) -> Vector<O>;
+
+    /// Calls `f` with the weight matrix and the biases of every layer, from the first hidden
+    /// layer to the output layer
+    fn for_each_layer(&self, f: &mut dyn FnMut(DMatrixView<f32>, &[f32]));
+}
+
+/// A hidden layer of `H` neurons with ReLU activation, followed by the `next` layers
+#[derive(Debug, Clone, Copy)]
+pub struct Hidden<const I: usize, const H: usize, Next> {
+    pub layer: Layer<I, H>,
+    pub next: Next,
+}
+
+/// The output layer, without activation
+impl<const I: usize, const O: usize> LayerStack<I, O> for Layer<I, O> {
+    fn eval(&self, inputs: Vector<I>) -> Vector<O> {
+        self.a * inputs + self.biases
+    }
+
+    fn for_each_layer(&self, f: &mut dyn FnMut(DMatrixView<f32>, &[f32])) {
+        f(
+            DMatrixView::from_slice(self.a.as_slice(), O, I),
+            self.biases.as_slice(),
+        );
+    }
+}
+
+impl<const I: usize, const H: usize, const O: usize, Next: LayerStack<H, O>> LayerStack<I, O>
+    for Hidden<I, H, Next>
+{
+    fn eval(&self, inputs: Vector<I>) -> Vector<O> {
+        let hidden = (self.layer.a * inputs + self.layer.biases).sup(&Vector::zeros());
+        self.next.eval(hidden)
+    }
+
+    fn for_each_layer(&self, f: &mut dyn FnMut(DMatrixView<f32>, &[f32])) {
+        self.layer.for_each_layer(f);
+        self.next.for_each_layer(f);
+    }
+}
+
+/// A neuronal network whose layers may differ in width
+///
+/// + `N_INPUT` is the count of input variables
+/// + `N_OUTPUT` is the number of output variables
+/// + `L` is the [`LayerStack`] from the inputs to the outputs, e.g.
+///   `Hidden<3, 50, Hidden<50, 25, Layer<25, 5>>>` for two hidden layers of 50 and 25 neurons
+///
+/// The normalization is the same as for [`NNet`].
+#[derive(Debug, Clone)]
+pub struct LayeredNNet<const N_INPUT: usize, const N_OUTPUT: usize, L> {
+    pub layers: L,
+    pub min_input: Vector<N_INPUT>,
+    pub max_input: Vector<N_INPUT>,
+    pub mean_value: Vector<N_INPUT>,
+    pub range: Vector<N_INPUT>,
+    pub mean_output: f32,
+    pub range_output: f32,
+}
+
+impl<const N_INPUT: usize, const N_OUTPUT: usize, L: LayerStack<N_INPUT, N_OUTPUT>>
+    LayeredNNet<N_INPUT, N_OUTPUT, L>
+{
+    /// Evaluates the network with specific inputs, see [`NNet::eval`]
+    pub fn eval(&self, inputs: Vector<N_INPUT>) -> Vector<N_OUTPUT> {
+        let normalized = inputs
+            .sup(&self.min_input)
+            .inf(&self.max_input)
+            .map_with_location(|i, _, e| (e - self.mean_value[i]) / self.range[i]);
+
+        (self.layers.eval(normalized) * self.range_output).add_scalar(self.mean_output)
+    }
+}
+
+impl<const INPUT_NEURONS: usize, const OUTPUT_NEURONS: usize> Layer<INPUT_NEURONS, OUTPUT_NEURONS> {
+    /// Computes `a * inputs + biases` for every column of `inputs`
+    fn apply<const N_BATCH: usize>(
+        &self,
+        inputs: &Matrix<INPUT_NEURONS, N_BATCH>,
+    ) -> Matrix<OUTPUT_NEURONS, N_BATCH> {
+        let mut outputs = self.a * inputs;
+        for mut column in outputs.column_iter_mut() {
+            column += self.biases;
+        }
+        outputs
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -161,4 +324,41 @@ mod test {
 
         assert_eq!(output, vector![2345.525, 1355.6001]);
     }
+
+    #[test]
+    fn tapered_inference_example() {
+        let nnet = LayeredNNet {
+            layers: Hidden {
+                layer: Layer {
+                    a: matrix![
+                        1.0, -1.0;
+                        2.0, 0.5;
+                        -1.0, 1.0
+                    ],
+                    biases: vector![0.0, 1.0, 0.5],
+                },
+                next: Layer {
+                    a: matrix![1.0, 2.0, 4.0],
+                    biases: vector![-1.0],
+                },
+            },
+            min_input: vector![-2.0, -2.5],
+            max_input: vector![2.0, 2.5],
+            mean_value: vector![1.0, 0.0],
+            range: vector![2.0, 5.0],
+            mean_output: 1.5,
+            range_output: 2.0,
+        };
+
+        // the first input is clamped to 2 and normalized to 0.5, the second one to -0.5
+        let output = nnet.eval(vector![3.0, -2.5]);
+        // hidden layer: relu([1.0, 1.75, -0.5]) = [1.0, 1.75, 0.0]
+        // output layer: 1.0 + 3.5 + 0.0 - 1.0 = 3.5
+        assert_eq!(output, vector![3.5 * 2.0 + 1.5]);
+
+        let mut widths = Vec::new();
+        nnet.layers
+            .for_each_layer(&mut |a, biases| widths.push((a.ncols(), a.nrows(), biases.len())));
+        assert_eq!(widths, [(2, 3, 3), (3, 1, 1)]);
+    }
 }
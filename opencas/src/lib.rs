@@ -6,20 +6,10 @@
 //! Python and Julia is not sufficient.
 #![cfg_attr(not(test), no_std)]
 
-use core::convert::TryFrom;
-
-use inference::Vector;
-#[cfg(feature = "serde")]
-use serde::{Serialize, Deserialize};
-
-#[allow(unused_imports)]
-use num::Float;
-
-use uom::si::angle::radian;
-use uom::si::f32::*;
-use uom::si::length::foot;
-use uom::si::time::second;
-use uom::si::velocity::foot_per_minute;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(all(feature = "std", not(test)))]
+extern crate std;
 
 /// This module contains autogenerated instances of all nnet files found in the `nnets` directory.
 /// Every nnet file is the written representation of a trained neural network. The nnet file
@@ -30,216 +20,173 @@ use uom::si::velocity::foot_per_minute;
 #[allow(non_upper_case_globals)]
 #[allow(clippy::approx_constant)]
 mod nnets {
-    use crate::inference::{Layer, NNet};
+    use crate::inference::{Hidden, Layer, LayeredNNet, NNet};
+    use crate::tau::TauSchedule;
     use nalgebra::{matrix, vector};
 
+    #[cfg(feature = "fixed")]
+    use crate::fixed::{Fixed, FixedLayer, FixedNNet};
+
     include!(concat!(env!("OUT_DIR"), "/nnets.rs"));
 }
 
 /// This module is inferencing the input data specific to the network with the network itself. In
 /// doing so, the input data will be passed through all network layers and an evaluation will be
 /// given as the network output.
-mod inference;
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-
-/// This will store the last given advisory in order to locate the correct network in the
-/// evaluation.
-pub struct HCas {
-    pub last_advisory: HAdvisory,
-}
-
-/// HAdvisory stores all possible output evaluations.
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum HAdvisory {
-    ClearOfConflict = 0,
-    WeakLeft = 1,
-    WeakRight = 2,
-    StrongLeft = 3,
-    StrongRight = 4,
-}
-
-impl TryFrom<u8> for HAdvisory {
-    type Error = ();
-
-    fn try_from(v: u8) -> Result<Self, Self::Error> {
-        Ok(match v {
-            0 => Self::ClearOfConflict,
-            1 => Self::WeakLeft,
-            2 => Self::WeakRight,
-            3 => Self::StrongLeft,
-            4 => Self::StrongRight,
-            _ => return Err(()),
-        })
-    }
-}
-
-impl HCas {
-    /// HorizontalCAS consists of 40 different neural networks (smaller network = les runtime). The
-    /// splitting parameters are:
-    ///
-    /// + time until impact: tau [sec]
-    /// + previous given advisory: pra [-]
-    ///
-    /// Based on those parameters the right network is chosen.
-    ///
-    /// HorizontalCAS, as we use it, needs three inputs:
-    /// + `range` [ft]: absolute distance between homeship and intruder
-    /// + `theta`[rad]: angle from homeships heading to intruder in the mathematical sense
-    ///   (counterclockwise)
-    /// + `psi` [rad]:  angle of intruder relative to flight direction of the homeship
-    ///   (mathematical sense => counterclockwise)
-    ///
-    /// Example:
-    ///
-    /// `theta` = 5° => intruder is slidely on the left of the homeships heading.
-    /// `psi` = 90° / pi/2 => intruder is flying to the left perpendicular to the homeships
-    /// heading. See [figure 3](https://arxiv.org/pdf/1912.07084.pdf).
-    pub fn process_polar(
-        &mut self,
-        tau: Time,
-        range: Length,
-        theta: Angle,
-        psi: Angle,
-    ) -> (HAdvisory, f32) {
-        self.process_cartesian(
-            tau,
-            range * (theta.get::<radian>().cos()),
-            range * (theta.get::<radian>().sin()),
-            psi,
-        )
-    }
-
-    pub fn process_cartesian(
-        &mut self,
-        tau: Time,
-        forward_range: Length,
-        left_range: Length,
-        psi: Angle,
-    ) -> (HAdvisory, f32) {
-        // match the value of tau to the corresponding tau trained networks
-        let index = match tau.get::<second>() {
-            t if (0.0..5.0).contains(&t) => 0,
-            t if (5.0..10.0).contains(&t) => 1,
-            t if (10.0..15.0).contains(&t) => 2,
-            t if (15.5..20.0).contains(&t) => 3,
-            t if (20.0..30.0).contains(&t) => 4,
-            t if (30.0..40.0).contains(&t) => 5,
-            t if (40.0..60.0).contains(&t) => 6,
-            _ => 7,
-        };
-
-        // find the correct network by using the last given advisory and the tau index
-        let nnet = &nnets::HCAS_NNETS[self.last_advisory as usize][index];
-
-        // generate the network inputs as a vector [x,y,psi]
-        let inputs: Vector<3> = nalgebra::vector![
-            forward_range.get::<foot>(),
-            left_range.get::<foot>(),
-            psi.get::<radian>()
-        ];
-
-        // do the actual evalutaion (see inference.rs)
-        let evaluated = nnet.eval(inputs);
-
-        // find the highest value in the returning vector
-        let priority = evaluated.max();
-
-        // find the index of said highest value and map the index to the possible advisories
-        // the unwrap will never actually panic
-        self.last_advisory = (evaluated.imax() as u8).try_into().unwrap();
-        (self.last_advisory, priority)
-    }
-}
-
-//***** Here begins the verticalCAS *****//
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-
-/// This will store the last given advisory in order to locate the correct network in the
-/// evaluation.
-pub struct VCas {
-    pub last_advisory: VAdvisory,
-}
-
-/// VAdvisory stores all possible output evaluations.
-#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum VAdvisory {
-    ClearOfConflict = 0,
-    DoNotClimb = 1,
-    DoNotDescend = 2,
-    Descend1500 = 3,
-    Climb1500 = 4,
-    StrengthenDescend1500 = 5,
-    StrengthenClimb1500 = 6,
-    StrengthenDescend2500 = 7,
-    StrengthenClimb2500 = 8,
-}
-
-impl TryFrom<u8> for VAdvisory {
-    type Error = ();
-
-    fn try_from(v: u8) -> Result<Self, Self::Error> {
-        Ok(match v {
-            0 => Self::ClearOfConflict,
-            1 => Self::DoNotClimb,
-            2 => Self::DoNotDescend,
-            3 => Self::Descend1500,
-            4 => Self::Climb1500,
-            5 => Self::StrengthenDescend1500,
-            6 => Self::StrengthenClimb1500,
-            7 => Self::StrengthenDescend2500,
-            8 => Self::StrengthenClimb2500,
-            _ => return Err(()),
-        })
-    }
-}
-
-impl VCas {
-    /// The VerticalCAS contains 9 different networks.
-    ///
-    /// There are 4 specific inputs:
-    /// + Relative intruder altitude [ft]: vertical distance between intruder and homeship
-    /// + Vertical speed of homeship [ft/min]
-    /// + Vertical speed of intruder [ft/min]
-    /// + time until horizontal seperation loss: tau [sec]
-    pub fn process(
-        &mut self,
-        height: Length,
-        vertical_speed_homeship: Velocity,
-        vertical_speed_intruder: Velocity,
-        tau: Time,
-    ) -> (VAdvisory, f32) {
-        // find the correct network by selecting the last given advisory
-        let nnet = &nnets::VCAS_NNETS[self.last_advisory as usize];
-
-        // generate input vector for network
-        let inputs: Vector<4> = nalgebra::vector![
-            height.get::<foot>(),
-            vertical_speed_homeship.get::<foot_per_minute>(),
-            vertical_speed_intruder.get::<foot_per_minute>(),
-            tau.get::<second>()
-        ];
-
-        // evaluate the network
-        let evaluated = nnet.eval(inputs);
-
-        // find highest value within the the return vector
-        let priority = evaluated.max();
-
-        // find index of said highest value and map to the possible advisories
-        // the unwrap will never actually panic
-        self.last_advisory = (evaluated.imax() as u8).try_into().unwrap();
-        (self.last_advisory, priority)
-    }
-}
+pub mod inference;
+
+/// This module bounds the outputs of a network over whole boxes of inputs.
+///
+/// An axis-aligned box of inputs is propagated through [`NNet`](inference::NNet), including the
+/// clamping and normalization of the inputs and the undoing of the normalization on the outputs.
+/// The result is an [`Interval`](analysis::Interval) per output, which is guaranteed to contain
+/// the output of the network for every input in the box.
+///
+/// Two methods are available, see [`Method`](analysis::Method). Every floating point operation is
+/// rounded outward by one unit in the last place, so that the bounds hold for the exact real
+/// valued network despite the rounding of `f32` arithmetic.
+pub mod analysis;
+
+/// This module evaluates quantized versions of the networks in fixed point arithmetic, for
+/// targets without a floating point unit.
+#[cfg(feature = "fixed")]
+pub mod fixed;
+
+/// This module contains helpers around tau, the time until loss of separation.
+pub mod tau;
+pub use nnets::HCAS_TAU_SCHEDULE;
+
+pub use nnets::extra;
+
+/// This module contains the full score vector returned by a network evaluation.
+mod scores;
+pub use scores::{Advisory, AdvisoryScores, Ranked};
+
+/// This module contains the error types reported by the fallible functions of this crate.
+mod error;
+pub use error::{CasError, CasInput};
+
+/// This module contains the HorizontalCAS, both as stateless functions taking the previous
+/// advisory explicitly and as the stateful [`HCas`].
+pub mod hcas;
+pub use hcas::{HAdvisory, HCas};
+
+/// This module contains the VerticalCAS, both as stateless functions taking the previous advisory
+/// explicitly and as the stateful [`VCas`].
+pub mod vcas;
+pub use vcas::{VAdvisory, VCas};
+
+/// This module resolves encounters with several intruders at once.
+pub mod multi;
+pub use multi::{HCasMulti, VCasMulti};
+
+/// This module coordinates the HorizontalCAS and the VerticalCAS into one resolution.
+pub mod combined;
+pub use combined::CombinedCas;
+
+/// This module derives the network inputs from the state vectors of homeship and intruder.
+pub mod encounter;
+
+/// This module converts between WGS84 geodetic, earth-centered and local coordinates.
+pub mod geodesy;
+
+/// This module smoothes noisy surveillance reports of intruders into tracks.
+pub mod tracking;
+
+/// This module decodes ADS-B extended squitters into intruder states.
+#[cfg(feature = "adsb")]
+pub mod adsb;
+
+/// This module filters advisories over time, to keep them from chattering.
+pub mod filter;
+
+/// This module contains the ACAS Xu, which is only available if its networks are placed in
+/// `nnets/acasxu` (see the README there). The unit tests fall back to a synthetic fixture.
+#[cfg(any(acasxu, test))]
+pub mod acasxu;
+#[cfg(any(acasxu, test))]
+pub use acasxu::{AcasXu, AcasXuAdvisory};
+#[cfg(any(acasxu, test))]
+pub use nnets::ACASXU_TAU_SCHEDULE;
+
+/// This module simulates encounters in a closed loop, with the homeship following the advisories.
+#[cfg(feature = "alloc")]
+pub mod sim;
+
+/// This module draws random encounters and estimates safety metrics over many simulations.
+#[cfg(feature = "std")]
+pub mod montecarlo;
+
+/// This module runs encounter scenarios from TOML files and checks the advisories they expect.
+#[cfg(feature = "scenario")]
+pub mod scenario;
+
+/// This module proves properties of the advisories over whole regions of inputs, or finds
+/// counterexamples.
+#[cfg(feature = "std")]
+pub mod verify;
+
+/// This module reads and writes properties in the VNN-LIB format, and checks them with the
+/// verifier.
+///
+/// VNN-LIB is the subset of SMT-LIB the neural network verification community exchanges
+/// properties in. A property declares the inputs `X_i` and the outputs `Y_j` of a network, bounds
+/// every input to an interval and constrains the outputs. The output constraints describe a
+/// violation: the property holds if no input within the bounds leads to outputs satisfying them.
+///
+/// [`Spec::parse`](vnnlib::Spec::parse) reads such a property,
+/// [`Spec::from_property`](vnnlib::Spec::from_property) converts a
+/// [`Property`](verify::Property) of the verifier, and the `Display` implementation writes the
+/// VNN-LIB text. Written properties refer to the model exported by [`onnx::export`] with
+/// [`Normalization::Folded`](onnx::Normalization::Folded), so that they can be run in external
+/// tools. [`Verifier::check_spec`](verify::Verifier::check_spec) checks them with the verifier of
+/// this crate instead.
+///
+/// Only conjunctions and disjunctions of linear constraints are supported. The inputs must be
+/// bounded by constants, and must not appear within a disjunction. Strict comparisons are treated
+/// like non-strict ones. Literals are read as `f64`, and the bounds are rounded outward to `f32`,
+/// so that a proof of the parsed property also holds for the written one.
+///
+/// For more information on the format, read up [here](https://www.vnnlib.org/).
+#[cfg(feature = "std")]
+pub mod vnnlib;
+
+/// This module parses `.nnet` files at runtime, so that networks can be swapped without
+/// recompiling the crate.
+///
+/// The networks in `nnets` are baked into the binary by `build.rs`. This module offers the same
+/// parsing at runtime, so that a retrained network can be evaluated without recompiling the
+/// crate. As the dimensions of such a network are only known at runtime, it is stored in a
+/// [`DynNNet`](loader::DynNNet) instead of the statically sized `inference::NNet`.
+///
+/// For more information on the file format, read up [here](https://github.com/sisl/nnet).
+#[cfg(feature = "alloc")]
+pub mod loader;
+
+/// This module exports networks to ONNX, imports feed-forward networks from ONNX, and evaluates
+/// ONNX models without an external runtime.
+///
+/// Only the subset of ONNX needed by feed-forward ReLU networks is covered. The protobuf encoding
+/// is written and read by hand, so that neither a code generator nor any further dependency is
+/// necessary. [`export`](onnx::export) writes a network as a chain of `Gemm` and `Relu` nodes,
+/// which every ONNX runtime and most verification tools understand.
+/// [`Model::parse`](onnx::Model::parse) and [`Model::eval`](onnx::Model::eval) read and evaluate
+/// such models, which allows to check exported models without an external runtime.
+/// [`import`](onnx::import) reads feed-forward networks of any layer widths from models written
+/// by other tools.
+///
+/// For more information on the format, read up [here](https://onnx.ai/onnx/repo-docs/IR.html).
+#[cfg(feature = "alloc")]
+pub mod onnx;
 
 #[cfg(test)]
 mod test {
     use super::*;
     use core::mem::size_of_val;
+    use uom::si::f32::*;
+    use uom::si::length::foot;
+    use uom::si::time::second;
+    use uom::si::velocity::foot_per_minute;
 
     #[test]
     pub fn check_hcas_size() {
@@ -264,13 +211,23 @@ mod test {
         let mut vcas = VCas {
             last_advisory: VAdvisory::StrengthenDescend2500,
         };
-        let (adv, value) = vcas.process(
+        let scores = vcas.process(
             Length::new::<foot>(0.0),
             Velocity::new::<foot_per_minute>(0.0),
             Velocity::new::<foot_per_minute>(0.0),
             Time::new::<second>(15.0),
         );
 
-        println!("adv: {:#?} and value: {:#?}", adv, value);
+        println!(
+            "adv: {:#?} and value: {:#?}",
+            scores.advisory(),
+            scores.best_score()
+        );
+    }
+
+    #[test]
+    pub fn invalid_advisory() {
+        assert_eq!(HAdvisory::try_from(5), Err(CasError::InvalidAdvisory(5)));
+        assert_eq!(VAdvisory::try_from(9), Err(CasError::InvalidAdvisory(9)));
     }
 }
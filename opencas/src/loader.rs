@@ -0,0 +1,446 @@
+use alloc::vec::Vec;
+use core::{fmt, str::FromStr};
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::inference::{LayerStack, LayeredNNet, NNet};
+
+/// Errors that may occur while parsing a `.nnet` file
+#[derive(Debug)]
+pub enum NNetParseError {
+    /// The underlying reader failed
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+
+    /// The file ended before all layers were read
+    UnexpectedEof,
+
+    /// A field in the given line could not be parsed as a number
+    InvalidNumber { line: usize },
+
+    /// The given line does not contain the expected number of elements
+    ElementCount {
+        line: usize,
+        expected: usize,
+        found: usize,
+    },
+
+    /// The header in the given line contradicts an earlier header line
+    InconsistentHeader { line: usize },
+}
+
+impl fmt::Display for NNetParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            Self::Io(e) => write!(f, "failed to read nnet file: {e}"),
+            Self::UnexpectedEof => write!(f, "unexpected end of nnet file"),
+            Self::InvalidNumber { line } => write!(f, "invalid number in line {line}"),
+            Self::ElementCount {
+                line,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {expected} elements in line {line}, but found {found} elements"
+            ),
+            Self::InconsistentHeader { line } => {
+                write!(
+                    f,
+                    "header in line {line} contradicts the network dimensions"
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for NNetParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<std::io::Error> for NNetParseError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+/// One layer of a dynamically sized network, consisting of a matrix of weights and a vector of
+/// biases.
+///
+/// + The matrix is of the dimension output neurons rows x input neurons columns
+/// + The vector is of the dimension output neurons
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynLayer {
+    pub a: DMatrix<f32>,
+    pub biases: DVector<f32>,
+}
+
+/// A neuronal network whose dimensions are only known at runtime
+///
+/// This is the runtime counterpart to `NNet`. `layers` holds every transition, starting with
+/// the one from the input neurons to the first hidden layer and ending with the one to the output
+/// neurons. The remaining fields are used for input normalization and to undo normalization on
+/// the output values, exactly as in `NNet`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DynNNet {
+    pub layers: Vec<DynLayer>,
+    pub min_input: DVector<f32>,
+    pub max_input: DVector<f32>,
+    pub mean_value: DVector<f32>,
+    pub range: DVector<f32>,
+    pub mean_output: f32,
+    pub range_output: f32,
+}
+
+/// Converts a line into a vector of `T`.
+///
+/// Empty fields are filtered, and the number of remaining elements is checked against
+/// `expected`. This mirrors the `line_to_vec!` macro in `build.rs`.
+fn line_to_vec<T: FromStr>(
+    line: Option<(usize, &str)>,
+    expected: usize,
+) -> Result<Vec<T>, NNetParseError> {
+    let (line_no, line) = line.ok_or(NNetParseError::UnexpectedEof)?;
+
+    let elements = line
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse())
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|_| NNetParseError::InvalidNumber { line: line_no })?;
+
+    if elements.len() != expected {
+        return Err(NNetParseError::ElementCount {
+            line: line_no,
+            expected,
+            found: elements.len(),
+        });
+    }
+
+    Ok(elements)
+}
+
+impl DynNNet {
+    /// Parse the content of a `.nnet` file
+    ///
+    /// Leading comment lines (starting with `//`) are skipped. All header lines and weight rows
+    /// are checked to contain exactly the number of elements the header announces. Dimensions in
+    /// the header which overflow are rejected, and no more memory is reserved than the remaining
+    /// input could fill.
+    pub fn parse(source: &str) -> Result<Self, NNetParseError> {
+        // stupid humans count from one
+        let mut lines = source
+            .lines()
+            .enumerate()
+            .map(|(line_no, line)| (line_no + 1, line.trim()))
+            .skip_while(|(_, line)| line.starts_with("//"));
+
+        // parse header
+        let header = lines.next();
+        let header_line = header.map_or(0, |(line_no, _)| line_no);
+        let [num_layer, n_input, n_output, _n_neuron]: [usize; 4] =
+            line_to_vec(header, 4)?.try_into().unwrap();
+        if num_layer == 0 {
+            return Err(NNetParseError::InconsistentHeader { line: header_line });
+        }
+
+        let n_layer_sizes = num_layer
+            .checked_add(1)
+            .ok_or(NNetParseError::InconsistentHeader { line: header_line })?;
+        let n_normalization = n_input
+            .checked_add(1)
+            .ok_or(NNetParseError::InconsistentHeader { line: header_line })?;
+
+        let nodes_per_layer: Vec<usize> = line_to_vec(lines.next(), n_layer_sizes)?;
+        if nodes_per_layer[0] != n_input || nodes_per_layer[num_layer] != n_output {
+            return Err(NNetParseError::InconsistentHeader {
+                line: header_line + 1,
+            });
+        }
+
+        lines.next().ok_or(NNetParseError::UnexpectedEof)?; // can be ignored
+        let min_input: Vec<f32> = line_to_vec(lines.next(), n_input)?;
+        let max_input: Vec<f32> = line_to_vec(lines.next(), n_input)?;
+        let mut mean: Vec<f32> = line_to_vec(lines.next(), n_normalization)?;
+        let mut range: Vec<f32> = line_to_vec(lines.next(), n_normalization)?;
+
+        // every number takes at least one byte, which bounds what the remaining lines can hold
+        let max_elements = source.len();
+
+        // parse data aka the rest of the file
+        let mut layers = Vec::with_capacity(num_layer.min(max_elements));
+        for dims in nodes_per_layer.windows(2) {
+            let (num_cols, num_rows) = (dims[0], dims[1]);
+
+            let num_weights =
+                num_rows
+                    .checked_mul(num_cols)
+                    .ok_or(NNetParseError::InconsistentHeader {
+                        line: header_line + 1,
+                    })?;
+            let mut weights = Vec::with_capacity(num_weights.min(max_elements));
+            for _ in 0..num_rows {
+                weights.extend(line_to_vec::<f32>(lines.next(), num_cols)?);
+            }
+
+            let mut biases = Vec::with_capacity(num_rows.min(max_elements));
+            for _ in 0..num_rows {
+                biases.extend(line_to_vec::<f32>(lines.next(), 1)?);
+            }
+
+            layers.push(DynLayer {
+                a: DMatrix::from_row_slice(num_rows, num_cols, &weights),
+                biases: DVector::from_vec(biases),
+            });
+        }
+
+        let mean_output = mean.pop().unwrap();
+        let range_output = range.pop().unwrap();
+
+        Ok(Self {
+            layers,
+            min_input: DVector::from_vec(min_input),
+            max_input: DVector::from_vec(max_input),
+            mean_value: DVector::from_vec(mean),
+            range: DVector::from_vec(range),
+            mean_output,
+            range_output,
+        })
+    }
+
+    /// Read and parse a `.nnet` file from `reader`
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> Result<Self, NNetParseError> {
+        let mut source = std::string::String::new();
+        reader.read_to_string(&mut source)?;
+        Self::parse(&source)
+    }
+
+    /// Number of input variables
+    pub fn n_input(&self) -> usize {
+        self.min_input.len()
+    }
+
+    /// Number of output variables
+    pub fn n_output(&self) -> usize {
+        self.layers.last().map_or(0, |l| l.biases.len())
+    }
+
+    /// Evaluates the network with specific inputs
+    ///
+    /// This follows the very same steps as `NNet::eval`: the inputs are clamped and normalized,
+    /// passed through all layers with a ReLU activation on every layer but the output layer, and
+    /// finally the normalization is undone on the outputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inputs` does not contain exactly [`Self::n_input`] elements.
+    pub fn eval(&self, inputs: &[f32]) -> DVector<f32> {
+        assert_eq!(
+            inputs.len(),
+            self.n_input(),
+            "expected {} inputs, but got {}",
+            self.n_input(),
+            inputs.len()
+        );
+
+        let mut accumulator = self.normalize(inputs);
+
+        let (output_layer, hidden_layers) = self
+            .layers
+            .split_last()
+            .expect("a network has at least one layer");
+
+        for layer in hidden_layers {
+            accumulator =
+                (&layer.a * accumulator + &layer.biases).sup(&DVector::zeros(layer.biases.len()));
+        }
+
+        let output = &output_layer.a * accumulator + &output_layer.biases;
+
+        self.undo_normalize(output)
+    }
+
+    /// Normalize network inputs, see `NNet` for details
+    fn normalize(&self, inputs: &[f32]) -> DVector<f32> {
+        DVector::from_column_slice(inputs)
+            .sup(&self.min_input)
+            .inf(&self.max_input)
+            .map_with_location(|i, _, e| (e - self.mean_value[i]) / self.range[i])
+    }
+
+    /// Undo normalization on network outputs, see `NNet` for details
+    fn undo_normalize(&self, outputs: DVector<f32>) -> DVector<f32> {
+        (outputs * self.range_output).add_scalar(self.mean_output)
+    }
+}
+
+impl<const N_INPUT: usize, const N_MAT: usize, const N_NEURON: usize, const N_OUTPUT: usize>
+    From<&NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>> for DynNNet
+{
+    fn from(nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>) -> Self {
+        let input_layer = DynLayer {
+            a: DMatrix::from_column_slice(N_NEURON, N_INPUT, nnet.input_layer.a.as_slice()),
+            biases: DVector::from_column_slice(nnet.input_layer.biases.as_slice()),
+        };
+        let hidden_layers = nnet.hidden_layers.iter().map(|layer| DynLayer {
+            a: DMatrix::from_column_slice(N_NEURON, N_NEURON, layer.a.as_slice()),
+            biases: DVector::from_column_slice(layer.biases.as_slice()),
+        });
+        let output_layer = DynLayer {
+            a: DMatrix::from_column_slice(N_OUTPUT, N_NEURON, nnet.output_layer.a.as_slice()),
+            biases: DVector::from_column_slice(nnet.output_layer.biases.as_slice()),
+        };
+
+        Self {
+            layers: core::iter::once(input_layer)
+                .chain(hidden_layers)
+                .chain(core::iter::once(output_layer))
+                .collect(),
+            min_input: DVector::from_column_slice(nnet.min_input.as_slice()),
+            max_input: DVector::from_column_slice(nnet.max_input.as_slice()),
+            mean_value: DVector::from_column_slice(nnet.mean_value.as_slice()),
+            range: DVector::from_column_slice(nnet.range.as_slice()),
+            mean_output: nnet.mean_output,
+            range_output: nnet.range_output,
+        }
+    }
+}
+
+impl<const N_INPUT: usize, const N_OUTPUT: usize, L: LayerStack<N_INPUT, N_OUTPUT>>
+    From<&LayeredNNet<N_INPUT, N_OUTPUT, L>> for DynNNet
+{
+    fn from(nnet: &LayeredNNet<N_INPUT, N_OUTPUT, L>) -> Self {
+        let mut layers = Vec::new();
+        nnet.layers.for_each_layer(&mut |a, biases| {
+            layers.push(DynLayer {
+                a: a.into_owned(),
+                biases: DVector::from_column_slice(biases),
+            })
+        });
+
+        Self {
+            layers,
+            min_input: DVector::from_column_slice(nnet.min_input.as_slice()),
+            max_input: DVector::from_column_slice(nnet.max_input.as_slice()),
+            mean_value: DVector::from_column_slice(nnet.mean_value.as_slice()),
+            range: DVector::from_column_slice(nnet.range.as_slice()),
+            mean_output: nnet.mean_output,
+            range_output: nnet.range_output,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nnets;
+    use nalgebra::vector;
+
+    const HCAS_PRA0_TAU00: &str = include_str!("../nnets/HCAS_rect_v6_pra0_tau00_25HU_3000.nnet");
+    const VCAS_PRA01: &str = include_str!("../nnets/VertCAS_pra01_v4_45HU_200.nnet");
+
+    #[test]
+    fn parsed_equals_generated() {
+        let hcas = DynNNet::parse(HCAS_PRA0_TAU00).unwrap();
+        assert_eq!(hcas, DynNNet::from(&nnets::HCAS_NNETS[0][0]));
+
+        let vcas = DynNNet::parse(VCAS_PRA01).unwrap();
+        assert_eq!(vcas, DynNNet::from(&nnets::VCAS_NNETS[0]));
+    }
+
+    #[test]
+    fn eval_matches_static_network() {
+        let dyn_nnet = DynNNet::parse(HCAS_PRA0_TAU00).unwrap();
+        let nnet = &nnets::HCAS_NNETS[0][0];
+
+        for input in [
+            [0.0, 0.0, 0.0],
+            [5e3, -2e3, 1.0],
+            [-7e4, 7e4, -4.0],
+            [12e3, 800.0, 3.0],
+        ] {
+            let expected = nnet.eval(vector![input[0], input[1], input[2]]);
+            assert_eq!(dyn_nnet.eval(&input).as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn parsed_tapered_equals_generated() {
+        let source = include_str!("../nnets/extra/tapered_example.nnet");
+        let dyn_nnet = DynNNet::parse(source).unwrap();
+        let nnet = &nnets::extra::TAPERED_EXAMPLE_NNET;
+        assert_eq!(dyn_nnet, DynNNet::from(nnet));
+
+        let widths: Vec<usize> = dyn_nnet.layers.iter().map(|l| l.a.nrows()).collect();
+        assert_eq!(widths, [6, 4, 2]);
+        for input in [[0.0, 0.0, 0.0], [0.4, -0.8, 0.1], [-2.0, 3.0, 0.5]] {
+            let expected = nnet.eval(vector![input[0], input[1], input[2]]);
+            assert_eq!(dyn_nnet.eval(&input).as_slice(), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn reject_wrong_element_count() {
+        let broken = HCAS_PRA0_TAU00.replacen("3,25,25,25,25,25,5,", "3,25,25,25,25,5,", 1);
+        assert!(matches!(
+            DynNNet::parse(&broken),
+            Err(NNetParseError::ElementCount {
+                line: 3,
+                expected: 7,
+                found: 6
+            })
+        ));
+    }
+
+    #[test]
+    fn reject_truncated_file() {
+        let truncated = &HCAS_PRA0_TAU00[..HCAS_PRA0_TAU00.len() / 2];
+        let truncated = &truncated[..truncated.rfind('\n').unwrap()];
+        assert!(matches!(
+            DynNNet::parse(truncated),
+            Err(NNetParseError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn reject_hostile_header() {
+        assert!(matches!(
+            DynNNet::parse("18446744073709551615,3,5,25,\n"),
+            Err(NNetParseError::InconsistentHeader { line: 1 })
+        ));
+        assert!(matches!(
+            DynNNet::parse("2,18446744073709551615,5,25,\n"),
+            Err(NNetParseError::InconsistentHeader { line: 1 })
+        ));
+
+        // a hidden layer too large to ever be allocated
+        let huge = "2,1,1,1000000000000,\n1,1000000000000,1,\n0,\n0,\n1,\n0,0,\n1,1,\n";
+        assert!(matches!(
+            DynNNet::parse(huge),
+            Err(NNetParseError::UnexpectedEof)
+        ));
+
+        let overflow = "2,2,1,1,\n2,9223372036854775808,1,\n0,\n0,0,\n1,1,\n0,0,0,\n1,1,1,\n";
+        assert!(matches!(
+            DynNNet::parse(overflow),
+            Err(NNetParseError::InconsistentHeader { line: 2 })
+        ));
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn read_from_reader() {
+        let nnet = DynNNet::from_reader(std::io::Cursor::new(VCAS_PRA01)).unwrap();
+        assert_eq!(nnet.n_input(), 4);
+        assert_eq!(nnet.n_output(), 9);
+    }
+}
@@ -0,0 +1,455 @@
+use alloc::vec::Vec;
+
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+use uom::si::velocity::meter_per_second;
+
+use crate::combined::Maneuver;
+use crate::filter::Directional;
+use crate::sim::{Aircraft, SimConfig, Simulation, Trajectory};
+
+/// The SplitMix64 generator, small and fast, and good enough for drawing encounters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A uniformly distributed number in `low..high`
+    pub fn uniform(&mut self, low: f32, high: f32) -> f32 {
+        // the upper 24 bits fill the mantissa of an f32 exactly
+        let unit = (self.next_u64() >> 40) as f32 / (1u32 << 24) as f32;
+        low + (high - low) * unit
+    }
+}
+
+/// Ranges the parameters of an encounter are drawn from, uniformly
+///
+/// Each range is given as `(low, high)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncounterDistribution {
+    /// Time from the start of the simulation until the closest point of approach
+    pub time_to_cpa: (Time, Time),
+
+    /// Horizontal miss distance at the closest point of approach, negative to the left
+    pub horizontal_miss: (Length, Length),
+
+    /// Altitude of the intruder minus the altitude of the homeship at the closest point of
+    /// approach
+    pub vertical_miss: (Length, Length),
+
+    /// Heading of the intruder relative to the heading of the homeship
+    pub closure_angle: (Angle, Angle),
+
+    pub ownship_speed: (Velocity, Velocity),
+    pub intruder_speed: (Velocity, Velocity),
+    pub ownship_vertical_rate: (Velocity, Velocity),
+    pub intruder_vertical_rate: (Velocity, Velocity),
+}
+
+impl Default for EncounterDistribution {
+    fn default() -> Self {
+        let m = Length::new::<meter>;
+        let mps = Velocity::new::<meter_per_second>;
+        Self {
+            time_to_cpa: (Time::new::<second>(40.0), Time::new::<second>(60.0)),
+            horizontal_miss: (m(-300.0), m(300.0)),
+            vertical_miss: (m(-60.0), m(60.0)),
+            closure_angle: (
+                Angle::new::<radian>(-core::f32::consts::PI),
+                Angle::new::<radian>(core::f32::consts::PI),
+            ),
+            ownship_speed: (mps(50.0), mps(150.0)),
+            intruder_speed: (mps(50.0), mps(150.0)),
+            ownship_vertical_rate: (mps(-5.0), mps(5.0)),
+            intruder_vertical_rate: (mps(-5.0), mps(5.0)),
+        }
+    }
+}
+
+/// Draws reproducible random encounters
+///
+/// Every encounter is drawn from its own generator, seeded from the seed and the index of the
+/// encounter. So an encounter only depends on the seed and its index, not on the order the
+/// encounters are drawn in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EncounterGenerator {
+    pub distribution: EncounterDistribution,
+    pub seed: u64,
+}
+
+impl EncounterGenerator {
+    pub const fn new(distribution: EncounterDistribution, seed: u64) -> Self {
+        Self { distribution, seed }
+    }
+
+    /// The homeship and the intruder of the encounter with the given index
+    ///
+    /// The homeship starts at the origin heading north, the intruder is placed such that both
+    /// fly into the drawn closest point of approach if neither maneuvers.
+    pub fn encounter(&self, index: u64) -> (Aircraft, Aircraft) {
+        let mut rng = SplitMix64::new(self.seed ^ SplitMix64::new(index).next_u64());
+        let d = &self.distribution;
+        let mut draw = |(low, high): (f32, f32)| rng.uniform(low, high);
+
+        let t = draw((
+            d.time_to_cpa.0.get::<second>(),
+            d.time_to_cpa.1.get::<second>(),
+        ));
+        let hmd = draw(meters(d.horizontal_miss));
+        let vmd = draw(meters(d.vertical_miss));
+        let closure = draw((
+            d.closure_angle.0.get::<radian>(),
+            d.closure_angle.1.get::<radian>(),
+        ));
+        let own_speed = draw(mps(d.ownship_speed));
+        let int_speed = draw(mps(d.intruder_speed));
+        let own_vs = draw(mps(d.ownship_vertical_rate));
+        let int_vs = draw(mps(d.intruder_vertical_rate));
+
+        // the relative velocity in east, north, and the miss distance perpendicular to it
+        let v_int = [int_speed * closure.sin(), int_speed * closure.cos()];
+        let v_rel = [v_int[0], v_int[1] - own_speed];
+        let norm = v_rel[0].hypot(v_rel[1]).max(f32::EPSILON);
+        let miss = [-v_rel[1] / norm * hmd, v_rel[0] / norm * hmd];
+
+        // the homeship is at (0, own_speed * t) at the closest point of approach
+        let cpa_int = [miss[0], own_speed * t + miss[1], own_vs * t + vmd];
+        let start = [
+            cpa_int[0] - v_int[0] * t,
+            cpa_int[1] - v_int[1] * t,
+            cpa_int[2] - int_vs * t,
+        ];
+
+        let mut ownship = Aircraft::new(
+            [0.0; 3].map(Length::new::<meter>),
+            Velocity::new::<meter_per_second>(own_speed),
+            Angle::new::<radian>(0.0),
+        );
+        ownship.vertical_speed = Velocity::new::<meter_per_second>(own_vs);
+
+        let mut intruder = Aircraft::new(
+            start.map(Length::new::<meter>),
+            Velocity::new::<meter_per_second>(int_speed),
+            Angle::new::<radian>(closure),
+        );
+        intruder.vertical_speed = Velocity::new::<meter_per_second>(int_vs);
+
+        (ownship, intruder)
+    }
+}
+
+fn meters((low, high): (Length, Length)) -> (f32, f32) {
+    (low.get::<meter>(), high.get::<meter>())
+}
+
+fn mps((low, high): (Velocity, Velocity)) -> (f32, f32) {
+    (
+        low.get::<meter_per_second>(),
+        high.get::<meter_per_second>(),
+    )
+}
+
+/// The outcome of one encounter, flown with and without responding to the advisories
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Outcome {
+    /// Whether a near mid-air collision happened while following the advisories
+    pub nmac: bool,
+
+    /// Whether a near mid-air collision happened while ignoring the advisories
+    pub unequipped_nmac: bool,
+
+    /// Whether any advisory other than clear of conflict was issued
+    pub alerted: bool,
+
+    /// Number of sense reversals of the issued advisories
+    pub reversals: u32,
+
+    /// Number of strengthenings of the issued advisories
+    pub strengthenings: u32,
+}
+
+impl Outcome {
+    /// Whether following the advisories caused a near mid-air collision
+    pub fn is_induced_nmac(&self) -> bool {
+        self.nmac && !self.unequipped_nmac
+    }
+
+    fn new(equipped: &Trajectory, unequipped: &Trajectory) -> Self {
+        let (h_reversals, h_strengthenings) =
+            transitions(equipped.samples.iter().map(|s| s.horizontal));
+        let (v_reversals, v_strengthenings) =
+            transitions(equipped.samples.iter().map(|s| s.vertical));
+
+        Self {
+            nmac: equipped.nmac(),
+            unequipped_nmac: unequipped.nmac(),
+            alerted: equipped
+                .samples
+                .iter()
+                .any(|s| s.horizontal.sense().is_some() || s.vertical.sense().is_some()),
+            reversals: h_reversals + v_reversals,
+            strengthenings: h_strengthenings + v_strengthenings,
+        }
+    }
+}
+
+/// Counts the sense reversals and the strengthenings in a sequence of advisories
+///
+/// A reversal is counted against the last advisory with a sense, so also through clear of
+/// conflict. A strengthening has to follow its weaker advisory directly.
+fn transitions<A: Directional>(advisories: impl Iterator<Item = A>) -> (u32, u32) {
+    let (mut reversals, mut strengthenings) = (0, 0);
+    let mut last: Option<A> = None;
+    let mut last_sense = None;
+
+    for advisory in advisories {
+        if let Some(sense) = advisory.sense() {
+            if last_sense.is_some_and(|last_sense| last_sense != sense) {
+                reversals += 1;
+            } else if last.is_some_and(|last| {
+                last.sense() == Some(sense) && advisory.strength() > last.strength()
+            }) {
+                strengthenings += 1;
+            }
+            last_sense = Some(sense);
+        }
+        last = Some(advisory);
+    }
+
+    (reversals, strengthenings)
+}
+
+/// Safety metrics summed over a batch of encounters
+///
+/// Only counts are summed, so the metrics are bit-identical regardless of the number of threads.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Metrics {
+    pub encounters: u64,
+    pub nmacs: u64,
+    pub unequipped_nmacs: u64,
+    pub induced_nmacs: u64,
+    pub alerts: u64,
+    pub reversals: u64,
+    pub strengthenings: u64,
+}
+
+impl Metrics {
+    /// Probability of a near mid-air collision while following the advisories
+    pub fn nmac_probability(&self) -> f64 {
+        ratio(self.nmacs, self.encounters)
+    }
+
+    /// Probability of a near mid-air collision while ignoring the advisories
+    pub fn unequipped_nmac_probability(&self) -> f64 {
+        ratio(self.unequipped_nmacs, self.encounters)
+    }
+
+    /// Fraction of the encounters with an alert
+    pub fn alert_rate(&self) -> f64 {
+        ratio(self.alerts, self.encounters)
+    }
+
+    /// Ratio of the NMAC probability with to the one without following the advisories
+    pub fn risk_ratio(&self) -> f64 {
+        ratio(self.nmacs, self.unequipped_nmacs)
+    }
+
+    fn add(mut self, outcome: &Outcome) -> Self {
+        self.encounters += 1;
+        self.nmacs += outcome.nmac as u64;
+        self.unequipped_nmacs += outcome.unequipped_nmac as u64;
+        self.induced_nmacs += outcome.is_induced_nmac() as u64;
+        self.alerts += outcome.alerted as u64;
+        self.reversals += outcome.reversals as u64;
+        self.strengthenings += outcome.strengthenings as u64;
+        self
+    }
+}
+
+fn ratio(a: u64, b: u64) -> f64 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f64 / b as f64
+    }
+}
+
+/// Runs batches of random encounters through the [`Simulation`] on several threads
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonteCarlo {
+    /// Configuration of the equipped runs, the unequipped runs use [`Maneuver::None`]
+    pub config: SimConfig,
+    pub generator: EncounterGenerator,
+
+    /// Number of threads, at least one is used
+    pub threads: usize,
+}
+
+impl MonteCarlo {
+    pub const fn new(config: SimConfig, generator: EncounterGenerator, threads: usize) -> Self {
+        Self {
+            config,
+            generator,
+            threads,
+        }
+    }
+
+    /// Simulates the encounter with the given index, with and without following the advisories
+    pub fn outcome(&self, index: u64) -> Outcome {
+        let (ownship, intruder) = self.generator.encounter(index);
+        let unequipped = SimConfig {
+            respond_to: Maneuver::None,
+            ..self.config
+        };
+
+        Outcome::new(
+            &Simulation::new(self.config, ownship, intruder).run(),
+            &Simulation::new(unequipped, ownship, intruder).run(),
+        )
+    }
+
+    /// The outcomes of the encounters with index `0..encounters`, in the order of their index
+    pub fn outcomes(&self, encounters: u64) -> Vec<Outcome> {
+        let threads = self.threads.max(1) as u64;
+        let chunk = encounters.div_ceil(threads);
+
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..threads)
+                .map(|thread| {
+                    let range =
+                        (thread * chunk).min(encounters)..((thread + 1) * chunk).min(encounters);
+                    scope.spawn(move || range.map(|index| self.outcome(index)).collect::<Vec<_>>())
+                })
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("simulation thread panicked"))
+                .collect()
+        })
+    }
+
+    /// The metrics of the encounters with index `0..encounters`
+    pub fn run(&self, encounters: u64) -> Metrics {
+        self.outcomes(encounters)
+            .iter()
+            .fold(Metrics::default(), Metrics::add)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encounter::{Encounter, Frame};
+
+    fn monte_carlo(seed: u64, threads: usize) -> MonteCarlo {
+        MonteCarlo::new(
+            SimConfig::default(),
+            EncounterGenerator::new(EncounterDistribution::default(), seed),
+            threads,
+        )
+    }
+
+    #[test]
+    pub fn uniform_stays_in_range() {
+        let mut rng = SplitMix64::new(7);
+        for _ in 0..10_000 {
+            let x = rng.uniform(-2.0, 3.0);
+            assert!((-2.0..3.0).contains(&x));
+        }
+    }
+
+    #[test]
+    pub fn count_transitions() {
+        use crate::HAdvisory::*;
+        let advisories = [
+            ClearOfConflict,
+            WeakLeft,
+            StrongLeft,
+            WeakRight,
+            ClearOfConflict,
+        ];
+        assert_eq!(transitions(advisories.into_iter()), (1, 1));
+
+        // reversing and strengthening through clear of conflict
+        let advisories = [
+            WeakLeft,
+            ClearOfConflict,
+            WeakRight,
+            ClearOfConflict,
+            StrongRight,
+        ];
+        assert_eq!(transitions(advisories.into_iter()), (1, 0));
+
+        use crate::VAdvisory;
+        let advisories = [
+            VAdvisory::Climb1500,
+            VAdvisory::ClearOfConflict,
+            VAdvisory::DoNotClimb,
+            VAdvisory::Descend1500,
+        ];
+        assert_eq!(transitions(advisories.into_iter()), (1, 1));
+    }
+
+    #[test]
+    pub fn encounters_meet_at_cpa() {
+        let generator = EncounterGenerator::new(EncounterDistribution::default(), 42);
+        for index in 0..100 {
+            let (ownship, intruder) = generator.encounter(index);
+            let encounter = Encounter::new(Frame::Enu, ownship.state(), intruder.state());
+            let t = encounter.time_to_cpa().unwrap().get::<second>();
+            assert!((40.0..=60.01).contains(&t), "time to cpa {t}");
+
+            // flying straight into the closest point of approach
+            let mut simulation = Simulation::new(
+                SimConfig {
+                    respond_to: Maneuver::None,
+                    step: Time::new::<second>(t),
+                    ..Default::default()
+                },
+                ownship,
+                intruder,
+            );
+            simulation.step();
+            let sample = simulation.step();
+            let miss = sample.encounter().range().get::<meter>();
+            assert!(miss <= 300.5, "horizontal miss of {miss} m");
+        }
+        assert_eq!(generator.encounter(3), generator.encounter(3));
+        assert_ne!(generator.encounter(3), generator.encounter(4));
+    }
+
+    #[test]
+    pub fn reproducible_across_threads() {
+        let single = monte_carlo(1234, 1).outcomes(24);
+        let parallel = monte_carlo(1234, 5).outcomes(24);
+        assert_eq!(single, parallel);
+        assert_eq!(monte_carlo(1234, 3).run(24), monte_carlo(1234, 4).run(24));
+        assert_ne!(monte_carlo(4321, 3).outcomes(24), single);
+
+        let metrics = monte_carlo(1234, 3).run(24);
+        assert_eq!(metrics.encounters, 24);
+        assert!(metrics.alerts > 0);
+        assert!(metrics.unequipped_nmacs > 0);
+        assert!(metrics.nmac_probability() < metrics.unequipped_nmac_probability());
+        assert!(metrics.induced_nmacs <= metrics.nmacs);
+    }
+}
@@ -0,0 +1,340 @@
+use uom::si::f32::*;
+use uom::si::time::second;
+
+use crate::{hcas, vcas, Advisory, AdvisoryScores, CasError, HAdvisory, VAdvisory};
+
+/// How the score vectors of several intruders are combined into one
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Strategy {
+    /// Every advisory is rated by its worst score over all intruders
+    #[default]
+    WorstCase,
+
+    /// Every advisory is rated by the sum of its scores over all intruders
+    Summed,
+
+    /// Only the intruder with the lowest tau is considered
+    ClosestThreat,
+}
+
+/// The combined result of a multi intruder evaluation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Resolution<A: Advisory> {
+    /// The combined scores of all intruders
+    pub scores: AdvisoryScores<A>,
+
+    /// Index of the intruder that drove the decision
+    ///
+    /// For [`Strategy::ClosestThreat`] this is the closest intruder. Otherwise it is the intruder
+    /// with the worst score for the chosen advisory.
+    pub intruder: usize,
+}
+
+/// Geometry of one intruder, as seen by the HorizontalCAS (see [`crate::HCas::process_cartesian`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HIntruder {
+    pub tau: Time,
+    pub forward_range: Length,
+    pub left_range: Length,
+    pub psi: Angle,
+}
+
+/// Geometry of one intruder, as seen by the VerticalCAS (see [`crate::VCas::process`])
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VIntruder {
+    pub height: Length,
+    pub vertical_speed_intruder: Velocity,
+    pub tau: Time,
+}
+
+/// HorizontalCAS for up to `N` intruders
+///
+/// All intruders are evaluated with the networks selected by the single `last_advisory` of the
+/// homeship, and their scores are combined according to `strategy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct HCasMulti<const N: usize> {
+    pub last_advisory: HAdvisory,
+    pub strategy: Strategy,
+}
+
+impl<const N: usize> HCasMulti<N> {
+    /// Creates a new multi intruder HorizontalCAS
+    pub const fn new(last_advisory: HAdvisory, strategy: Strategy) -> Self {
+        Self {
+            last_advisory,
+            strategy,
+        }
+    }
+
+    /// Evaluates all `intruders` and combines their scores
+    ///
+    /// Fails if there are no or more than `N` intruders, and rejects inputs like
+    /// [`crate::HCas::try_process_cartesian`]. If an error is returned, `last_advisory` is left
+    /// untouched.
+    pub fn try_process(
+        &mut self,
+        intruders: &[HIntruder],
+    ) -> Result<Resolution<HAdvisory>, CasError> {
+        let pra = self.last_advisory;
+        let resolution = resolve::<_, _, N>(
+            self.strategy,
+            intruders,
+            |i| i.tau,
+            |i| hcas::try_evaluate(pra, i.tau, i.forward_range, i.left_range, i.psi),
+        )?;
+
+        self.last_advisory = resolution.scores.try_advisory()?;
+        Ok(resolution)
+    }
+}
+
+/// VerticalCAS for up to `N` intruders
+///
+/// All intruders are evaluated with the network selected by the single `last_advisory` of the
+/// homeship, and their scores are combined according to `strategy`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct VCasMulti<const N: usize> {
+    pub last_advisory: VAdvisory,
+    pub strategy: Strategy,
+}
+
+impl<const N: usize> VCasMulti<N> {
+    /// Creates a new multi intruder VerticalCAS
+    pub const fn new(last_advisory: VAdvisory, strategy: Strategy) -> Self {
+        Self {
+            last_advisory,
+            strategy,
+        }
+    }
+
+    /// Evaluates all `intruders` and combines their scores
+    ///
+    /// Fails if there are no or more than `N` intruders, and rejects inputs like
+    /// [`crate::VCas::try_process`]. If an error is returned, `last_advisory` is left untouched.
+    pub fn try_process(
+        &mut self,
+        vertical_speed_homeship: Velocity,
+        intruders: &[VIntruder],
+    ) -> Result<Resolution<VAdvisory>, CasError> {
+        let pra = self.last_advisory;
+        let resolution = resolve::<_, _, N>(
+            self.strategy,
+            intruders,
+            |i| i.tau,
+            |i| {
+                vcas::try_evaluate(
+                    pra,
+                    i.height,
+                    vertical_speed_homeship,
+                    i.vertical_speed_intruder,
+                    i.tau,
+                )
+            },
+        )?;
+
+        self.last_advisory = resolution.scores.try_advisory()?;
+        Ok(resolution)
+    }
+}
+
+/// Evaluates every intruder and combines the scores according to `strategy`
+///
+/// `tau` yields the priority of an intruder for [`Strategy::ClosestThreat`].
+fn resolve<A: Advisory, I, const N: usize>(
+    strategy: Strategy,
+    intruders: &[I],
+    tau: impl Fn(&I) -> Time,
+    mut evaluate: impl FnMut(&I) -> Result<AdvisoryScores<A>, CasError>,
+) -> Result<Resolution<A>, CasError> {
+    if intruders.is_empty() {
+        return Err(CasError::NoIntruder);
+    }
+    if intruders.len() > N {
+        return Err(CasError::TooManyIntruders {
+            max: N,
+            found: intruders.len(),
+        });
+    }
+
+    // evaluate all intruders up front, so that an error leaves nothing half done
+    let mut evaluated: [Option<AdvisoryScores<A>>; N] = [None; N];
+    for (slot, intruder) in evaluated.iter_mut().zip(intruders) {
+        *slot = Some(evaluate(intruder)?);
+    }
+    let evaluated = evaluated.iter().flatten();
+
+    if strategy == Strategy::ClosestThreat {
+        let (closest, _) = intruders
+            .iter()
+            .map(|i| tau(i).get::<second>())
+            .enumerate()
+            .reduce(|closest, next| if next.1 < closest.1 { next } else { closest })
+            .unwrap();
+
+        return Ok(Resolution {
+            scores: evaluated.copied().nth(closest).unwrap(),
+            intruder: closest,
+        });
+    }
+
+    let mut combined = evaluated.clone().next().unwrap().into_scores();
+    for scores in evaluated.clone().skip(1) {
+        for (c, &s) in combined.as_mut().iter_mut().zip(scores.scores()) {
+            *c = match strategy {
+                Strategy::Summed => *c + s,
+                _ => worse::<A>(*c, s),
+            };
+        }
+    }
+    let scores = AdvisoryScores::new(combined);
+
+    // the intruder with the worst score for the chosen advisory drove the decision
+    let advisory = scores.advisory();
+    let (intruder, _) = evaluated
+        .map(|s| s.score(advisory))
+        .enumerate()
+        .reduce(|driver, next| {
+            if worse::<A>(driver.1, next.1) == driver.1 {
+                driver
+            } else {
+                next
+            }
+        })
+        .unwrap();
+
+    Ok(Resolution { scores, intruder })
+}
+
+/// The less desirable of two scores
+fn worse<A: Advisory>(a: f32, b: f32) -> f32 {
+    if A::MINIMIZE {
+        a.max(b)
+    } else {
+        a.min(b)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uom::si::angle::radian;
+    use uom::si::length::foot;
+    use uom::si::velocity::foot_per_minute;
+
+    fn intruders() -> [HIntruder; 3] {
+        let intruder = |tau, x, y, psi| HIntruder {
+            tau: Time::new::<second>(tau),
+            forward_range: Length::new::<foot>(x),
+            left_range: Length::new::<foot>(y),
+            psi: Angle::new::<radian>(psi),
+        };
+
+        [
+            intruder(25.0, 5e3, 1e3, -2.5),
+            intruder(10.0, 2e3, -1.5e3, 2.0),
+            intruder(40.0, 8e3, 0.0, 3.1),
+        ]
+    }
+
+    #[test]
+    pub fn single_intruder_matches_hcas() {
+        let [intruder, ..] = intruders();
+        let expected = hcas::evaluate(
+            HAdvisory::WeakLeft,
+            intruder.tau,
+            intruder.forward_range,
+            intruder.left_range,
+            intruder.psi,
+        );
+
+        for strategy in [
+            Strategy::WorstCase,
+            Strategy::Summed,
+            Strategy::ClosestThreat,
+        ] {
+            let mut multi = HCasMulti::<4>::new(HAdvisory::WeakLeft, strategy);
+            let resolution = multi.try_process(&[intruder]).unwrap();
+
+            assert_eq!(resolution.scores, expected);
+            assert_eq!(resolution.intruder, 0);
+            assert_eq!(multi.last_advisory, expected.advisory());
+        }
+    }
+
+    #[test]
+    pub fn strategies_combine_scores() {
+        let intruders = intruders();
+        let single = intruders.map(|i| {
+            hcas::evaluate(
+                HAdvisory::ClearOfConflict,
+                i.tau,
+                i.forward_range,
+                i.left_range,
+                i.psi,
+            )
+        });
+
+        let mut multi = HCasMulti::<3>::default();
+        let worst = multi.try_process(&intruders).unwrap();
+        for &advisory in HAdvisory::ALL {
+            let expected = single
+                .iter()
+                .map(|s| s.score(advisory))
+                .fold(f32::INFINITY, f32::min);
+            assert_eq!(worst.scores.score(advisory), expected);
+        }
+        assert_eq!(
+            single[worst.intruder].score(worst.scores.advisory()),
+            worst.scores.best_score()
+        );
+
+        let mut multi = HCasMulti::<3>::new(HAdvisory::ClearOfConflict, Strategy::Summed);
+        let summed = multi.try_process(&intruders).unwrap();
+        for &advisory in HAdvisory::ALL {
+            let expected: f32 = single.iter().map(|s| s.score(advisory)).sum();
+            assert!((summed.scores.score(advisory) - expected).abs() < 1e-3);
+        }
+
+        let mut multi = HCasMulti::<3>::new(HAdvisory::ClearOfConflict, Strategy::ClosestThreat);
+        let closest = multi.try_process(&intruders).unwrap();
+        assert_eq!(closest.intruder, 1);
+        assert_eq!(closest.scores, single[1]);
+    }
+
+    #[test]
+    pub fn reject_wrong_intruder_count() {
+        let mut multi = HCasMulti::<2>::new(HAdvisory::StrongLeft, Strategy::WorstCase);
+        assert_eq!(
+            multi.try_process(&intruders()),
+            Err(CasError::TooManyIntruders { max: 2, found: 3 })
+        );
+        assert_eq!(multi.try_process(&[]), Err(CasError::NoIntruder));
+        assert_eq!(multi.last_advisory, HAdvisory::StrongLeft);
+    }
+
+    #[test]
+    pub fn vertical_closest_threat() {
+        let intruder = |height, vs, tau| VIntruder {
+            height: Length::new::<foot>(height),
+            vertical_speed_intruder: Velocity::new::<foot_per_minute>(vs),
+            tau: Time::new::<second>(tau),
+        };
+        let intruders = [intruder(500.0, 0.0, 30.0), intruder(-100.0, 1e3, 12.0)];
+        let vs_own = Velocity::new::<foot_per_minute>(0.0);
+
+        let mut multi = VCasMulti::<2>::new(VAdvisory::ClearOfConflict, Strategy::ClosestThreat);
+        let resolution = multi.try_process(vs_own, &intruders).unwrap();
+
+        assert_eq!(resolution.intruder, 1);
+        assert_eq!(
+            resolution.scores,
+            vcas::evaluate(
+                VAdvisory::ClearOfConflict,
+                intruders[1].height,
+                vs_own,
+                intruders[1].vertical_speed_intruder,
+                intruders[1].tau
+            )
+        );
+    }
+}
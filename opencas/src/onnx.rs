@@ -0,0 +1,437 @@
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use nalgebra::{DMatrix, DVector};
+
+use crate::inference::NNet;
+use crate::loader::{DynLayer, DynNNet};
+
+mod model;
+pub use model::{Attribute, FeedForward, Graph, Model, Node, OnnxError, Tensor, ValueInfo};
+
+/// The version of the default operator set used by [`export`]
+pub const OPSET_VERSION: i64 = 13;
+
+/// How the normalization of the inputs and outputs is represented in an exported model
+///
+/// Either way, the normalization is stored in the metadata of the model, see [`export`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// The normalization is folded into the weights of the first and the last layer, so that the
+    /// model takes the same inputs and yields the same outputs as `NNet::eval`
+    ///
+    /// The clamping of the inputs is not part of the model, which therefore only matches
+    /// `NNet::eval` for inputs within `min_input..=max_input`.
+    #[default]
+    Folded,
+
+    /// The model takes normalized inputs and yields normalized outputs
+    Exposed,
+}
+
+/// Exports `nnet` to ONNX, see [`export_dyn`]
+pub fn export<
+    const N_INPUT: usize,
+    const N_MAT: usize,
+    const N_NEURON: usize,
+    const N_OUTPUT: usize,
+>(
+    nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+    normalization: Normalization,
+) -> Model {
+    export_dyn(&DynNNet::from(nnet), normalization)
+}
+
+/// Exports `nnet` to ONNX
+///
+/// The model has a single input `X` of shape `[batch, n_input]` and a single output `Y` of shape
+/// `[batch, n_output]`. Every layer is one `Gemm` node, followed by a `Relu` node for all but the
+/// output layer.
+///
+/// The metadata holds the normalization of `nnet`, with the keys `min_input`, `max_input`,
+/// `mean_input`, `range_input`, `mean_output` and `range_output`. Vectors are separated by
+/// commas. The key `normalization` tells whether it is `folded` or `exposed`.
+pub fn export_dyn(nnet: &DynNNet, normalization: Normalization) -> Model {
+    let mut layers = nnet.layers.clone();
+    if normalization == Normalization::Folded {
+        // a * (x - mean) / range + b == (a / range) * x + (b - (a / range) * mean)
+        let first = &mut layers[0];
+        for (col, (mean, range)) in nnet.mean_value.iter().zip(&nnet.range).enumerate() {
+            let mut column = first.a.column_mut(col);
+            column /= *range;
+            let shift = column * *mean;
+            first.biases -= shift;
+        }
+
+        let last = layers.last_mut().unwrap();
+        last.a *= nnet.range_output;
+        last.biases = (&last.biases * nnet.range_output).add_scalar(nnet.mean_output);
+    }
+
+    let mut nodes = Vec::new();
+    let mut initializers = BTreeMap::new();
+    let mut value = String::from("X");
+    for (i, layer) in layers.iter().enumerate() {
+        let (weights, biases) = (format!("W{i}"), format!("B{i}"));
+        initializers.insert(
+            weights.clone(),
+            Tensor {
+                dims: [layer.a.nrows(), layer.a.ncols()].into(),
+                data: layer.a.transpose().as_slice().into(),
+            },
+        );
+        initializers.insert(
+            biases.clone(),
+            Tensor {
+                dims: [layer.biases.len()].into(),
+                data: layer.biases.as_slice().into(),
+            },
+        );
+
+        let output = if i + 1 == layers.len() {
+            String::from("Y")
+        } else {
+            format!("gemm{i}")
+        };
+        nodes.push(Node {
+            name: format!("gemm{i}"),
+            op_type: String::from("Gemm"),
+            inputs: [value, weights, biases].into(),
+            outputs: [output.clone()].into(),
+            attributes: [(String::from("transB"), Attribute::Int(1))].into(),
+        });
+        value = output;
+
+        if i + 1 < layers.len() {
+            let output = format!("relu{i}");
+            nodes.push(Node {
+                name: output.clone(),
+                op_type: String::from("Relu"),
+                inputs: [value].into(),
+                outputs: [output.clone()].into(),
+                attributes: BTreeMap::new(),
+            });
+            value = output;
+        }
+    }
+
+    let join = |values: &[f32]| {
+        values
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+    let metadata = [
+        (
+            "normalization",
+            match normalization {
+                Normalization::Folded => String::from("folded"),
+                Normalization::Exposed => String::from("exposed"),
+            },
+        ),
+        ("min_input", join(nnet.min_input.as_slice())),
+        ("max_input", join(nnet.max_input.as_slice())),
+        ("mean_input", join(nnet.mean_value.as_slice())),
+        ("range_input", join(nnet.range.as_slice())),
+        ("mean_output", nnet.mean_output.to_string()),
+        ("range_output", nnet.range_output.to_string()),
+    ]
+    .map(|(key, value)| (String::from(key), value))
+    .into();
+
+    Model {
+        producer_name: String::from("opencas"),
+        opset_version: OPSET_VERSION,
+        graph: Graph {
+            name: String::from("nnet"),
+            nodes,
+            initializers,
+            inputs: [ValueInfo {
+                name: String::from("X"),
+                dims: [None, Some(nnet.n_input())].into(),
+            }]
+            .into(),
+            outputs: [ValueInfo {
+                name: String::from("Y"),
+                dims: [None, Some(nnet.n_output())].into(),
+            }]
+            .into(),
+        },
+        metadata,
+    }
+}
+
+/// Imports a feed-forward ReLU network, see [`Model::feed_forward`]
+///
+/// Models written by [`export`] are imported with their normalization, so that the network
+/// evaluates to the same outputs as the exported one.
+pub fn import(model: &Model) -> Result<DynNNet, OnnxError> {
+    let network = model.feed_forward()?;
+    let layers = network
+        .weights
+        .iter()
+        .zip(&network.biases)
+        .map(|(weights, biases)| DynLayer {
+            a: DMatrix::from_row_slice(weights.dims[0], weights.dims[1], &weights.data),
+            biases: DVector::from_column_slice(biases),
+        })
+        .collect();
+
+    Ok(DynNNet {
+        layers,
+        min_input: DVector::from_vec(network.min_input),
+        max_input: DVector::from_vec(network.max_input),
+        mean_value: DVector::from_vec(network.mean_input),
+        range: DVector::from_vec(network.range_input),
+        mean_output: network.mean_output,
+        range_output: network.range_output,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::nnets;
+
+    /// Asserts that both outputs agree up to the rounding of `f32`
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                (a - e).abs() <= 1e-4 * e.abs().max(1.0),
+                "{actual:?} differs from {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    pub fn folded_round_trip() {
+        let nnet = &nnets::HCAS_NNETS[1][3];
+        let bytes = export(nnet, Normalization::Folded).encode();
+        let model = Model::parse(&bytes).unwrap();
+        assert_eq!(model, export(nnet, Normalization::Folded));
+        assert_eq!(model.opset_version, OPSET_VERSION);
+        assert_eq!(model.metadata["normalization"], "folded");
+
+        for input in [
+            [0.0, 0.0, 0.0],
+            [5e3, -2e3, 1.0],
+            [12e3, 800.0, 3.0],
+            [-3e4, 2e4, -2.5],
+        ] {
+            let expected = nnet.eval(input.into());
+            assert_close(&model.eval(&input).unwrap(), expected.as_slice());
+        }
+
+        let vcas = &nnets::VCAS_NNETS[4];
+        let model = Model::parse(&export(vcas, Normalization::Folded).encode()).unwrap();
+        let input = [-200.0, 20.0, -30.0, 12.0];
+        assert_close(
+            &model.eval(&input).unwrap(),
+            vcas.eval(input.into()).as_slice(),
+        );
+    }
+
+    #[test]
+    pub fn exposed_normalization() {
+        let nnet = &nnets::VCAS_NNETS[0];
+        let model = Model::parse(&export(nnet, Normalization::Exposed).encode()).unwrap();
+        assert_eq!(model.metadata["normalization"], "exposed");
+
+        let parse = |key: &str| -> Vec<f32> {
+            model.metadata[key]
+                .split(',')
+                .map(|v| v.parse().unwrap())
+                .collect()
+        };
+        assert_eq!(parse("min_input"), nnet.min_input.as_slice());
+        assert_eq!(parse("range_input"), nnet.range.as_slice());
+        let (mean, range) = (parse("mean_input"), parse("range_input"));
+        let (mean_output, range_output) = (parse("mean_output")[0], parse("range_output")[0]);
+
+        let input = [500.0, -10.0, 40.0, 25.0];
+        let normalized: Vec<f32> = (0..4).map(|i| (input[i] - mean[i]) / range[i]).collect();
+        let output: Vec<f32> = model
+            .eval(&normalized)
+            .unwrap()
+            .iter()
+            .map(|y| y * range_output + mean_output)
+            .collect();
+        assert_close(&output, nnet.eval(input.into()).as_slice());
+    }
+
+    #[test]
+    pub fn interpreter_operators() {
+        let node = |op_type: &str, inputs: &[&str], attributes: &[(&str, Attribute)]| Node {
+            name: op_type.into(),
+            op_type: op_type.into(),
+            inputs: inputs.iter().map(|s| String::from(*s)).collect(),
+            outputs: [String::from(op_type)].into(),
+            attributes: attributes
+                .iter()
+                .map(|(k, v)| (String::from(*k), v.clone()))
+                .collect(),
+        };
+        let tensor = |dims: &[usize], data: &[f32]| Tensor {
+            dims: dims.into(),
+            data: data.into(),
+        };
+
+        // clamp, normalize and a single neuron written the way other exporters do
+        let model = Model {
+            producer_name: String::new(),
+            opset_version: OPSET_VERSION,
+            graph: Graph {
+                name: String::new(),
+                nodes: [
+                    node("Max", &["X", "min"], &[]),
+                    node("Min", &["Max", "max"], &[]),
+                    node("Sub", &["Min", "mean"], &[]),
+                    node("Div", &["Sub", "range"], &[]),
+                    node(
+                        "Constant",
+                        &[],
+                        &[("value", Attribute::Tensor(tensor(&[2, 1], &[1.0, -1.0])))],
+                    ),
+                    node("MatMul", &["Div", "Constant"], &[]),
+                    node("Relu", &["MatMul"], &[]),
+                    node("Flatten", &["Relu"], &[]),
+                    node(
+                        "Gemm",
+                        &["Flatten", "w", ""],
+                        &[("alpha", Attribute::Float(2.0))],
+                    ),
+                ]
+                .into(),
+                initializers: [
+                    ("min", tensor(&[2], &[-1.0, -1.0])),
+                    ("max", tensor(&[2], &[1.0, 1.0])),
+                    ("mean", tensor(&[], &[0.5])),
+                    ("range", tensor(&[1, 2], &[2.0, 4.0])),
+                    ("w", tensor(&[1, 1], &[3.0])),
+                ]
+                .map(|(name, t)| (String::from(name), t))
+                .into(),
+                inputs: [ValueInfo {
+                    name: String::from("X"),
+                    dims: [None, Some(2)].into(),
+                }]
+                .into(),
+                outputs: [ValueInfo {
+                    name: String::from("Gemm"),
+                    dims: [None, Some(1)].into(),
+                }]
+                .into(),
+            },
+            metadata: BTreeMap::new(),
+        };
+        let model = Model::parse(&model.encode()).unwrap();
+
+        // (1 - 0.5) / 2 - (-1 - 0.5) / 4 = 0.625, times alpha and w
+        assert_eq!(model.eval(&[3.0, -2.0]).unwrap(), [3.75]);
+        // the relu cuts off
+        assert_eq!(model.eval(&[-1.0, 1.0]).unwrap(), [0.0]);
+        assert_eq!(
+            model.eval(&[1.0]),
+            Err(OnnxError::InputCount {
+                expected: 2,
+                found: 1
+            })
+        );
+
+        let mut unsupported = model.clone();
+        unsupported.graph.nodes[6].op_type = String::from("Sigmoid");
+        assert_eq!(
+            unsupported.eval(&[0.0, 0.0]),
+            Err(OnnxError::UnsupportedOperator(String::from("Sigmoid")))
+        );
+    }
+
+    #[test]
+    pub fn import_round_trip() {
+        let nnet = &nnets::HCAS_NNETS[2][5];
+        let exposed = import(&export(nnet, Normalization::Exposed)).unwrap();
+        assert_eq!(exposed, DynNNet::from(nnet));
+
+        let folded = import(&export(nnet, Normalization::Folded)).unwrap();
+        assert_eq!(folded.min_input, exposed.min_input);
+        assert_eq!(folded.range_output, 1.0);
+        // the clamping survives the folded normalization
+        for input in [[0.0, 0.0, 0.0], [5e3, -2e3, 1.0], [-7e4, 7e4, -4.0]] {
+            assert_close(
+                folded.eval(&input).as_slice(),
+                nnet.eval(input.into()).as_slice(),
+            );
+        }
+    }
+
+    #[test]
+    pub fn import_tapered_model() {
+        let bytes = include_bytes!("../nnets/extra/tapered_example.onnx");
+        let model = Model::parse(bytes).unwrap();
+        let nnet = import(&model).unwrap();
+        let widths: Vec<(usize, usize)> = nnet.layers.iter().map(|l| l.a.shape()).collect();
+        assert_eq!(widths, [(5, 2), (3, 5), (2, 3)]);
+        // without metadata, the inputs are taken as they are
+        assert_eq!(nnet.max_input.as_slice(), [f32::MAX, f32::MAX]);
+
+        let generated = &nnets::extra::TAPERED_EXAMPLE_ONNX;
+        assert_eq!(DynNNet::from(generated), nnet);
+        for input in [[0.0, 0.0], [0.3, -0.7], [-1.5, 2.0]] {
+            let expected = model.eval(&input).unwrap();
+            assert_close(nnet.eval(&input).as_slice(), &expected);
+            assert_close(generated.eval(input.into()).as_slice(), &expected);
+        }
+
+        // a final activation does not fit into the network
+        let mut model = model;
+        let output = model.graph.outputs[0].name.clone();
+        model.graph.nodes.push(Node {
+            name: String::from("final"),
+            op_type: String::from("Relu"),
+            inputs: [output].into(),
+            outputs: [String::from("final")].into(),
+            attributes: BTreeMap::new(),
+        });
+        model.graph.outputs[0].name = String::from("final");
+        assert_eq!(
+            import(&model),
+            Err(OnnxError::NotFeedForward(String::from("final")))
+        );
+
+        model.graph.nodes[3].op_type = String::from("Sigmoid");
+        assert_eq!(
+            import(&model),
+            Err(OnnxError::UnsupportedOperator(String::from("Sigmoid")))
+        );
+    }
+
+    #[test]
+    pub fn reject_overflowing_dims() {
+        let huge = 1 << 40;
+        let mut model = export(&nnets::HCAS_NNETS[0][0], Normalization::Folded);
+        model.graph.inputs[0].dims = [Some(huge), Some(huge)].into();
+        assert_eq!(model.eval(&[0.0; 3]), Err(OnnxError::Malformed));
+
+        model.graph.initializers.insert(
+            String::from("huge"),
+            Tensor {
+                dims: [huge, huge].into(),
+                data: Vec::new(),
+            },
+        );
+        assert_eq!(Model::parse(&model.encode()), Err(OnnxError::Malformed));
+    }
+
+    #[test]
+    pub fn reject_truncated_model() {
+        let bytes = export(&nnets::HCAS_NNETS[0][0], Normalization::Folded).encode();
+        assert_eq!(
+            Model::parse(&bytes[..bytes.len() / 2]),
+            Err(OnnxError::UnexpectedEof)
+        );
+    }
+}
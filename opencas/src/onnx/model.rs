@@ -0,0 +1,1121 @@
+//! The ONNX model and its protobuf encoding, together with the interpreter.
+//!
+//! This module does not depend on the rest of the crate, so that `build.rs` can include it to
+//! generate networks from `.onnx` files.
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+
+/// The IR version written by [`Model::encode`]
+const IR_VERSION: i64 = 8;
+
+/// Data types of a `TensorProto`
+const FLOAT: i32 = 1;
+const DOUBLE: i32 = 11;
+
+/// Types of an `AttributeProto`
+const ATTRIBUTE_FLOAT: i64 = 1;
+const ATTRIBUTE_INT: i64 = 2;
+const ATTRIBUTE_STRING: i64 = 3;
+const ATTRIBUTE_TENSOR: i64 = 4;
+const ATTRIBUTE_FLOATS: i64 = 6;
+const ATTRIBUTE_INTS: i64 = 7;
+
+/// Errors that may occur while parsing or evaluating an ONNX model
+#[derive(Debug, Clone, PartialEq)]
+pub enum OnnxError {
+    /// The protobuf encoding ended in the middle of a field
+    UnexpectedEof,
+
+    /// The protobuf encoding is broken, e.g. a varint is too long or a wire type is unknown
+    Malformed,
+
+    /// A string is not valid UTF-8
+    InvalidUtf8,
+
+    /// A tensor has an element type other than float or double
+    UnsupportedDataType(i32),
+
+    /// A node uses an operator which is not implemented
+    UnsupportedOperator(String),
+
+    /// A value is neither a graph input, an initializer nor the output of an earlier node
+    MissingValue(String),
+
+    /// The shapes of the operands of the given node do not fit together
+    ShapeMismatch { node: String },
+
+    /// The number of inputs does not match the graph input
+    InputCount { expected: usize, found: usize },
+
+    /// The given node does not fit into a chain of affine layers with ReLU activations, see
+    /// [`Model::feed_forward`]
+    NotFeedForward(String),
+
+    /// The value of the given metadata key can not be parsed
+    InvalidMetadata(String),
+}
+
+impl fmt::Display for OnnxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedEof => write!(f, "unexpected end of onnx model"),
+            Self::Malformed => write!(f, "malformed protobuf encoding"),
+            Self::InvalidUtf8 => write!(f, "invalid utf-8 in string"),
+            Self::UnsupportedDataType(data_type) => {
+                write!(f, "unsupported tensor data type {data_type}")
+            }
+            Self::UnsupportedOperator(op_type) => write!(f, "unsupported operator {op_type}"),
+            Self::MissingValue(name) => write!(f, "value {name} is never produced"),
+            Self::ShapeMismatch { node } => write!(f, "shapes of the operands of {node} mismatch"),
+            Self::InputCount { expected, found } => {
+                write!(f, "expected {expected} inputs, but got {found}")
+            }
+            Self::NotFeedForward(node) => {
+                write!(f, "{node} does not fit into a feed-forward relu network")
+            }
+            Self::InvalidMetadata(key) => write!(f, "invalid value for metadata key {key}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for OnnxError {}
+
+/// A tensor of `f32` values, stored in row-major order
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tensor {
+    pub dims: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+/// The value of an attribute of a [`Node`]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attribute {
+    Float(f32),
+    Int(i64),
+    String(String),
+    Tensor(Tensor),
+    Floats(Vec<f32>),
+    Ints(Vec<i64>),
+}
+
+/// One operation of a [`Graph`]
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Node {
+    pub name: String,
+    pub op_type: String,
+    /// Names of the operands, an empty name marks an omitted optional operand
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    pub attributes: BTreeMap<String, Attribute>,
+}
+
+/// The name and shape of a graph input or output
+///
+/// `dims` holds `None` for symbolic dimensions, like the batch size.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ValueInfo {
+    pub name: String,
+    pub dims: Vec<Option<usize>>,
+}
+
+/// The computation of a [`Model`]
+///
+/// `nodes` are sorted topologically, so that every node only depends on graph inputs,
+/// initializers and the outputs of earlier nodes.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Graph {
+    pub name: String,
+    pub nodes: Vec<Node>,
+    pub initializers: BTreeMap<String, Tensor>,
+    pub inputs: Vec<ValueInfo>,
+    pub outputs: Vec<ValueInfo>,
+}
+
+/// An ONNX model
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Model {
+    pub producer_name: String,
+    /// Version of the default operator set
+    pub opset_version: i64,
+    pub graph: Graph,
+    /// Free-form key value pairs, see [`super::export`] for the ones written there
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// The layers and the normalization of a feed-forward ReLU network, see [`Model::feed_forward`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeedForward {
+    /// The weights of every layer, of shape `[outputs, inputs]`
+    pub weights: Vec<Tensor>,
+    pub biases: Vec<Vec<f32>>,
+    pub min_input: Vec<f32>,
+    pub max_input: Vec<f32>,
+    pub mean_input: Vec<f32>,
+    pub range_input: Vec<f32>,
+    pub mean_output: f32,
+    pub range_output: f32,
+}
+
+impl Model {
+    /// Parse the protobuf encoding of a `ModelProto`
+    ///
+    /// Fields which are not needed to evaluate the model, like doc strings, are skipped. Tensors
+    /// must be of type float or double, and must not be stored externally.
+    pub fn parse(bytes: &[u8]) -> Result<Self, OnnxError> {
+        let mut model = Self::default();
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (2, Value::Bytes(bytes)) => model.producer_name = string(bytes)?,
+                (7, Value::Bytes(bytes)) => model.graph = Graph::parse(bytes)?,
+                (8, Value::Bytes(bytes)) => {
+                    let (mut domain, mut version) = (String::new(), 0);
+                    for field in Reader::new(bytes) {
+                        match field? {
+                            (1, Value::Bytes(bytes)) => domain = string(bytes)?,
+                            (2, Value::Varint(value)) => version = value as i64,
+                            _ => {}
+                        }
+                    }
+                    if domain.is_empty() || domain == "ai.onnx" {
+                        model.opset_version = version;
+                    }
+                }
+                (14, Value::Bytes(bytes)) => {
+                    let (mut key, mut value) = (String::new(), String::new());
+                    for field in Reader::new(bytes) {
+                        match field? {
+                            (1, Value::Bytes(bytes)) => key = string(bytes)?,
+                            (2, Value::Bytes(bytes)) => value = string(bytes)?,
+                            _ => {}
+                        }
+                    }
+                    model.metadata.insert(key, value);
+                }
+                _ => {}
+            }
+        }
+
+        Ok(model)
+    }
+
+    /// Read and parse an ONNX model from `reader`
+    #[cfg(feature = "std")]
+    pub fn from_reader<R: std::io::Read>(mut reader: R) -> std::io::Result<Self> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Self::parse(&bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+
+    /// The protobuf encoding of the model, as stored in `.onnx` files
+    pub fn encode(&self) -> Vec<u8> {
+        let mut writer = Writer::default();
+        writer.int(1, IR_VERSION);
+        writer.string(2, &self.producer_name);
+        writer.message(7, |writer| self.graph.encode(writer));
+        writer.message(8, |writer| {
+            writer.string(1, "");
+            writer.int(2, self.opset_version);
+        });
+        for (key, value) in &self.metadata {
+            writer.message(14, |writer| {
+                writer.string(1, key);
+                writer.string(2, value);
+            });
+        }
+        writer.buf
+    }
+
+    /// The graph input which is not also an initializer
+    pub fn input(&self) -> Option<&ValueInfo> {
+        let graph = &self.graph;
+        graph
+            .inputs
+            .iter()
+            .find(|input| !graph.initializers.contains_key(&input.name))
+    }
+
+    /// Evaluates the model for a single input vector
+    ///
+    /// `inputs` is fed into [`Model::input`], with every symbolic dimension like the batch size
+    /// set to one. The nodes are evaluated in order, and the first graph output is returned
+    /// flattened.
+    pub fn eval(&self, inputs: &[f32]) -> Result<Vec<f32>, OnnxError> {
+        let input = self
+            .input()
+            .ok_or_else(|| OnnxError::MissingValue(String::from("graph input")))?;
+        let dims: Vec<usize> = if input.dims.is_empty() {
+            [1, inputs.len()].into()
+        } else {
+            input.dims.iter().map(|d| d.unwrap_or(1)).collect()
+        };
+        let expected = element_count(&dims).ok_or(OnnxError::Malformed)?;
+        if inputs.len() != expected {
+            return Err(OnnxError::InputCount {
+                expected,
+                found: inputs.len(),
+            });
+        }
+
+        let mut values: BTreeMap<&str, Tensor> = BTreeMap::new();
+        values.insert(
+            &input.name,
+            Tensor {
+                dims,
+                data: inputs.into(),
+            },
+        );
+
+        for node in &self.graph.nodes {
+            let operands = node
+                .inputs
+                .iter()
+                .map(|name| match name.as_str() {
+                    "" => Ok(None),
+                    name => values
+                        .get(name)
+                        .or_else(|| self.graph.initializers.get(name))
+                        .map(Some)
+                        .ok_or_else(|| OnnxError::MissingValue(name.into())),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let output = node.apply(&operands)?;
+            let name = node
+                .outputs
+                .first()
+                .ok_or_else(|| OnnxError::MissingValue(format!("output of {}", node.name)))?;
+            values.insert(name, output);
+        }
+
+        let output = self
+            .graph
+            .outputs
+            .first()
+            .ok_or_else(|| OnnxError::MissingValue(String::from("graph output")))?;
+        values
+            .remove(output.name.as_str())
+            .or_else(|| self.graph.initializers.get(&output.name).cloned())
+            .map(|tensor| tensor.data)
+            .ok_or_else(|| OnnxError::MissingValue(output.name.clone()))
+    }
+
+    /// Reads the layers of a model which is a plain feed-forward ReLU network
+    ///
+    /// The nodes must form a chain from [`Model::input`] to the first graph output, in which
+    /// every layer is either a `Gemm` or a `MatMul` followed by optional `Add` nodes, and every
+    /// layer but the last one is followed by a `Relu`. `Identity` and `Flatten` nodes are
+    /// skipped, weights and biases must be initializers or `Constant` nodes.
+    ///
+    /// The normalization is taken from the metadata written by [`super::export`]. Without
+    /// metadata, the inputs are neither normalized nor bounded, apart from `f32::MAX`. A folded
+    /// normalization only contributes the bounds of the inputs, as the rest of it is already
+    /// part of the weights.
+    pub fn feed_forward(&self) -> Result<FeedForward, OnnxError> {
+        let input = self
+            .input()
+            .ok_or_else(|| OnnxError::MissingValue(String::from("graph input")))?;
+        let output = self
+            .graph
+            .outputs
+            .first()
+            .ok_or_else(|| OnnxError::MissingValue(String::from("graph output")))?;
+
+        let mut constants: BTreeMap<&str, &Tensor> = self
+            .graph
+            .initializers
+            .iter()
+            .map(|(name, tensor)| (name.as_str(), tensor))
+            .collect();
+        let mut value = input.name.as_str();
+        let mut width = input.dims.last().copied().flatten();
+        let mut weights: Vec<Tensor> = Vec::new();
+        let mut biases: Vec<Vec<f32>> = Vec::new();
+        // whether the last layer is not yet followed by its activation
+        let mut open = false;
+
+        for node in &self.graph.nodes {
+            let not_feed_forward = || OnnxError::NotFeedForward(node.name.clone());
+            let mismatch = || OnnxError::ShapeMismatch {
+                node: node.name.clone(),
+            };
+            let constant = |name: &str| {
+                constants
+                    .get(name)
+                    .copied()
+                    .ok_or_else(|| OnnxError::MissingValue(name.into()))
+            };
+            // a vector of `len` elements, broadcasting a scalar
+            let vector = |tensor: &Tensor, len: usize| match tensor.data.len() {
+                n if n == len && tensor.dims.iter().rev().skip(1).all(|d| *d == 1) => {
+                    Ok(tensor.data.clone())
+                }
+                1 => Ok(vec![tensor.data[0]; len]),
+                _ => Err(mismatch()),
+            };
+
+            if node.op_type == "Constant" {
+                let (Some(name), Some(Attribute::Tensor(tensor))) =
+                    (node.outputs.first(), node.attributes.get("value"))
+                else {
+                    return Err(OnnxError::MissingValue(format!("value of {}", node.name)));
+                };
+                constants.insert(name, tensor);
+                continue;
+            }
+
+            let inputs: Vec<&str> = node.inputs.iter().map(String::as_str).collect();
+            match (node.op_type.as_str(), inputs.as_slice()) {
+                ("Identity" | "Flatten", [x]) if *x == value => {}
+                ("Relu", [x]) if *x == value && open => open = false,
+                ("Gemm", [x, w, c @ ..]) if *x == value && !open => {
+                    let w = constant(w)?;
+                    if node.int("transA", 0) != 0 {
+                        return Err(not_feed_forward());
+                    }
+                    let mut w = match node.int("transB", 0) {
+                        0 => transpose(w).ok_or_else(mismatch)?,
+                        _ if w.dims.len() == 2 => w.clone(),
+                        _ => return Err(mismatch()),
+                    };
+                    let alpha = node.float("alpha", 1.0);
+                    w.data.iter_mut().for_each(|x| *x *= alpha);
+
+                    let mut b = match c {
+                        [] | [""] => vec![0.0; w.dims[0]],
+                        [c] => vector(constant(c)?, w.dims[0])?,
+                        _ => return Err(not_feed_forward()),
+                    };
+                    let beta = node.float("beta", 1.0);
+                    b.iter_mut().for_each(|x| *x *= beta);
+
+                    if width.is_some_and(|width| width != w.dims[1]) {
+                        return Err(mismatch());
+                    }
+                    width = Some(w.dims[0]);
+                    weights.push(w);
+                    biases.push(b);
+                    open = true;
+                }
+                ("MatMul", [x, w]) if *x == value && !open => {
+                    let w = transpose(constant(w)?).ok_or_else(mismatch)?;
+                    if width.is_some_and(|width| width != w.dims[1]) {
+                        return Err(mismatch());
+                    }
+                    width = Some(w.dims[0]);
+                    biases.push(vec![0.0; w.dims[0]]);
+                    weights.push(w);
+                    open = true;
+                }
+                ("Add", [x, c] | [c, x]) if *x == value && open => {
+                    let b = biases.last_mut().unwrap();
+                    let c = vector(constant(c)?, b.len())?;
+                    b.iter_mut().zip(c).for_each(|(b, c)| *b += c);
+                }
+                ("Identity" | "Flatten" | "Relu" | "Gemm" | "MatMul" | "Add", _) => {
+                    return Err(not_feed_forward())
+                }
+                (op_type, _) => return Err(OnnxError::UnsupportedOperator(op_type.into())),
+            }
+
+            value = node.outputs.first().ok_or_else(not_feed_forward)?.as_str();
+        }
+
+        if !open || value != output.name {
+            return Err(OnnxError::NotFeedForward(output.name.clone()));
+        }
+
+        let n_input = weights[0].dims[1];
+        let metadata = |key: &str, len: usize, default: f32| -> Result<Vec<f32>, OnnxError> {
+            let invalid = || OnnxError::InvalidMetadata(key.into());
+            let Some(values) = self.metadata.get(key) else {
+                return Ok(vec![default; len]);
+            };
+            let values = values
+                .split(',')
+                .map(|v| v.trim().parse().map_err(|_| invalid()))
+                .collect::<Result<Vec<f32>, _>>()?;
+            if values.len() != len {
+                return Err(invalid());
+            }
+            Ok(values)
+        };
+
+        let mut feed_forward = FeedForward {
+            weights,
+            biases,
+            min_input: metadata("min_input", n_input, -f32::MAX)?,
+            max_input: metadata("max_input", n_input, f32::MAX)?,
+            mean_input: vec![0.0; n_input],
+            range_input: vec![1.0; n_input],
+            mean_output: 0.0,
+            range_output: 1.0,
+        };
+        if self.metadata.get("normalization").map(String::as_str) == Some("exposed") {
+            feed_forward.mean_input = metadata("mean_input", n_input, 0.0)?;
+            feed_forward.range_input = metadata("range_input", n_input, 1.0)?;
+            feed_forward.mean_output = metadata("mean_output", 1, 0.0)?[0];
+            feed_forward.range_output = metadata("range_output", 1, 1.0)?[0];
+        }
+        Ok(feed_forward)
+    }
+}
+
+impl Graph {
+    fn parse(bytes: &[u8]) -> Result<Self, OnnxError> {
+        let mut graph = Self::default();
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (1, Value::Bytes(bytes)) => graph.nodes.push(Node::parse(bytes)?),
+                (2, Value::Bytes(bytes)) => graph.name = string(bytes)?,
+                (5, Value::Bytes(bytes)) => {
+                    let (name, tensor) = Tensor::parse(bytes)?;
+                    graph.initializers.insert(name, tensor);
+                }
+                (11, Value::Bytes(bytes)) => graph.inputs.push(ValueInfo::parse(bytes)?),
+                (12, Value::Bytes(bytes)) => graph.outputs.push(ValueInfo::parse(bytes)?),
+                _ => {}
+            }
+        }
+
+        Ok(graph)
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        for node in &self.nodes {
+            writer.message(1, |writer| node.encode(writer));
+        }
+        writer.string(2, &self.name);
+        for (name, tensor) in &self.initializers {
+            writer.message(5, |writer| tensor.encode(name, writer));
+        }
+        for input in &self.inputs {
+            writer.message(11, |writer| input.encode(writer));
+        }
+        for output in &self.outputs {
+            writer.message(12, |writer| output.encode(writer));
+        }
+    }
+}
+
+impl Node {
+    fn parse(bytes: &[u8]) -> Result<Self, OnnxError> {
+        let mut node = Self::default();
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (1, Value::Bytes(bytes)) => node.inputs.push(string(bytes)?),
+                (2, Value::Bytes(bytes)) => node.outputs.push(string(bytes)?),
+                (3, Value::Bytes(bytes)) => node.name = string(bytes)?,
+                (4, Value::Bytes(bytes)) => node.op_type = string(bytes)?,
+                (5, Value::Bytes(bytes)) => {
+                    if let Some((name, attribute)) = Attribute::parse(bytes)? {
+                        node.attributes.insert(name, attribute);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(node)
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        for input in &self.inputs {
+            writer.string(1, input);
+        }
+        for output in &self.outputs {
+            writer.string(2, output);
+        }
+        writer.string(3, &self.name);
+        writer.string(4, &self.op_type);
+        for (name, attribute) in &self.attributes {
+            writer.message(5, |writer| attribute.encode(name, writer));
+        }
+    }
+
+    fn int(&self, name: &str, default: i64) -> i64 {
+        match self.attributes.get(name) {
+            Some(Attribute::Int(value)) => *value,
+            _ => default,
+        }
+    }
+
+    fn float(&self, name: &str, default: f32) -> f32 {
+        match self.attributes.get(name) {
+            Some(Attribute::Float(value)) => *value,
+            _ => default,
+        }
+    }
+
+    /// Computes the first output of the node from its operands
+    fn apply(&self, operands: &[Option<&Tensor>]) -> Result<Tensor, OnnxError> {
+        let mismatch = || OnnxError::ShapeMismatch {
+            node: self.name.clone(),
+        };
+        let operand = |i: usize| operands.get(i).copied().flatten().ok_or_else(mismatch);
+
+        let binary = |op: fn(f32, f32) -> f32| -> Result<Tensor, OnnxError> {
+            let mut result = operand(0)?.clone();
+            for i in 1..operands.len() {
+                result = broadcast(&result, operand(i)?, op).ok_or_else(mismatch)?;
+            }
+            Ok(result)
+        };
+
+        match self.op_type.as_str() {
+            "Identity" => Ok(operand(0)?.clone()),
+            "Relu" => {
+                let mut result = operand(0)?.clone();
+                result.data.iter_mut().for_each(|x| *x = x.max(0.0));
+                Ok(result)
+            }
+            "Add" => binary(|a, b| a + b),
+            "Sub" => binary(|a, b| a - b),
+            "Mul" => binary(|a, b| a * b),
+            "Div" => binary(|a, b| a / b),
+            "Max" => binary(f32::max),
+            "Min" => binary(f32::min),
+            "Flatten" => {
+                let input = operand(0)?;
+                let axis = self.int("axis", 1);
+                let axis = usize::try_from(if axis < 0 {
+                    input.dims.len() as i64 + axis
+                } else {
+                    axis
+                })
+                .map_err(|_| mismatch())?;
+                if axis > input.dims.len() {
+                    return Err(mismatch());
+                }
+                Ok(Tensor {
+                    dims: [
+                        element_count(&input.dims[..axis]).ok_or_else(mismatch)?,
+                        element_count(&input.dims[axis..]).ok_or_else(mismatch)?,
+                    ]
+                    .into(),
+                    data: input.data.clone(),
+                })
+            }
+            "Constant" => match self.attributes.get("value") {
+                Some(Attribute::Tensor(tensor)) => Ok(tensor.clone()),
+                _ => Err(OnnxError::MissingValue(format!("value of {}", self.name))),
+            },
+            "MatMul" => {
+                let (a, b) = (operand(0)?, operand(1)?);
+                let product = matmul(a, false, b, false).ok_or_else(mismatch)?;
+                // a vector operand loses its dimension again
+                Ok(match (a.dims.len(), b.dims.len()) {
+                    (1, _) => Tensor {
+                        dims: [product.dims[1]].into(),
+                        data: product.data,
+                    },
+                    (_, 1) => Tensor {
+                        dims: [product.dims[0]].into(),
+                        data: product.data,
+                    },
+                    _ => product,
+                })
+            }
+            "Gemm" => {
+                let (a, b) = (operand(0)?, operand(1)?);
+                let (alpha, beta) = (self.float("alpha", 1.0), self.float("beta", 1.0));
+                let mut result =
+                    matmul(a, self.int("transA", 0) != 0, b, self.int("transB", 0) != 0)
+                        .ok_or_else(mismatch)?;
+                result.data.iter_mut().for_each(|x| *x *= alpha);
+                if let Some(c) = operands.get(2).copied().flatten() {
+                    result = broadcast(&result, c, |r, c| r + beta * c).ok_or_else(mismatch)?;
+                    if result.dims.len() != 2 {
+                        return Err(mismatch());
+                    }
+                }
+                Ok(result)
+            }
+            op_type => Err(OnnxError::UnsupportedOperator(op_type.into())),
+        }
+    }
+}
+
+impl Attribute {
+    /// Parses an `AttributeProto`, returns `None` for attribute types which are not supported
+    fn parse(bytes: &[u8]) -> Result<Option<(String, Self)>, OnnxError> {
+        let mut name = String::new();
+        let mut attribute_type = 0;
+        let (mut float, mut int, mut text, mut tensor) = (None, None, None, None);
+        let (mut floats, mut ints) = (Vec::new(), Vec::new());
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (1, Value::Bytes(bytes)) => name = string(bytes)?,
+                (2, Value::Fixed32(bits)) => float = Some(f32::from_bits(bits)),
+                (3, Value::Varint(value)) => int = Some(value as i64),
+                (4, Value::Bytes(bytes)) => text = Some(string(bytes)?),
+                (5, Value::Bytes(bytes)) => tensor = Some(Tensor::parse(bytes)?.1),
+                (7, value) => push_floats(&mut floats, value)?,
+                (8, value) => push_ints(&mut ints, value)?,
+                (20, Value::Varint(value)) => attribute_type = value as i64,
+                _ => {}
+            }
+        }
+
+        let attribute = match attribute_type {
+            ATTRIBUTE_FLOAT => float.map(Self::Float),
+            ATTRIBUTE_INT => int.map(Self::Int),
+            ATTRIBUTE_STRING => text.map(Self::String),
+            ATTRIBUTE_TENSOR => tensor.map(Self::Tensor),
+            ATTRIBUTE_FLOATS => Some(Self::Floats(floats)),
+            ATTRIBUTE_INTS => Some(Self::Ints(ints)),
+            _ => None,
+        };
+        Ok(attribute.map(|attribute| (name, attribute)))
+    }
+
+    fn encode(&self, name: &str, writer: &mut Writer) {
+        writer.string(1, name);
+        let attribute_type = match self {
+            Self::Float(value) => {
+                writer.float(2, *value);
+                ATTRIBUTE_FLOAT
+            }
+            Self::Int(value) => {
+                writer.int(3, *value);
+                ATTRIBUTE_INT
+            }
+            Self::String(value) => {
+                writer.string(4, value);
+                ATTRIBUTE_STRING
+            }
+            Self::Tensor(tensor) => {
+                writer.message(5, |writer| tensor.encode("", writer));
+                ATTRIBUTE_TENSOR
+            }
+            Self::Floats(values) => {
+                writer.message(7, |writer| {
+                    values
+                        .iter()
+                        .for_each(|v| writer.buf.extend(v.to_le_bytes()))
+                });
+                ATTRIBUTE_FLOATS
+            }
+            Self::Ints(values) => {
+                writer.message(8, |writer| {
+                    values.iter().for_each(|v| writer.varint(*v as u64))
+                });
+                ATTRIBUTE_INTS
+            }
+        };
+        writer.int(20, attribute_type);
+    }
+}
+
+impl Tensor {
+    /// Parses a `TensorProto` into its name and the tensor
+    fn parse(bytes: &[u8]) -> Result<(String, Self), OnnxError> {
+        let mut name = String::new();
+        let mut data_type = 0;
+        let mut dims = Vec::new();
+        let mut data = Vec::new();
+        let mut doubles = Vec::new();
+        let mut raw = None;
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (1, value) => push_ints(&mut dims, value)?,
+                (2, Value::Varint(value)) => data_type = value as i32,
+                (4, value) => push_floats(&mut data, value)?,
+                (8, Value::Bytes(bytes)) => name = string(bytes)?,
+                (9, Value::Bytes(bytes)) => raw = Some(bytes),
+                (10, Value::Fixed64(bits)) => doubles.push(f64::from_bits(bits)),
+                (10, Value::Bytes(bytes)) => {
+                    for chunk in fixed_chunks::<8>(bytes)? {
+                        doubles.push(f64::from_le_bytes(chunk));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let data = match (data_type, raw) {
+            (FLOAT, None) => data,
+            (FLOAT, Some(raw)) => fixed_chunks::<4>(raw)?.map(f32::from_le_bytes).collect(),
+            (DOUBLE, None) => doubles.into_iter().map(|d| d as f32).collect(),
+            (DOUBLE, Some(raw)) => fixed_chunks::<8>(raw)?
+                .map(|chunk| f64::from_le_bytes(chunk) as f32)
+                .collect(),
+            (data_type, _) => return Err(OnnxError::UnsupportedDataType(data_type)),
+        };
+
+        let dims: Vec<usize> = dims
+            .into_iter()
+            .map(|d| usize::try_from(d).map_err(|_| OnnxError::Malformed))
+            .collect::<Result<_, _>>()?;
+        if element_count(&dims).ok_or(OnnxError::Malformed)? != data.len() {
+            return Err(OnnxError::ShapeMismatch { node: name });
+        }
+
+        Ok((name, Self { dims, data }))
+    }
+
+    fn encode(&self, name: &str, writer: &mut Writer) {
+        writer.message(1, |writer| {
+            self.dims.iter().for_each(|d| writer.varint(*d as u64))
+        });
+        writer.int(2, FLOAT.into());
+        if !name.is_empty() {
+            writer.string(8, name);
+        }
+        let raw: Vec<u8> = self.data.iter().flat_map(|v| v.to_le_bytes()).collect();
+        writer.bytes(9, &raw);
+    }
+}
+
+impl ValueInfo {
+    fn parse(bytes: &[u8]) -> Result<Self, OnnxError> {
+        let mut info = Self::default();
+
+        for field in Reader::new(bytes) {
+            match field? {
+                (1, Value::Bytes(bytes)) => info.name = string(bytes)?,
+                // TypeProto, TypeProto.Tensor, TensorShapeProto, TensorShapeProto.Dimension
+                (2, Value::Bytes(type_proto)) => {
+                    for field in Reader::new(type_proto) {
+                        let (1, Value::Bytes(tensor_type)) = field? else {
+                            continue;
+                        };
+                        for field in Reader::new(tensor_type) {
+                            let (2, Value::Bytes(shape)) = field? else {
+                                continue;
+                            };
+                            for field in Reader::new(shape) {
+                                let (1, Value::Bytes(dimension)) = field? else {
+                                    continue;
+                                };
+                                let mut dim = None;
+                                for field in Reader::new(dimension) {
+                                    if let (1, Value::Varint(value)) = field? {
+                                        dim = Some(value as usize);
+                                    }
+                                }
+                                info.dims.push(dim);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(info)
+    }
+
+    fn encode(&self, writer: &mut Writer) {
+        writer.string(1, &self.name);
+        writer.message(2, |writer| {
+            writer.message(1, |writer| {
+                writer.int(1, FLOAT.into());
+                writer.message(2, |writer| {
+                    for dim in &self.dims {
+                        writer.message(1, |writer| match dim {
+                            Some(value) => writer.int(1, *value as i64),
+                            None => writer.string(2, "batch"),
+                        });
+                    }
+                });
+            });
+        });
+    }
+}
+
+/// Applies `op` elementwise, with numpy-style broadcasting of the operands
+///
+/// Returns `None` if the shapes can not be broadcast to each other.
+fn broadcast(a: &Tensor, b: &Tensor, op: impl Fn(f32, f32) -> f32) -> Option<Tensor> {
+    let rank = a.dims.len().max(b.dims.len());
+    let pad = |dims: &[usize]| -> Vec<usize> {
+        core::iter::repeat_n(1, rank - dims.len())
+            .chain(dims.iter().copied())
+            .collect()
+    };
+    let (dims_a, dims_b) = (pad(&a.dims), pad(&b.dims));
+    let dims: Vec<usize> = dims_a
+        .iter()
+        .zip(&dims_b)
+        .map(|(&x, &y)| match (x, y) {
+            _ if x == y || y == 1 => Some(x),
+            (1, _) => Some(y),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+
+    let data = (0..element_count(&dims)?)
+        .map(|index| {
+            // walk the coordinates from the last dimension, skipping broadcast dimensions
+            let (mut rest, mut index_a, mut index_b) = (index, 0, 0);
+            let (mut stride_a, mut stride_b) = (1, 1);
+            for d in (0..rank).rev() {
+                let coordinate = rest % dims[d];
+                rest /= dims[d];
+                if dims_a[d] != 1 {
+                    index_a += coordinate * stride_a;
+                }
+                if dims_b[d] != 1 {
+                    index_b += coordinate * stride_b;
+                }
+                stride_a *= dims_a[d];
+                stride_b *= dims_b[d];
+            }
+            op(a.data[index_a], b.data[index_b])
+        })
+        .collect();
+
+    Some(Tensor { dims, data })
+}
+
+/// The transpose of a matrix, `None` for tensors of another rank
+fn transpose(t: &Tensor) -> Option<Tensor> {
+    let [rows, cols] = *t.dims.as_slice() else {
+        return None;
+    };
+    Some(Tensor {
+        dims: [cols, rows].into(),
+        data: (0..cols)
+            .flat_map(|c| (0..rows).map(move |r| t.data[r * cols + c]))
+            .collect(),
+    })
+}
+
+/// Number of elements of a tensor with the given dimensions, `None` if it overflows
+fn element_count(dims: &[usize]) -> Option<usize> {
+    dims.iter()
+        .try_fold(1usize, |count, &d| count.checked_mul(d))
+}
+
+/// The matrix product of `a` and `b`, each optionally transposed
+///
+/// Vectors are treated as a single row on the left and as a single column on the right. Returns
+/// `None` for operands of higher rank or with mismatching inner dimensions.
+fn matmul(a: &Tensor, transpose_a: bool, b: &Tensor, transpose_b: bool) -> Option<Tensor> {
+    let shape = |t: &Tensor, row: bool| match *t.dims.as_slice() {
+        [n] if row => Some((1, n)),
+        [n] => Some((n, 1)),
+        [rows, cols] => Some((rows, cols)),
+        _ => None,
+    };
+    let (rows_a, cols_a) = shape(a, true)?;
+    let (rows_b, cols_b) = shape(b, false)?;
+    let at_a = |r: usize, c: usize| {
+        if transpose_a {
+            a.data[c * cols_a + r]
+        } else {
+            a.data[r * cols_a + c]
+        }
+    };
+    let at_b = |r: usize, c: usize| {
+        if transpose_b {
+            b.data[c * cols_b + r]
+        } else {
+            b.data[r * cols_b + c]
+        }
+    };
+
+    let (m, k) = if transpose_a {
+        (cols_a, rows_a)
+    } else {
+        (rows_a, cols_a)
+    };
+    let (inner, n) = if transpose_b {
+        (cols_b, rows_b)
+    } else {
+        (rows_b, cols_b)
+    };
+    if k != inner {
+        return None;
+    }
+
+    let mut data = Vec::with_capacity(m.checked_mul(n)?);
+    for r in 0..m {
+        for c in 0..n {
+            data.push((0..k).map(|i| at_a(r, i) * at_b(i, c)).sum());
+        }
+    }
+    Some(Tensor {
+        dims: [m, n].into(),
+        data,
+    })
+}
+
+/// Wire types of the protobuf encoding
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LENGTH_DELIMITED: u8 = 2;
+const FIXED32: u8 = 5;
+
+/// A field value as found in the protobuf encoding
+#[derive(Debug, Clone, Copy)]
+enum Value<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    Bytes(&'a [u8]),
+    Fixed32(u32),
+}
+
+/// Iterates over the fields of a protobuf message, yielding their numbers and values
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'a [u8], OnnxError> {
+        if len > self.data.len() {
+            return Err(OnnxError::UnexpectedEof);
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    fn varint(&mut self) -> Result<u64, OnnxError> {
+        let mut value = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte < 0x80 {
+                return Ok(value);
+            }
+        }
+        Err(OnnxError::Malformed)
+    }
+
+    fn field(&mut self) -> Result<(u32, Value<'a>), OnnxError> {
+        let key = self.varint()?;
+        let number = u32::try_from(key >> 3).map_err(|_| OnnxError::Malformed)?;
+        let value = match (key & 0x7) as u8 {
+            VARINT => Value::Varint(self.varint()?),
+            FIXED64 => Value::Fixed64(u64::from_le_bytes(self.take(8)?.try_into().unwrap())),
+            LENGTH_DELIMITED => {
+                let len = usize::try_from(self.varint()?).map_err(|_| OnnxError::Malformed)?;
+                Value::Bytes(self.take(len)?)
+            }
+            FIXED32 => Value::Fixed32(u32::from_le_bytes(self.take(4)?.try_into().unwrap())),
+            _ => return Err(OnnxError::Malformed),
+        };
+        Ok((number, value))
+    }
+}
+
+impl<'a> Iterator for Reader<'a> {
+    type Item = Result<(u32, Value<'a>), OnnxError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let field = self.field();
+        if field.is_err() {
+            // stop after the first error
+            self.data = &[];
+        }
+        Some(field)
+    }
+}
+
+/// Decodes a string field
+fn string(bytes: &[u8]) -> Result<String, OnnxError> {
+    core::str::from_utf8(bytes)
+        .map(String::from)
+        .map_err(|_| OnnxError::InvalidUtf8)
+}
+
+/// Splits `bytes` into chunks of `N` bytes, for packed fixed size fields
+fn fixed_chunks<const N: usize>(
+    bytes: &[u8],
+) -> Result<impl Iterator<Item = [u8; N]> + '_, OnnxError> {
+    if !bytes.len().is_multiple_of(N) {
+        return Err(OnnxError::Malformed);
+    }
+    Ok(bytes.chunks_exact(N).map(|chunk| chunk.try_into().unwrap()))
+}
+
+/// Appends a repeated int64 field, which may be packed
+fn push_ints(values: &mut Vec<i64>, value: Value) -> Result<(), OnnxError> {
+    match value {
+        Value::Varint(value) => values.push(value as i64),
+        Value::Bytes(bytes) => {
+            let mut reader = Reader::new(bytes);
+            while !reader.data.is_empty() {
+                values.push(reader.varint()? as i64);
+            }
+        }
+        _ => return Err(OnnxError::Malformed),
+    }
+    Ok(())
+}
+
+/// Appends a repeated float field, which may be packed
+fn push_floats(values: &mut Vec<f32>, value: Value) -> Result<(), OnnxError> {
+    match value {
+        Value::Fixed32(bits) => values.push(f32::from_bits(bits)),
+        Value::Bytes(bytes) => values.extend(fixed_chunks::<4>(bytes)?.map(f32::from_le_bytes)),
+        _ => return Err(OnnxError::Malformed),
+    }
+    Ok(())
+}
+
+/// Writes the fields of a protobuf message
+#[derive(Debug, Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u8) {
+        self.varint(u64::from(field) << 3 | u64::from(wire_type));
+    }
+
+    fn int(&mut self, field: u32, value: i64) {
+        self.key(field, VARINT);
+        self.varint(value as u64);
+    }
+
+    fn float(&mut self, field: u32, value: f32) {
+        self.key(field, FIXED32);
+        self.buf.extend(value.to_le_bytes());
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, LENGTH_DELIMITED);
+        self.varint(value.len() as u64);
+        self.buf.extend(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    /// Writes a nested message, or the elements of a packed repeated field
+    fn message(&mut self, field: u32, write: impl FnOnce(&mut Writer)) {
+        let mut nested = Writer::default();
+        write(&mut nested);
+        self.bytes(field, &nested.buf);
+    }
+}
@@ -0,0 +1,523 @@
+use core::fmt;
+use std::path::{Path, PathBuf};
+use std::string::String;
+use std::vec::Vec;
+
+use serde::Deserialize;
+
+use uom::si::angle::degree;
+use uom::si::f32::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+use uom::si::velocity::meter_per_second;
+
+use crate::encounter::{AircraftState, Encounter, Frame, IntruderCas};
+use crate::multi::{HIntruder, VIntruder};
+use crate::{AdvisoryScores, HAdvisory, VAdvisory};
+
+/// An encounter given as a sequence of timestamped states, with the advisories expected for them
+///
+/// Scenarios are written in TOML. Positions are given in meters, velocities in meters per second
+/// and headings in degrees clockwise from north:
+///
+/// ```toml
+/// name = "head on"
+/// frame = "Enu"          # optional, the default
+/// time_tolerance = 1.0   # optional, in seconds
+///
+/// [[steps]]
+/// time = 0.0
+/// ownship = { position = [0, 0, 1500], velocity = [0, 100, 0], heading = 0 }
+/// intruder = { position = [0, 4000, 1500], velocity = [0, -100, 0], heading = 180 }
+/// horizontal = "ClearOfConflict"            # optional
+/// vertical = ["DoNotClimb", "Descend1500"]  # optional, any of the given advisories
+///
+/// [horizontal_sequence]                     # optional, as well as vertical_sequence
+/// advisories = ["ClearOfConflict", "StrongRight"]
+/// extra_changes = 1                         # optional
+/// ```
+///
+/// The steps are run in order through a stateful [`IntruderCas`]. An expected advisory is
+/// also met if it was issued at another step at most `time_tolerance` apart. The sequences
+/// constrain the order of the advisories over the whole scenario, see [`Sequence`].
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Scenario {
+    pub name: String,
+
+    #[serde(default)]
+    pub description: String,
+
+    #[serde(default)]
+    pub frame: Frame,
+
+    /// Time in seconds an advisory may be issued early or late
+    #[serde(default)]
+    pub time_tolerance: f32,
+
+    pub horizontal_sequence: Option<Sequence<HAdvisory>>,
+    pub vertical_sequence: Option<Sequence<VAdvisory>>,
+
+    pub steps: Vec<Step>,
+}
+
+/// One point in time of a [`Scenario`]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Step {
+    /// Time in seconds
+    pub time: f32,
+
+    pub ownship: State,
+    pub intruder: State,
+
+    pub horizontal: Option<Expected<HAdvisory>>,
+    pub vertical: Option<Expected<VAdvisory>>,
+}
+
+/// The state of one aircraft, in SI units and degrees
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct State {
+    pub position: [f32; 3],
+    pub velocity: [f32; 3],
+    pub heading: f32,
+}
+
+impl From<State> for AircraftState {
+    fn from(state: State) -> Self {
+        Self {
+            position: state.position.map(Length::new::<meter>),
+            velocity: state.velocity.map(Velocity::new::<meter_per_second>),
+            heading: Angle::new::<degree>(state.heading),
+        }
+    }
+}
+
+/// The advisories accepted at one step
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Expected<A> {
+    Exactly(A),
+    AnyOf(Vec<A>),
+}
+
+impl<A: PartialEq> Expected<A> {
+    /// Whether `advisory` is accepted
+    pub fn accepts(&self, advisory: &A) -> bool {
+        match self {
+            Self::Exactly(expected) => expected == advisory,
+            Self::AnyOf(expected) => expected.contains(advisory),
+        }
+    }
+}
+
+impl<A: fmt::Debug> fmt::Display for Expected<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exactly(expected) => write!(f, "{expected:?}"),
+            Self::AnyOf(expected) => write!(f, "any of {expected:?}"),
+        }
+    }
+}
+
+/// The expected order of the advisories of one system over a whole [`Scenario`]
+///
+/// The issued advisories are taken step by step, with repetitions collapsed, so that only the
+/// changes of advisory remain. `advisories` must appear in that sequence in the given order, and
+/// at most `extra_changes` further advisories may be interleaved, e.g. a brief weak advisory
+/// between two strong ones.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sequence<A> {
+    pub advisories: Vec<A>,
+
+    #[serde(default)]
+    pub extra_changes: usize,
+}
+
+impl<A: Copy + PartialEq> Sequence<A> {
+    /// Whether the sequence `issued`, with repetitions already collapsed, is accepted
+    pub fn accepts(&self, issued: &[A]) -> bool {
+        let mut issued_iter = issued.iter();
+        let in_order = self
+            .advisories
+            .iter()
+            .all(|expected| issued_iter.any(|a| a == expected));
+
+        in_order && issued.len() <= self.advisories.len() + self.extra_changes
+    }
+}
+
+impl<A: fmt::Debug> fmt::Display for Sequence<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.advisories)?;
+        if self.extra_changes > 0 {
+            write!(f, " with up to {} further changes", self.extra_changes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collapses repetitions, so that only the changes of advisory remain
+fn changes<A: PartialEq>(advisories: impl IntoIterator<Item = A>) -> Vec<A> {
+    let mut changes: Vec<A> = Vec::new();
+    for advisory in advisories {
+        if changes.last() != Some(&advisory) {
+            changes.push(advisory);
+        }
+    }
+    changes
+}
+
+/// What was computed at one step of a [`Scenario`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Evaluation {
+    pub time: f32,
+
+    /// Inputs of the HorizontalCAS, `None` if there is no threat
+    pub hcas_inputs: Option<HIntruder>,
+    pub horizontal: Option<AdvisoryScores<HAdvisory>>,
+
+    /// Inputs of the VerticalCAS, `None` if there is no threat
+    pub vcas_inputs: Option<VIntruder>,
+    pub vertical: Option<AdvisoryScores<VAdvisory>>,
+}
+
+impl Evaluation {
+    /// The issued horizontal advisory, clear of conflict without a threat
+    pub fn horizontal_advisory(&self) -> HAdvisory {
+        self.horizontal.map(|s| s.advisory()).unwrap_or_default()
+    }
+
+    /// The issued vertical advisory, clear of conflict without a threat
+    pub fn vertical_advisory(&self) -> VAdvisory {
+        self.vertical.map(|s| s.advisory()).unwrap_or_default()
+    }
+}
+
+/// An expected advisory which was not issued
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mismatch {
+    Horizontal {
+        step: usize,
+        expected: Expected<HAdvisory>,
+        evaluation: Evaluation,
+    },
+    Vertical {
+        step: usize,
+        expected: Expected<VAdvisory>,
+        evaluation: Evaluation,
+    },
+    HorizontalSequence {
+        expected: Sequence<HAdvisory>,
+        issued: Vec<HAdvisory>,
+    },
+    VerticalSequence {
+        expected: Sequence<VAdvisory>,
+        issued: Vec<VAdvisory>,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Horizontal {
+                step,
+                expected,
+                evaluation: e,
+            } => {
+                write!(
+                    f,
+                    "step {step} at {} s: expected horizontal {expected}, got {:?}",
+                    e.time,
+                    e.horizontal_advisory()
+                )?;
+                if let (Some(i), Some(scores)) = (e.hcas_inputs, e.horizontal) {
+                    write!(
+                        f,
+                        " (tau {} s, forward range {} m, left range {} m, psi {} deg, scores {:?})",
+                        i.tau.get::<second>(),
+                        i.forward_range.get::<meter>(),
+                        i.left_range.get::<meter>(),
+                        i.psi.get::<degree>(),
+                        scores.into_scores()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::Vertical {
+                step,
+                expected,
+                evaluation: e,
+            } => {
+                write!(
+                    f,
+                    "step {step} at {} s: expected vertical {expected}, got {:?}",
+                    e.time,
+                    e.vertical_advisory()
+                )?;
+                if let (Some(i), Some(scores)) = (e.vcas_inputs, e.vertical) {
+                    write!(
+                        f,
+                        " (tau {} s, height {} m, vertical speed of intruder {} m/s, scores {:?})",
+                        i.tau.get::<second>(),
+                        i.height.get::<meter>(),
+                        i.vertical_speed_intruder.get::<meter_per_second>(),
+                        scores.into_scores()
+                    )?;
+                }
+                Ok(())
+            }
+            Self::HorizontalSequence { expected, issued } => {
+                write!(f, "expected horizontal sequence {expected}, got {issued:?}")
+            }
+            Self::VerticalSequence { expected, issued } => {
+                write!(f, "expected vertical sequence {expected}, got {issued:?}")
+            }
+        }
+    }
+}
+
+/// Errors reported when loading scenarios
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, toml::de::Error),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(path, e) => write!(f, "{}: {e}", path.display()),
+            Self::Parse(path, e) => write!(f, "{}: {e}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+impl Scenario {
+    /// Parses a scenario from TOML
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    /// Loads a scenario from a TOML file
+    pub fn load(path: &Path) -> Result<Self, ScenarioError> {
+        let toml = std::fs::read_to_string(path).map_err(|e| ScenarioError::Io(path.into(), e))?;
+        Self::from_toml(&toml).map_err(|e| ScenarioError::Parse(path.into(), e))
+    }
+
+    /// Loads all `.toml` files in `dir`, sorted by their path
+    pub fn load_dir(dir: &Path) -> Result<Vec<(PathBuf, Self)>, ScenarioError> {
+        let io = |e| ScenarioError::Io(dir.into(), e);
+        let mut paths = std::fs::read_dir(dir)
+            .map_err(io)?
+            .map(|entry| entry.map(|e| e.path()))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(io)?;
+        paths.retain(|path| path.extension().is_some_and(|e| e == "toml"));
+        paths.sort();
+
+        paths
+            .into_iter()
+            .map(|path| Self::load(&path).map(|scenario| (path, scenario)))
+            .collect()
+    }
+
+    /// Runs all steps through a fresh [`IntruderCas`]
+    pub fn evaluate(&self) -> Vec<Evaluation> {
+        let mut cas = IntruderCas::default();
+
+        self.steps
+            .iter()
+            .map(|step| {
+                let encounter =
+                    Encounter::new(self.frame, step.ownship.into(), step.intruder.into());
+                let (horizontal, vertical) = cas.evaluate(&encounter);
+
+                Evaluation {
+                    time: step.time,
+                    hcas_inputs: encounter.hcas_inputs(),
+                    horizontal,
+                    vcas_inputs: encounter.vcas_inputs(),
+                    vertical,
+                }
+            })
+            .collect()
+    }
+
+    /// Runs the scenario and returns all expected advisories and sequences which were not issued
+    pub fn run(&self) -> Vec<Mismatch> {
+        let evaluations = self.evaluate();
+        let near = |time: f32| {
+            evaluations
+                .iter()
+                .filter(move |e| (e.time - time).abs() <= self.time_tolerance)
+        };
+
+        let mut mismatches = Vec::new();
+        for (index, (step, evaluation)) in self.steps.iter().zip(&evaluations).enumerate() {
+            if let Some(expected) = &step.horizontal {
+                if !near(step.time).any(|e| expected.accepts(&e.horizontal_advisory())) {
+                    mismatches.push(Mismatch::Horizontal {
+                        step: index,
+                        expected: expected.clone(),
+                        evaluation: *evaluation,
+                    });
+                }
+            }
+            if let Some(expected) = &step.vertical {
+                if !near(step.time).any(|e| expected.accepts(&e.vertical_advisory())) {
+                    mismatches.push(Mismatch::Vertical {
+                        step: index,
+                        expected: expected.clone(),
+                        evaluation: *evaluation,
+                    });
+                }
+            }
+        }
+
+        if let Some(expected) = &self.horizontal_sequence {
+            let issued = changes(evaluations.iter().map(Evaluation::horizontal_advisory));
+            if !expected.accepts(&issued) {
+                mismatches.push(Mismatch::HorizontalSequence {
+                    expected: expected.clone(),
+                    issued,
+                });
+            }
+        }
+        if let Some(expected) = &self.vertical_sequence {
+            let issued = changes(evaluations.iter().map(Evaluation::vertical_advisory));
+            if !expected.accepts(&issued) {
+                mismatches.push(Mismatch::VerticalSequence {
+                    expected: expected.clone(),
+                    issued,
+                });
+            }
+        }
+        mismatches
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fmt::Write;
+
+    const HEAD_ON: &str = r#"
+        name = "head on"
+        time_tolerance = 1.0
+
+        [[steps]]
+        time = 0.0
+        ownship = { position = [0, 0, 1500], velocity = [0, 100, 0], heading = 0 }
+        intruder = { position = [0, 20000, 1500], velocity = [0, -100, 0], heading = 180 }
+        horizontal = "ClearOfConflict"
+        vertical = ["ClearOfConflict"]
+
+        [[steps]]
+        time = 1.0
+        ownship = { position = [0, 100, 1500], velocity = [0, 100, 0], heading = 0 }
+        intruder = { position = [0, 19900, 1500], velocity = [0, -100, 0], heading = 180 }
+        horizontal = "StrongLeft"
+    "#;
+
+    #[test]
+    pub fn parse_and_report() {
+        let scenario = Scenario::from_toml(HEAD_ON).unwrap();
+        assert_eq!(scenario.frame, Frame::Enu);
+        assert_eq!(scenario.steps.len(), 2);
+        assert_eq!(
+            scenario.steps[0].vertical,
+            Some(Expected::AnyOf(vec![VAdvisory::ClearOfConflict]))
+        );
+
+        // 100 s apart, so nothing but clear of conflict is issued
+        let mismatches = scenario.run();
+        assert_eq!(mismatches.len(), 1);
+        let message = mismatches[0].to_string();
+        assert!(
+            message
+                .starts_with("step 1 at 1 s: expected horizontal StrongLeft, got ClearOfConflict"),
+            "{message}"
+        );
+
+        assert!(Scenario::from_toml("name = \"x\"\nsteps = []\nspeed = 1").is_err());
+    }
+
+    #[test]
+    pub fn sequences() {
+        use HAdvisory::*;
+
+        assert_eq!(
+            changes([ClearOfConflict, ClearOfConflict, StrongLeft, StrongLeft]),
+            vec![ClearOfConflict, StrongLeft]
+        );
+
+        let sequence = Sequence {
+            advisories: vec![ClearOfConflict, StrongLeft, ClearOfConflict],
+            extra_changes: 0,
+        };
+        assert!(sequence.accepts(&[ClearOfConflict, StrongLeft, ClearOfConflict]));
+        assert!(!sequence.accepts(&[ClearOfConflict, StrongLeft]));
+        assert!(!sequence.accepts(&[StrongLeft, ClearOfConflict, ClearOfConflict]));
+        assert!(!sequence.accepts(&[
+            ClearOfConflict,
+            StrongLeft,
+            WeakLeft,
+            StrongLeft,
+            ClearOfConflict
+        ]));
+
+        let sequence = Sequence {
+            extra_changes: 2,
+            ..sequence
+        };
+        assert!(sequence.accepts(&[
+            ClearOfConflict,
+            StrongLeft,
+            WeakLeft,
+            StrongLeft,
+            ClearOfConflict
+        ]));
+        assert!(!sequence.accepts(&[ClearOfConflict, StrongRight, ClearOfConflict]));
+
+        // the head on scenario above only ever issues clear of conflict
+        let mut scenario = Scenario::from_toml(HEAD_ON).unwrap();
+        scenario.steps[1].horizontal = None;
+        scenario.horizontal_sequence = Some(Sequence {
+            advisories: vec![ClearOfConflict, StrongLeft],
+            extra_changes: 0,
+        });
+        let mismatches = scenario.run();
+        assert_eq!(
+            mismatches,
+            vec![Mismatch::HorizontalSequence {
+                expected: scenario.horizontal_sequence.clone().unwrap(),
+                issued: vec![ClearOfConflict],
+            }]
+        );
+        assert_eq!(
+            mismatches[0].to_string(),
+            "expected horizontal sequence [ClearOfConflict, StrongLeft], got [ClearOfConflict]"
+        );
+    }
+
+    /// Runs the regression suite in the `scenarios` directory
+    #[test]
+    pub fn regression_scenarios() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("scenarios");
+        let scenarios = Scenario::load_dir(&dir).unwrap();
+        assert!(!scenarios.is_empty());
+
+        let mut report = String::new();
+        for (path, scenario) in scenarios {
+            for mismatch in scenario.run() {
+                writeln!(report, "{}: {mismatch}", path.display()).unwrap();
+            }
+        }
+        assert!(report.is_empty(), "scenarios failed:\n{report}");
+    }
+}
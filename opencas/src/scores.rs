@@ -0,0 +1,315 @@
+use core::fmt::Debug;
+use core::marker::PhantomData;
+
+use crate::CasError;
+
+/// Common interface of the advisory enums, like [`crate::HAdvisory`] and [`crate::VAdvisory`]
+///
+/// Every advisory corresponds to one output of the networks. The index of an advisory is the
+/// index of its score within the network output.
+pub trait Advisory: Debug + Copy + Eq + TryFrom<u8, Error = CasError> + 'static {
+    /// The score vector of the networks, with one score per advisory
+    type Scores: Debug + Copy + PartialEq + AsRef<[f32]> + AsMut<[f32]>;
+
+    /// All advisories, ordered by their index
+    const ALL: &'static [Self];
+
+    /// Whether the networks output costs instead of scores, so that the lowest value is the most
+    /// desirable advisory
+    const MINIMIZE: bool = false;
+
+    /// Index of the advisory within the network output
+    fn index(self) -> usize;
+}
+
+/// The complete output of a network evaluation
+///
+/// Besides the winning advisory, this keeps the score of every other advisory. A higher score
+/// means a more desirable advisory, unless [`Advisory::MINIMIZE`] is set, in which case the lowest
+/// score wins. Ties are resolved in favour of the advisory with the lower index, and NaN scores
+/// are ranked below every other score.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdvisoryScores<A: Advisory> {
+    scores: A::Scores,
+}
+
+impl<A: Advisory> AdvisoryScores<A> {
+    /// Wraps the score vector of a network evaluation
+    pub fn new(scores: A::Scores) -> Self {
+        Self { scores }
+    }
+
+    /// The raw score vector, indexed by [`Advisory::index`]
+    pub fn scores(&self) -> &[f32] {
+        self.scores.as_ref()
+    }
+
+    /// The raw score vector, consuming `self`
+    pub fn into_scores(self) -> A::Scores {
+        self.scores
+    }
+
+    /// Score of the given advisory
+    pub fn score(&self, advisory: A) -> f32 {
+        self.scores()[advisory.index()]
+    }
+
+    /// The advisory with the best score
+    ///
+    /// # Panics
+    ///
+    /// Panics if the score vector is empty, which the advisories of this crate rule out. See
+    /// [`AdvisoryScores::try_advisory`] for a version that never panics.
+    pub fn advisory(&self) -> A {
+        A::ALL[self.best_index()]
+    }
+
+    /// The advisory with the best score, converted from its index with [`TryFrom<u8>`]
+    ///
+    /// Fails with [`CasError::InvalidAdvisory`] if the index does not correspond to an advisory,
+    /// e.g. because the score vector is empty.
+    pub fn try_advisory(&self) -> Result<A, CasError> {
+        let index = self.best_index();
+        u8::try_from(index)
+            .map_err(|_| CasError::InvalidAdvisory(u8::MAX))
+            .and_then(A::try_from)
+    }
+
+    /// Index of the best score, zero for an empty score vector
+    fn best_index(&self) -> usize {
+        let scores = self.scores();
+        (0..scores.len())
+            .reduce(|best, i| {
+                if ranks_before::<A>(scores, i, best) {
+                    i
+                } else {
+                    best
+                }
+            })
+            .unwrap_or(0)
+    }
+
+    /// The best score, that is the score of [`AdvisoryScores::advisory`]
+    pub fn best_score(&self) -> f32 {
+        self.score(self.advisory())
+    }
+
+    /// The advisory with the second best score, together with its score
+    pub fn runner_up(&self) -> Option<(A, f32)> {
+        self.ranked().nth(1)
+    }
+
+    /// Absolute difference between the best and the second best score
+    ///
+    /// A small margin indicates a low-confidence decision close to a decision boundary of the
+    /// network. If there is only one advisory, the margin is infinite.
+    pub fn margin(&self) -> f32 {
+        self.runner_up().map_or(f32::INFINITY, |(_, score)| {
+            (self.best_score() - score).abs()
+        })
+    }
+
+    /// Iterates over all advisories and their scores, ordered by the advisory index
+    pub fn iter(&self) -> impl Iterator<Item = (A, f32)> + '_ {
+        A::ALL.iter().map(|&a| (a, self.score(a)))
+    }
+
+    /// Iterates over all advisories and their scores, from the best to the worst score
+    pub fn ranked(&self) -> Ranked<A> {
+        Ranked {
+            scores: self.scores,
+            last: None,
+            advisory: PhantomData,
+        }
+    }
+
+    /// Position of the given advisory in [`AdvisoryScores::ranked`], with zero being the best
+    pub fn rank(&self, advisory: A) -> usize {
+        let index = advisory.index();
+        (0..self.scores().len())
+            .filter(|&other| ranks_before::<A>(self.scores(), other, index))
+            .count()
+    }
+
+    /// Checks that none of the scores is NaN or infinite
+    pub fn is_finite(&self) -> bool {
+        self.scores().iter().all(|score| score.is_finite())
+    }
+}
+
+impl<A: Advisory> From<AdvisoryScores<A>> for (A, f32) {
+    fn from(scores: AdvisoryScores<A>) -> Self {
+        (scores.advisory(), scores.best_score())
+    }
+}
+
+/// Iterator over advisories and their scores, ordered from the best to the worst score
+///
+/// Created by [`AdvisoryScores::ranked`].
+#[derive(Debug, Clone)]
+pub struct Ranked<A: Advisory> {
+    scores: A::Scores,
+    last: Option<usize>,
+    advisory: PhantomData<A>,
+}
+
+impl<A: Advisory> Iterator for Ranked<A> {
+    type Item = (A, f32);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let scores = self.scores.as_ref();
+
+        // the scores are few, so a selection of the next best score is cheap enough
+        let next = (0..scores.len())
+            .filter(|&i| match self.last {
+                Some(last) => ranks_before::<A>(scores, last, i),
+                None => true,
+            })
+            .reduce(|best, i| {
+                if ranks_before::<A>(scores, i, best) {
+                    i
+                } else {
+                    best
+                }
+            })?;
+
+        self.last = Some(next);
+        Some((A::ALL[next], scores[next]))
+    }
+}
+
+/// Strict total order of the advisories: does the advisory at `i` rank before the one at `j`?
+fn ranks_before<A: Advisory>(scores: &[f32], i: usize, j: usize) -> bool {
+    let key = |index: usize| match scores[index] {
+        score if score.is_nan() => f32::NEG_INFINITY,
+        score if A::MINIMIZE => -score,
+        score => score,
+    };
+
+    key(i) > key(j) || (key(i) == key(j) && i < j)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{HAdvisory, VAdvisory};
+
+    #[test]
+    fn best_advisory_and_margin() {
+        let scores = AdvisoryScores::<HAdvisory>::new([-1.0, 2.5, 0.5, 1.75, -3.0]);
+
+        assert_eq!(scores.advisory(), HAdvisory::WeakLeft);
+        assert_eq!(scores.best_score(), 2.5);
+        assert_eq!(scores.runner_up(), Some((HAdvisory::StrongLeft, 1.75)));
+        assert_eq!(scores.margin(), 0.75);
+        assert_eq!(scores.score(HAdvisory::WeakRight), 0.5);
+        assert_eq!(<(HAdvisory, f32)>::from(scores), (HAdvisory::WeakLeft, 2.5));
+    }
+
+    #[test]
+    fn ranking_is_total() {
+        let scores = AdvisoryScores::<HAdvisory>::new([1.0, 3.0, f32::NAN, 3.0, -1.0]);
+
+        let ranked: std::vec::Vec<_> = scores.ranked().map(|(a, _)| a).collect();
+        assert_eq!(
+            ranked,
+            [
+                HAdvisory::WeakLeft,
+                HAdvisory::StrongLeft,
+                HAdvisory::ClearOfConflict,
+                HAdvisory::StrongRight,
+                HAdvisory::WeakRight,
+            ]
+        );
+        for (rank, advisory) in ranked.into_iter().enumerate() {
+            assert_eq!(scores.rank(advisory), rank);
+        }
+        assert_eq!(scores.margin(), 0.0);
+        assert!(!scores.is_finite());
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Cost {
+        Low,
+        High,
+    }
+
+    impl TryFrom<u8> for Cost {
+        type Error = CasError;
+
+        fn try_from(v: u8) -> Result<Self, Self::Error> {
+            Cost::ALL
+                .get(v as usize)
+                .copied()
+                .ok_or(CasError::InvalidAdvisory(v))
+        }
+    }
+
+    impl Advisory for Cost {
+        type Scores = [f32; 2];
+        const ALL: &'static [Self] = &[Self::Low, Self::High];
+        const MINIMIZE: bool = true;
+
+        fn index(self) -> usize {
+            self as usize
+        }
+    }
+
+    #[test]
+    fn minimize_costs() {
+        let scores = AdvisoryScores::<Cost>::new([-2.0, 3.0]);
+        assert_eq!(scores.advisory(), Cost::Low);
+        assert_eq!(scores.best_score(), -2.0);
+        assert_eq!(scores.margin(), 5.0);
+
+        let scores = AdvisoryScores::<Cost>::new([f32::NAN, 3.0]);
+        assert_eq!(scores.advisory(), Cost::High);
+        assert_eq!(scores.rank(Cost::Low), 1);
+    }
+
+    /// An advisory type without advisories, whose best score can not be converted
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Empty {}
+
+    impl TryFrom<u8> for Empty {
+        type Error = CasError;
+
+        fn try_from(v: u8) -> Result<Self, Self::Error> {
+            Err(CasError::InvalidAdvisory(v))
+        }
+    }
+
+    impl Advisory for Empty {
+        type Scores = [f32; 0];
+        const ALL: &'static [Self] = &[];
+        const MINIMIZE: bool = false;
+
+        fn index(self) -> usize {
+            match self {}
+        }
+    }
+
+    #[test]
+    fn try_advisory() {
+        let scores = AdvisoryScores::<Cost>::new([f32::NAN, 3.0]);
+        assert_eq!(scores.try_advisory(), Ok(Cost::High));
+
+        let scores = AdvisoryScores::<HAdvisory>::new([0.0, 1.0, 5.0, 2.0, 3.0]);
+        assert_eq!(scores.try_advisory(), Ok(scores.advisory()));
+
+        let scores = AdvisoryScores::<Empty>::new([]);
+        assert_eq!(scores.try_advisory(), Err(CasError::InvalidAdvisory(0)));
+    }
+
+    #[test]
+    fn iterate_by_advisory() {
+        let raw = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0];
+        let scores = AdvisoryScores::<VAdvisory>::new(raw);
+
+        assert!(scores
+            .iter()
+            .all(|(advisory, score)| score == raw[advisory as usize]));
+        assert_eq!(scores.iter().count(), VAdvisory::ALL.len());
+        assert_eq!(scores.advisory(), VAdvisory::StrengthenClimb2500);
+    }
+}
@@ -0,0 +1,751 @@
+use alloc::vec::Vec;
+use core::fmt;
+
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::acceleration::meter_per_second_squared;
+use uom::si::angle::radian;
+use uom::si::angular_acceleration::degree_per_second_squared;
+use uom::si::angular_velocity::{degree_per_second, radian_per_second};
+use uom::si::f32::*;
+use uom::si::length::{foot, meter};
+use uom::si::time::second;
+use uom::si::velocity::{foot_per_minute, meter_per_second};
+
+use crate::combined::Maneuver;
+use crate::encounter::{AircraftState, Encounter, Frame, IntruderCas};
+use crate::{Advisory, HAdvisory, VAdvisory};
+
+/// Turn rate of the weak horizontal advisories in degrees per second
+const WEAK_TURN_RATE: f32 = 1.5;
+
+/// Turn rate of the strong horizontal advisories in degrees per second
+const STRONG_TURN_RATE: f32 = 3.0;
+
+/// A point mass aircraft, flying with constant speed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aircraft {
+    /// Position as east, north, up
+    pub position: [Length; 3],
+
+    pub ground_speed: Velocity,
+
+    /// Heading, clockwise from north
+    pub heading: Angle,
+
+    /// Vertical speed, positive when climbing
+    pub vertical_speed: Velocity,
+
+    /// Turn rate, positive when turning right
+    pub turn_rate: AngularVelocity,
+}
+
+impl Aircraft {
+    /// Creates an aircraft in straight and level flight
+    pub fn new(position: [Length; 3], ground_speed: Velocity, heading: Angle) -> Self {
+        Self {
+            position,
+            ground_speed,
+            heading,
+            vertical_speed: Velocity::new::<meter_per_second>(0.0),
+            turn_rate: AngularVelocity::new::<radian_per_second>(0.0),
+        }
+    }
+
+    /// The state of the aircraft in an east, north, up frame
+    pub fn state(&self) -> AircraftState {
+        let heading = self.heading.get::<radian>();
+        AircraftState {
+            position: self.position,
+            velocity: [
+                self.ground_speed * heading.sin(),
+                self.ground_speed * heading.cos(),
+                self.vertical_speed,
+            ],
+            heading: self.heading,
+        }
+    }
+
+    /// Advances the aircraft by `dt`, with the current turn rate and vertical speed
+    fn advance(&mut self, dt: Time) {
+        let dt = dt.get::<second>();
+        let turn = self.turn_rate.get::<radian_per_second>() * dt;
+
+        // the mean heading over the step integrates a constant turn rate to second order
+        let heading = self.heading.get::<radian>() + turn / 2.0;
+        let distance = self.ground_speed.get::<meter_per_second>() * dt;
+        let climb = self.vertical_speed.get::<meter_per_second>() * dt;
+
+        self.position = [
+            self.position[0] + Length::new::<meter>(distance * heading.sin()),
+            self.position[1] + Length::new::<meter>(distance * heading.cos()),
+            self.position[2] + Length::new::<meter>(climb),
+        ];
+        self.heading = Angle::new::<radian>(self.heading.get::<radian>() + turn);
+    }
+}
+
+/// How the pilot of the homeship responds to advisories
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PilotModel {
+    /// Time from a new advisory until the pilot starts to follow it
+    pub delay: Time,
+
+    /// Acceleration used to reach the commanded vertical speed
+    pub vertical_acceleration: Acceleration,
+
+    /// Acceleration used to reach the commanded turn rate
+    pub turn_acceleration: AngularAcceleration,
+}
+
+impl Default for PilotModel {
+    /// The standard pilot of TCAS, with a delay of 5 s and a vertical acceleration of g/4
+    fn default() -> Self {
+        Self {
+            delay: Time::new::<second>(5.0),
+            vertical_acceleration: Acceleration::new::<meter_per_second_squared>(9.806_65 / 4.0),
+            turn_acceleration: AngularAcceleration::new::<degree_per_second_squared>(1.5),
+        }
+    }
+}
+
+/// Errors reported for a [`SimConfig`] a [`Simulation`] could not run with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SimConfigError {
+    /// The step is zero, negative or not finite, so the simulation would never advance
+    InvalidStep,
+
+    /// The duration is negative or not finite
+    InvalidDuration,
+
+    /// The duration divided by the step is too large to count the samples
+    TooManySteps,
+}
+
+impl fmt::Display for SimConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidStep => write!(f, "the step must be finite and greater than zero"),
+            Self::InvalidDuration => write!(f, "the duration must be finite and not negative"),
+            Self::TooManySteps => write!(f, "the duration is too long for the step"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SimConfigError {}
+
+/// Parameters of a [`Simulation`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SimConfig {
+    /// Integration step, the CAS is evaluated once per step
+    pub step: Time,
+
+    /// Time after which the simulation stops
+    pub duration: Time,
+
+    pub pilot: PilotModel,
+
+    /// Which advisories the pilot of the homeship follows, [`Maneuver::None`] simulates an
+    /// unequipped homeship
+    pub respond_to: Maneuver,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            step: Time::new::<second>(1.0),
+            duration: Time::new::<second>(80.0),
+            pilot: PilotModel::default(),
+            respond_to: Maneuver::Both,
+        }
+    }
+}
+
+impl SimConfig {
+    /// Creates a new configuration, rejecting the ones [`SimConfig::validate`] rejects
+    pub fn new(
+        step: Time,
+        duration: Time,
+        pilot: PilotModel,
+        respond_to: Maneuver,
+    ) -> Result<Self, SimConfigError> {
+        let config = Self {
+            step,
+            duration,
+            pilot,
+            respond_to,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Checks that the step is finite and greater than zero, the duration finite and not
+    /// negative, and that the number of samples fits into a `usize`, so that
+    /// [`Simulation::run`] terminates
+    pub fn validate(&self) -> Result<(), SimConfigError> {
+        self.sample_count().map(|_| ())
+    }
+
+    /// Number of samples [`Simulation::run`] records from time zero, one at every multiple of
+    /// the step up to and including the duration
+    pub fn sample_count(&self) -> Result<usize, SimConfigError> {
+        let step = self.step.get::<second>();
+        if !(step.is_finite() && step > 0.0) {
+            return Err(SimConfigError::InvalidStep);
+        }
+
+        let duration = self.duration.get::<second>();
+        if !(duration.is_finite() && duration >= 0.0) {
+            return Err(SimConfigError::InvalidDuration);
+        }
+
+        let steps = (duration / step).floor();
+        if steps >= usize::MAX as f32 {
+            return Err(SimConfigError::TooManySteps);
+        }
+        (steps as usize)
+            .checked_add(1)
+            .ok_or(SimConfigError::TooManySteps)
+    }
+}
+
+/// One recorded step of a [`Simulation`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sample {
+    pub time: Time,
+    pub ownship: AircraftState,
+    pub intruder: AircraftState,
+
+    /// The advisories issued in this step
+    pub horizontal: HAdvisory,
+    pub vertical: VAdvisory,
+
+    /// The advisories the pilot follows in this step
+    pub flown_horizontal: HAdvisory,
+    pub flown_vertical: VAdvisory,
+}
+
+impl Sample {
+    /// The encounter at the time of this sample
+    pub fn encounter(&self) -> Encounter {
+        Encounter::new(Frame::Enu, self.ownship, self.intruder)
+    }
+}
+
+/// The closest point of approach of a trajectory
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClosestApproach {
+    pub time: Time,
+
+    /// Horizontal distance at the closest point of approach
+    pub horizontal: Length,
+
+    /// Absolute vertical distance at the closest point of approach
+    pub vertical: Length,
+}
+
+impl ClosestApproach {
+    /// Whether the aircraft came closer than 500 ft horizontally and 100 ft vertically
+    pub fn is_nmac(&self) -> bool {
+        self.horizontal < Length::new::<foot>(500.0) && self.vertical < Length::new::<foot>(100.0)
+    }
+}
+
+/// A recorded encounter
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trajectory {
+    pub samples: Vec<Sample>,
+
+    /// The sample with the smallest horizontal distance, preferring the smaller vertical distance
+    /// among equal horizontal distances
+    pub closest_approach: ClosestApproach,
+}
+
+impl Trajectory {
+    /// Whether a near mid-air collision happened at any time
+    ///
+    /// Between two samples, the aircraft are assumed to move linearly relative to each other, so a
+    /// collision in between the samples is found as well.
+    pub fn nmac(&self) -> bool {
+        self.samples
+            .iter()
+            .any(|sample| separation(sample).is_nmac())
+            || self.samples.windows(2).any(|w| nmac_between(&w[0], &w[1]))
+    }
+}
+
+fn separation(sample: &Sample) -> ClosestApproach {
+    let encounter = sample.encounter();
+    ClosestApproach {
+        time: sample.time,
+        horizontal: encounter.range(),
+        vertical: encounter.height().abs(),
+    }
+}
+
+/// Whether the relative motion from `a` to `b`, taken as linear, enters the NMAC cylinder
+///
+/// The intervals of the step in which the aircraft are horizontally and vertically too close are
+/// computed separately, a near mid-air collision happened if they overlap.
+fn nmac_between(a: &Sample, b: &Sample) -> bool {
+    let relative = |s: &Sample| {
+        [0, 1, 2].map(|i| (s.intruder.position[i] - s.ownship.position[i]).get::<meter>())
+    };
+    let (start, end) = (relative(a), relative(b));
+    let motion = [0, 1, 2].map(|i| end[i] - start[i]);
+    let radius = Length::new::<foot>(500.0).get::<meter>();
+    let height = Length::new::<foot>(100.0).get::<meter>();
+
+    // |start + s * motion| < radius for the horizontal axes
+    let qa = motion[0] * motion[0] + motion[1] * motion[1];
+    let qb = 2.0 * (start[0] * motion[0] + start[1] * motion[1]);
+    let qc = start[0] * start[0] + start[1] * start[1] - radius * radius;
+    let horizontal = if qa == 0.0 {
+        if qc < 0.0 {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            return false;
+        }
+    } else {
+        let discriminant = qb * qb - 4.0 * qa * qc;
+        if discriminant <= 0.0 {
+            return false;
+        }
+        let root = discriminant.sqrt();
+        ((-qb - root) / (2.0 * qa), (-qb + root) / (2.0 * qa))
+    };
+
+    // |start + s * motion| < height for the vertical axis
+    let vertical = if motion[2] == 0.0 {
+        if start[2].abs() < height {
+            (f32::NEG_INFINITY, f32::INFINITY)
+        } else {
+            return false;
+        }
+    } else {
+        let (low, high) = (
+            (-height - start[2]) / motion[2],
+            (height - start[2]) / motion[2],
+        );
+        (low.min(high), low.max(high))
+    };
+
+    let low = horizontal.0.max(vertical.0).max(0.0);
+    let high = horizontal.1.min(vertical.1).min(1.0);
+    low < high
+}
+
+/// The delayed response of the pilot to one system
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Response<A> {
+    active: A,
+    pending: A,
+    since: Time,
+}
+
+impl<A: Advisory> Response<A> {
+    fn new(advisory: A) -> Self {
+        Self {
+            active: advisory,
+            pending: advisory,
+            since: Time::new::<second>(0.0),
+        }
+    }
+
+    /// Returns the advisory flown at `now`, after `issued` was issued
+    fn update(&mut self, now: Time, issued: A, delay: Time) -> A {
+        if issued != self.pending {
+            (self.pending, self.since) = (issued, now);
+        }
+        if self.pending != self.active && now - self.since >= delay {
+            self.active = self.pending;
+        }
+        self.active
+    }
+}
+
+/// Runs the HorizontalCAS and the VerticalCAS on the homeship in a closed loop
+///
+/// In every step, both systems are evaluated on the current [`Encounter`], the pilot of the
+/// homeship responds to the advisories according to the [`PilotModel`], and both aircraft are
+/// advanced by one step. The intruder keeps its turn rate and vertical speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Simulation {
+    pub config: SimConfig,
+    pub ownship: Aircraft,
+    pub intruder: Aircraft,
+    pub time: Time,
+    pub cas: IntruderCas,
+    horizontal: Response<HAdvisory>,
+    vertical: Response<VAdvisory>,
+}
+
+impl Simulation {
+    /// Creates a new simulation at time zero, with both systems clear of conflict
+    pub fn new(config: SimConfig, ownship: Aircraft, intruder: Aircraft) -> Self {
+        Self {
+            config,
+            ownship,
+            intruder,
+            time: Time::new::<second>(0.0),
+            cas: IntruderCas::default(),
+            horizontal: Response::new(HAdvisory::ClearOfConflict),
+            vertical: Response::new(VAdvisory::ClearOfConflict),
+        }
+    }
+
+    /// Evaluates both systems, advances by one step and returns the sample before the step
+    pub fn step(&mut self) -> Sample {
+        let (horizontal, vertical) =
+            self.cas
+                .step(Frame::Enu, self.ownship.state(), self.intruder.state());
+
+        let (pilot, respond_to) = (self.config.pilot, self.config.respond_to);
+        let flown_horizontal = if respond_to.is_horizontal() {
+            self.horizontal.update(self.time, horizontal, pilot.delay)
+        } else {
+            HAdvisory::ClearOfConflict
+        };
+        let flown_vertical = if respond_to.is_vertical() {
+            self.vertical.update(self.time, vertical, pilot.delay)
+        } else {
+            VAdvisory::ClearOfConflict
+        };
+
+        let sample = Sample {
+            time: self.time,
+            ownship: self.ownship.state(),
+            intruder: self.intruder.state(),
+            horizontal,
+            vertical,
+            flown_horizontal,
+            flown_vertical,
+        };
+
+        let dt = self.config.step;
+        let (ownship, dt_s) = (&mut self.ownship, dt.get::<second>());
+        ownship.turn_rate = AngularVelocity::new::<degree_per_second>(approach(
+            ownship.turn_rate.get::<degree_per_second>(),
+            turn_rate(flown_horizontal),
+            pilot.turn_acceleration.get::<degree_per_second_squared>() * dt_s,
+        ));
+        let target = vertical_speed(flown_vertical, ownship.vertical_speed)
+            .unwrap_or(ownship.vertical_speed);
+        ownship.vertical_speed = Velocity::new::<meter_per_second>(approach(
+            ownship.vertical_speed.get::<meter_per_second>(),
+            target.get::<meter_per_second>(),
+            pilot
+                .vertical_acceleration
+                .get::<meter_per_second_squared>()
+                * dt_s,
+        ));
+
+        self.ownship.advance(dt);
+        self.intruder.advance(dt);
+        self.time += dt;
+
+        sample
+    }
+
+    /// Runs the simulation for the configured duration and returns the recorded trajectory
+    ///
+    /// The time of every sample is computed from its index instead of summing up the steps, so
+    /// rounding errors neither accumulate nor drop the last sample.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the configuration is invalid, see [`SimConfig::validate`], as the simulation
+    /// would never end.
+    pub fn run(mut self) -> Trajectory {
+        let count = match self.config.sample_count() {
+            Ok(count) => count,
+            Err(e) => panic!("invalid simulation config: {e}"),
+        };
+
+        let (start, step) = (self.time, self.config.step);
+        let mut samples = Vec::new();
+        for i in 0..count {
+            self.time = start + step * i as f32;
+            if self.time > self.config.duration {
+                break;
+            }
+            samples.push(self.step());
+        }
+
+        let closest_approach = samples
+            .iter()
+            .map(separation)
+            .min_by(|a, b| {
+                a.horizontal
+                    .partial_cmp(&b.horizontal)
+                    .unwrap_or(core::cmp::Ordering::Equal)
+                    .then(
+                        a.vertical
+                            .partial_cmp(&b.vertical)
+                            .unwrap_or(core::cmp::Ordering::Equal),
+                    )
+            })
+            .unwrap_or(ClosestApproach {
+                time: self.time,
+                horizontal: Length::new::<meter>(f32::INFINITY),
+                vertical: Length::new::<meter>(f32::INFINITY),
+            });
+
+        Trajectory {
+            samples,
+            closest_approach,
+        }
+    }
+}
+
+/// Moves `current` towards `target` by at most `max_change`
+fn approach(current: f32, target: f32, max_change: f32) -> f32 {
+    current + (target - current).clamp(-max_change, max_change)
+}
+
+/// The turn rate commanded by `advisory` in degrees per second, positive to the right
+fn turn_rate(advisory: HAdvisory) -> f32 {
+    match advisory {
+        HAdvisory::ClearOfConflict => 0.0,
+        HAdvisory::WeakLeft => -WEAK_TURN_RATE,
+        HAdvisory::WeakRight => WEAK_TURN_RATE,
+        HAdvisory::StrongLeft => -STRONG_TURN_RATE,
+        HAdvisory::StrongRight => STRONG_TURN_RATE,
+    }
+}
+
+/// The vertical speed commanded by `advisory`, `None` if `current` already complies
+fn vertical_speed(advisory: VAdvisory, current: Velocity) -> Option<Velocity> {
+    let fpm = Velocity::new::<foot_per_minute>;
+    let (min, max) = match advisory {
+        VAdvisory::ClearOfConflict => return None,
+        VAdvisory::DoNotClimb => (None, Some(fpm(0.0))),
+        VAdvisory::DoNotDescend => (Some(fpm(0.0)), None),
+        VAdvisory::Descend1500 | VAdvisory::StrengthenDescend1500 => (None, Some(fpm(-1500.0))),
+        VAdvisory::StrengthenDescend2500 => (None, Some(fpm(-2500.0))),
+        VAdvisory::Climb1500 | VAdvisory::StrengthenClimb1500 => (Some(fpm(1500.0)), None),
+        VAdvisory::StrengthenClimb2500 => (Some(fpm(2500.0)), None),
+    };
+
+    match (min, max) {
+        (Some(min), _) if current < min => Some(min),
+        (_, Some(max)) if current > max => Some(max),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use uom::si::angle::degree;
+    use uom::si::velocity::knot;
+
+    fn aircraft(east: f32, altitude: f32, heading: f32) -> Aircraft {
+        Aircraft::new(
+            [
+                Length::new::<meter>(east),
+                Length::new::<meter>(0.0),
+                Length::new::<foot>(altitude),
+            ],
+            Velocity::new::<knot>(200.0),
+            Angle::new::<degree>(heading),
+        )
+    }
+
+    /// Head on, 40 s to collision, with the intruder slightly above
+    fn head_on(respond_to: Maneuver) -> Trajectory {
+        let config = SimConfig {
+            respond_to,
+            ..Default::default()
+        };
+        let speed = Velocity::new::<knot>(200.0).get::<meter_per_second>();
+        Simulation::new(
+            config,
+            aircraft(0.0, 5000.0, 90.0),
+            aircraft(80.0 * speed, 5050.0, 270.0),
+        )
+        .run()
+    }
+
+    #[test]
+    pub fn straight_flight() {
+        let mut aircraft = aircraft(0.0, 0.0, 45.0);
+        aircraft.vertical_speed = Velocity::new::<meter_per_second>(2.0);
+        aircraft.advance(Time::new::<second>(10.0));
+
+        let [east, north, up] = aircraft.position.map(|p| p.get::<meter>());
+        let distance = Velocity::new::<knot>(200.0).get::<meter_per_second>() * 10.0;
+        assert!((east - distance / 2f32.sqrt()).abs() < 1e-2);
+        assert!((north - distance / 2f32.sqrt()).abs() < 1e-2);
+        assert!((up - 20.0).abs() < 1e-4);
+    }
+
+    #[test]
+    pub fn constant_turn() {
+        let mut aircraft = aircraft(0.0, 0.0, 0.0);
+        aircraft.turn_rate = AngularVelocity::new::<degree_per_second>(3.0);
+        for _ in 0..60 {
+            aircraft.advance(Time::new::<second>(1.0));
+        }
+
+        // a 180 degree turn ends up one diameter to the east, heading south
+        let diameter =
+            2.0 * aircraft.ground_speed.get::<meter_per_second>() * 60.0 / core::f32::consts::PI;
+        let [east, north, _] = aircraft.position.map(|p| p.get::<meter>());
+        assert!(
+            (east - diameter).abs() < 0.01 * diameter,
+            "{east} {diameter}"
+        );
+        assert!(north.abs() < 0.01 * diameter);
+        assert!((aircraft.heading.get::<degree>() - 180.0).abs() < 1e-3);
+    }
+
+    #[test]
+    pub fn pilot_delay() {
+        let (s, delay) = (Time::new::<second>, Time::new::<second>(5.0));
+        let mut response = Response::new(VAdvisory::ClearOfConflict);
+
+        assert_eq!(
+            response.update(s(1.0), VAdvisory::Climb1500, delay),
+            VAdvisory::ClearOfConflict
+        );
+        assert_eq!(
+            response.update(s(5.0), VAdvisory::Climb1500, delay),
+            VAdvisory::ClearOfConflict
+        );
+        assert_eq!(
+            response.update(s(6.0), VAdvisory::Climb1500, delay),
+            VAdvisory::Climb1500
+        );
+        assert_eq!(
+            vertical_speed(
+                VAdvisory::DoNotDescend,
+                Velocity::new::<foot_per_minute>(200.0)
+            ),
+            None
+        );
+    }
+
+    #[test]
+    pub fn advisories_resolve_head_on() {
+        let unequipped = head_on(Maneuver::None);
+        assert!(unequipped.nmac());
+        assert!(unequipped.closest_approach.is_nmac());
+        assert!(unequipped
+            .samples
+            .iter()
+            .all(|s| s.flown_vertical == VAdvisory::ClearOfConflict));
+
+        let equipped = head_on(Maneuver::Vertical);
+        assert!(!equipped.nmac(), "{:?}", equipped.closest_approach);
+        assert!(equipped
+            .samples
+            .iter()
+            .any(|s| s.flown_vertical != VAdvisory::ClearOfConflict));
+        assert!(equipped.closest_approach.vertical > unequipped.closest_approach.vertical);
+        assert_eq!(equipped.samples.len(), unequipped.samples.len());
+    }
+
+    #[test]
+    pub fn nmac_between_samples() {
+        let sample = |time: f32, intruder: [f32; 3]| Sample {
+            time: Time::new::<second>(time),
+            ownship: aircraft(0.0, 0.0, 0.0).state(),
+            intruder: AircraftState {
+                position: intruder.map(Length::new::<meter>),
+                ..aircraft(0.0, 0.0, 90.0).state()
+            },
+            horizontal: HAdvisory::ClearOfConflict,
+            vertical: VAdvisory::ClearOfConflict,
+            flown_horizontal: HAdvisory::ClearOfConflict,
+            flown_vertical: VAdvisory::ClearOfConflict,
+        };
+        let trajectory = |start: [f32; 3], end: [f32; 3]| Trajectory {
+            samples: vec![sample(0.0, start), sample(10.0, end)],
+            closest_approach: separation(&sample(0.0, start)),
+        };
+
+        // passing 50 m abeam in between two samples 2 km apart
+        let passing = trajectory([-1000.0, 50.0, 0.0], [1000.0, 50.0, 0.0]);
+        assert!(passing.samples.iter().all(|s| !separation(s).is_nmac()));
+        assert!(passing.nmac());
+
+        // climbing through the altitude of the ownship while passing
+        assert!(trajectory([-1000.0, 50.0, -100.0], [1000.0, 50.0, 100.0]).nmac());
+
+        // only level with the ownship when already far away
+        assert!(!trajectory([-1000.0, 50.0, 100.0], [1000.0, 50.0, 0.0]).nmac());
+
+        // too far abeam
+        assert!(!trajectory([-1000.0, 160.0, 0.0], [1000.0, 160.0, 0.0]).nmac());
+    }
+
+    #[test]
+    pub fn reject_invalid_config() {
+        let config = |step: f32, duration: f32| {
+            SimConfig::new(
+                Time::new::<second>(step),
+                Time::new::<second>(duration),
+                PilotModel::default(),
+                Maneuver::Both,
+            )
+        };
+
+        assert!(config(0.5, 10.0).is_ok());
+        for step in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(config(step, 10.0), Err(SimConfigError::InvalidStep));
+        }
+        for duration in [-1.0, f32::NAN, f32::INFINITY] {
+            assert_eq!(config(1.0, duration), Err(SimConfigError::InvalidDuration));
+        }
+        assert_eq!(config(1e-30, 1e30), Err(SimConfigError::TooManySteps));
+    }
+
+    #[test]
+    pub fn tiny_steps_terminate() {
+        // summing up steps of 0.1 µs stalls at 2 s, as the step is below the precision of f32
+        let config = SimConfig::new(
+            Time::new::<second>(1e-7),
+            Time::new::<second>(80.0),
+            PilotModel::default(),
+            Maneuver::Both,
+        )
+        .unwrap();
+        assert_eq!(config.sample_count(), Ok(800_000_001));
+
+        let config = SimConfig {
+            duration: Time::new::<second>(1e-5),
+            ..config
+        };
+        let trajectory =
+            Simulation::new(config, aircraft(0.0, 0.0, 0.0), aircraft(0.0, 5000.0, 0.0)).run();
+        assert_eq!(trajectory.samples.len(), 101);
+        assert_eq!(
+            trajectory.samples[100].time,
+            Time::new::<second>(1e-7) * 100.0
+        );
+
+        // steps of 0.1 s are inexact, but still end exactly at the duration
+        let config = SimConfig {
+            step: Time::new::<second>(0.1),
+            duration: Time::new::<second>(2.0),
+            ..Default::default()
+        };
+        let trajectory =
+            Simulation::new(config, aircraft(0.0, 0.0, 0.0), aircraft(0.0, 5000.0, 0.0)).run();
+        assert_eq!(trajectory.samples.len(), 21);
+        assert_eq!(trajectory.samples[20].time.get::<second>(), 2.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid simulation config")]
+    pub fn run_rejects_zero_step() {
+        let config = SimConfig {
+            step: Time::new::<second>(0.0),
+            ..Default::default()
+        };
+        Simulation::new(config, aircraft(0.0, 0.0, 0.0), aircraft(0.0, 0.0, 0.0)).run();
+    }
+}
@@ -0,0 +1,306 @@
+use uom::si::f32::{Length, Time, Velocity};
+use uom::si::length::meter;
+use uom::si::time::second;
+use uom::si::velocity::meter_per_second;
+
+/// The tau values a family of networks was trained for
+///
+/// Networks like the HorizontalCAS are split by tau, the time until loss of separation. Each
+/// network is only trained for one specific tau. A `TauSchedule` maps an arbitrary tau to the
+/// network trained for the nearest lower tau, which is the network that is evaluated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TauSchedule<const N: usize> {
+    /// Trained tau values in seconds, strictly ascending
+    taus: [f32; N],
+}
+
+impl<const N: usize> TauSchedule<N> {
+    /// Creates a new schedule from the trained tau values in seconds
+    ///
+    /// # Panics
+    ///
+    /// Panics if `taus` is empty or not strictly ascending.
+    pub const fn new(taus: [f32; N]) -> Self {
+        assert!(N > 0, "a tau schedule needs at least one tau value");
+
+        let mut i = 1;
+        while i < N {
+            assert!(
+                taus[i - 1] < taus[i],
+                "tau values must be strictly ascending"
+            );
+            i += 1;
+        }
+
+        Self { taus }
+    }
+
+    /// Index of the network selected for `tau`
+    ///
+    /// This is the index of the largest trained tau that is less than or equal to `tau`. A tau
+    /// below the first trained tau (or NaN) selects the first network, a tau beyond the last
+    /// trained tau selects the last network.
+    pub fn index(&self, tau: Time) -> usize {
+        let tau = tau.get::<second>();
+        self.taus.iter().rposition(|&t| t <= tau).unwrap_or(0)
+    }
+
+    /// The two networks surrounding `tau` and the interpolation weight between them
+    ///
+    /// Returns `(lower, upper, weight)`, with `lower` and `upper` being the indices of the
+    /// networks trained for the nearest lower and the nearest higher tau. `weight` is in `0..1`
+    /// and grows linearly from the lower to the upper trained tau, so that
+    /// `lower_scores * (1 - weight) + upper_scores * weight` interpolates the scores. Outside of
+    /// the trained tau values (or for NaN), both indices point to the nearest network and
+    /// `weight` is zero.
+    pub fn bracket(&self, tau: Time) -> (usize, usize, f32) {
+        let lower = self.index(tau);
+        let upper = lower + 1;
+        let tau = tau.get::<second>();
+
+        if upper < N && tau > self.taus[lower] {
+            let weight = (tau - self.taus[lower]) / (self.taus[upper] - self.taus[lower]);
+            (lower, upper, weight)
+        } else {
+            (lower, lower, 0.0)
+        }
+    }
+
+    /// Trained tau of the network selected for `tau`
+    pub fn select(&self, tau: Time) -> Time {
+        self.trained_tau(self.index(tau))
+    }
+
+    /// Trained tau of the network at `index`
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    pub fn trained_tau(&self, index: usize) -> Time {
+        Time::new::<second>(self.taus[index])
+    }
+
+    /// Number of trained tau values, which equals the number of networks
+    pub const fn len(&self) -> usize {
+        N
+    }
+
+    /// Always `false`, as a schedule contains at least one tau value
+    pub const fn is_empty(&self) -> bool {
+        false
+    }
+
+    /// Iterates over all trained tau values in ascending order
+    pub fn iter(&self) -> impl Iterator<Item = Time> + '_ {
+        self.taus.iter().map(|&t| Time::new::<second>(t))
+    }
+}
+
+/// Time until the closest point of approach, from the relative horizontal position and velocity
+/// of the intruder
+///
+/// Unlike range divided by range rate, this accounts for intruders that will pass at a distance.
+/// Returns `None` if the intruder is not closing in, that is if it is diverging or if there is no
+/// relative motion.
+pub fn time_to_cpa(
+    relative_position: [Length; 2],
+    relative_velocity: [Velocity; 2],
+) -> Option<Time> {
+    let [x, y] = relative_position.map(|l| l.get::<meter>());
+    let [vx, vy] = relative_velocity.map(|v| v.get::<meter_per_second>());
+
+    let closure = x * vx + y * vy;
+    let speed_squared = vx * vx + vy * vy;
+    (closure < 0.0).then(|| Time::new::<second>(-closure / speed_squared))
+}
+
+/// TCAS style modified tau
+///
+/// The modified tau is `-(range^2 - dmod^2) / (range * range_rate)`. It is shorter than the plain
+/// range over range rate, and reaches zero when the intruder comes within `dmod`, which protects
+/// against slowly closing intruders. Within `dmod`, this returns zero regardless of the range
+/// rate. Otherwise `None` is returned if the range is not closing.
+pub fn modified_tau(range: Length, range_rate: Velocity, dmod: Length) -> Option<Time> {
+    let (r, r_dot, dmod) = (
+        range.get::<meter>(),
+        range_rate.get::<meter_per_second>(),
+        dmod.get::<meter>(),
+    );
+
+    if r <= dmod {
+        Some(Time::new::<second>(0.0))
+    } else if r_dot < 0.0 {
+        Some(Time::new::<second>(-(r * r - dmod * dmod) / (r * r_dot)))
+    } else {
+        None
+    }
+}
+
+/// Time until homeship and intruder are at the same altitude
+///
+/// `height` is the altitude of the intruder relative to the homeship, `height_rate` its rate of
+/// change. Returns zero if both are at the same altitude, and `None` if the altitudes are not
+/// converging.
+pub fn time_to_co_altitude(height: Length, height_rate: Velocity) -> Option<Time> {
+    let (h, h_dot) = (height.get::<meter>(), height_rate.get::<meter_per_second>());
+
+    if h == 0.0 {
+        Some(Time::new::<second>(0.0))
+    } else if h * h_dot < 0.0 {
+        Some(Time::new::<second>(-h / h_dot))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::HCAS_TAU_SCHEDULE;
+    use uom::si::length::foot;
+    use uom::si::velocity::foot_per_minute;
+
+    fn m(meters: f32) -> Length {
+        Length::new::<meter>(meters)
+    }
+
+    fn mps(meters_per_second: f32) -> Velocity {
+        Velocity::new::<meter_per_second>(meters_per_second)
+    }
+
+    #[test]
+    fn cpa_of_passing_intruder() {
+        // intruder 1 km ahead and 300 m to the side, flying towards us with 100 m/s
+        let tau = time_to_cpa([m(1000.0), m(300.0)], [mps(-100.0), mps(0.0)]);
+        assert_eq!(tau, Some(Time::new::<second>(10.0)));
+
+        // diverging or without relative motion there is no threat
+        assert_eq!(
+            time_to_cpa([m(1000.0), m(0.0)], [mps(10.0), mps(0.0)]),
+            None
+        );
+        assert_eq!(time_to_cpa([m(1000.0), m(0.0)], [mps(0.0), mps(0.0)]), None);
+        assert_eq!(
+            time_to_cpa([m(0.0), m(300.0)], [mps(100.0), mps(0.0)]),
+            None
+        );
+    }
+
+    #[test]
+    fn modified_tau_with_dmod() {
+        let dmod = Length::new::<foot>(0.0);
+        let plain = modified_tau(m(2000.0), mps(-100.0), dmod).unwrap();
+        assert_eq!(plain.get::<second>(), 20.0);
+
+        let tau = modified_tau(m(2000.0), mps(-100.0), m(1000.0)).unwrap();
+        assert_eq!(tau.get::<second>(), 15.0);
+        assert!(tau < plain);
+
+        assert_eq!(
+            modified_tau(m(900.0), mps(5.0), m(1000.0)),
+            Some(Time::new::<second>(0.0))
+        );
+        assert_eq!(modified_tau(m(2000.0), mps(0.0), m(1000.0)), None);
+        assert_eq!(modified_tau(m(2000.0), mps(1.0), m(1000.0)), None);
+    }
+
+    #[test]
+    fn co_altitude() {
+        let climbing = Velocity::new::<foot_per_minute>(1200.0);
+        let tau = time_to_co_altitude(Length::new::<foot>(-600.0), climbing).unwrap();
+        assert!((tau.get::<second>() - 30.0).abs() < 1e-3);
+
+        assert_eq!(
+            time_to_co_altitude(Length::new::<foot>(600.0), climbing),
+            None
+        );
+        assert_eq!(
+            time_to_co_altitude(Length::new::<foot>(600.0), mps(0.0)),
+            None
+        );
+        assert_eq!(
+            time_to_co_altitude(m(0.0), climbing),
+            Some(Time::new::<second>(0.0))
+        );
+    }
+
+    #[test]
+    fn hcas_schedule_matches_trained_networks() {
+        let trained = [0.0, 5.0, 10.0, 15.0, 20.0, 30.0, 40.0, 60.0];
+        assert_eq!(HCAS_TAU_SCHEDULE.len(), trained.len());
+        assert!(HCAS_TAU_SCHEDULE
+            .iter()
+            .zip(trained)
+            .all(|(t, expected)| t.get::<second>() == expected));
+    }
+
+    /// The network for `tau`, written independently of [`TauSchedule::index`]
+    fn expected_index(tau: f32) -> usize {
+        match tau {
+            0.0..5.0 => 0,
+            5.0..10.0 => 1,
+            10.0..15.0 => 2,
+            15.0..20.0 => 3,
+            20.0..30.0 => 4,
+            30.0..40.0 => 5,
+            40.0..60.0 => 6,
+            60.0.. => 7,
+            // negative taus and NaN
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn index_selects_nearest_lower_tau() {
+        let sweep = (-100..8000).map(|centis| centis as f32 / 100.0);
+        let special = [
+            15.0,
+            15.25,
+            15.49,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            f32::NAN,
+        ];
+
+        for tau in sweep.chain(special) {
+            let index = HCAS_TAU_SCHEDULE.index(Time::new::<second>(tau));
+            assert_eq!(
+                index,
+                expected_index(tau),
+                "tau {tau} selected the wrong network"
+            );
+        }
+
+        // the gap the sweep was added for
+        for tau in [15.0, 15.25, 15.49] {
+            assert_eq!(HCAS_TAU_SCHEDULE.index(Time::new::<second>(tau)), 3);
+        }
+    }
+
+    #[test]
+    fn gap_between_15_and_15_5_is_closed() {
+        let index = HCAS_TAU_SCHEDULE.index(Time::new::<second>(15.2));
+        assert_eq!(HCAS_TAU_SCHEDULE.trained_tau(index).get::<second>(), 15.0);
+    }
+
+    #[test]
+    fn bracket_interpolates_between_trained_taus() {
+        let bracket = |tau| HCAS_TAU_SCHEDULE.bracket(Time::new::<second>(tau));
+
+        assert_eq!(bracket(0.0), (0, 0, 0.0));
+        assert_eq!(bracket(2.5), (0, 1, 0.5));
+        assert_eq!(bracket(15.0), (3, 3, 0.0));
+        assert_eq!(bracket(35.0), (5, 6, 0.5));
+        assert_eq!(bracket(45.0), (6, 7, 0.25));
+        assert_eq!(bracket(60.0), (7, 7, 0.0));
+        assert_eq!(bracket(90.0), (7, 7, 0.0));
+        assert_eq!(bracket(-1.0), (0, 0, 0.0));
+        assert_eq!(bracket(f32::NAN), (0, 0, 0.0));
+    }
+
+    #[test]
+    fn out_of_range_tau_is_clamped() {
+        assert_eq!(HCAS_TAU_SCHEDULE.index(Time::new::<second>(-3.0)), 0);
+        assert_eq!(HCAS_TAU_SCHEDULE.index(Time::new::<second>(1e4)), 7);
+    }
+}
@@ -0,0 +1,372 @@
+#[allow(unused_imports)]
+use num::Float;
+
+use uom::si::acceleration::meter_per_second_squared;
+use uom::si::angle::radian;
+use uom::si::f32::*;
+use uom::si::length::meter;
+use uom::si::time::second;
+use uom::si::velocity::meter_per_second;
+
+use crate::encounter::{AircraftState, Frame};
+
+/// Standard deviation of the velocity of a freshly initialized track in meters per second
+const INITIAL_VELOCITY_STD: f32 = 300.0;
+
+/// The filter smoothing each axis of a track
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TrackFilter {
+    /// Fixed gain filter, with `alpha` weighting the position and `beta` the velocity residual
+    AlphaBeta { alpha: f32, beta: f32 },
+
+    /// Constant velocity Kalman filter, with a white noise acceleration of the given standard
+    /// deviation
+    Kalman { acceleration_noise: Acceleration },
+}
+
+/// Parameters of a [`Tracker`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackerConfig {
+    pub filter: TrackFilter,
+
+    /// Standard deviation of the position reports
+    pub measurement_noise: Length,
+
+    /// Time after the last report until the track is dropped
+    pub coast_limit: Time,
+}
+
+/// One timestamped position report of an intruder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Report {
+    pub time: Time,
+
+    /// Position, ordered like the axes of the [`Frame`] used for the encounter
+    pub position: [Length; 3],
+}
+
+/// The smoothed state of an intruder
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Track {
+    /// Time the track was predicted to
+    pub time: Time,
+
+    /// Position, ordered like the axes of the reports
+    pub position: [Length; 3],
+
+    /// Velocity, ordered like the axes of the reports
+    pub velocity: [Velocity; 3],
+
+    /// Covariance of position and velocity per axis, in m², m²/s and m²/s²
+    pub covariance: [[[f32; 2]; 2]; 3],
+
+    /// Number of reports the track is built on, saturating at `u8::MAX`
+    pub quality: u8,
+
+    /// Whether the track was predicted beyond the last report
+    pub coasting: bool,
+}
+
+impl Track {
+    /// Standard deviation of the position on every axis
+    pub fn position_std(&self) -> [Length; 3] {
+        self.covariance
+            .map(|p| Length::new::<meter>(p[0][0].max(0.0).sqrt()))
+    }
+
+    /// The track as the state of an aircraft, with the heading taken from the horizontal
+    /// velocity, ready to be used in an [`crate::encounter::Encounter`]
+    pub fn aircraft_state(&self, frame: Frame) -> AircraftState {
+        let [a, b, _] = self.velocity.map(|v| v.get::<meter_per_second>());
+        let (east, north) = match frame {
+            Frame::Enu => (a, b),
+            Frame::Ned => (b, a),
+        };
+
+        AircraftState {
+            position: self.position,
+            velocity: self.velocity,
+            heading: Angle::new::<radian>(east.atan2(north)),
+        }
+    }
+}
+
+/// State of one axis in SI units
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Axis {
+    position: f32,
+    velocity: f32,
+    covariance: [[f32; 2]; 2],
+}
+
+impl Axis {
+    /// Propagates the state by `dt` seconds, adding the process noise `q` (a spectral density)
+    fn predict(&self, dt: f32, q: f32) -> Self {
+        let [[p00, p01], [p10, p11]] = self.covariance;
+
+        // F P F^T with F = [1 dt; 0 1], plus the white noise acceleration Q
+        let (dt2, dt3, dt4) = (dt * dt, dt * dt * dt, dt * dt * dt * dt);
+        Self {
+            position: self.position + self.velocity * dt,
+            velocity: self.velocity,
+            covariance: [
+                [
+                    p00 + dt * (p01 + p10) + dt2 * p11 + q * dt4 / 4.0,
+                    p01 + dt * p11 + q * dt3 / 2.0,
+                ],
+                [p10 + dt * p11 + q * dt3 / 2.0, p11 + q * dt2],
+            ],
+        }
+    }
+
+    /// Incorporates the measured position `z` with variance `r` using the gain `k`
+    fn update(&self, z: f32, r: f32, k: [f32; 2]) -> Self {
+        let residual = z - self.position;
+        let [[p00, p01], [p10, p11]] = self.covariance;
+
+        // Joseph form, which is valid for the fixed gain of the alpha-beta filter as well
+        // P = (I - K H) P (I - K H)^T + K R K^T with H = [1 0]
+        let (a, b) = (1.0 - k[0], -k[1]);
+        let m00 = a * p00;
+        let m01 = a * p01;
+        let m10 = b * p00 + p10;
+        let m11 = b * p01 + p11;
+
+        Self {
+            position: self.position + k[0] * residual,
+            velocity: self.velocity + k[1] * residual,
+            covariance: [
+                [m00 * a + k[0] * k[0] * r, m00 * b + m01 + k[0] * k[1] * r],
+                [m10 * a + k[1] * k[0] * r, m10 * b + m11 + k[1] * k[1] * r],
+            ],
+        }
+    }
+}
+
+/// Tracks one intruder from noisy position reports
+///
+/// Every axis is filtered separately, by the filter given in the [`TrackerConfig`]. The tracker
+/// is driven by the timestamps of the reports only, so it is fully deterministic.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tracker {
+    pub config: TrackerConfig,
+    axes: [Axis; 3],
+    last_report: Option<Time>,
+    quality: u8,
+}
+
+impl Tracker {
+    /// Creates a new tracker without any report
+    pub fn new(config: TrackerConfig) -> Self {
+        Self {
+            config,
+            axes: [Axis::default(); 3],
+            last_report: None,
+            quality: 0,
+        }
+    }
+
+    /// Variance of the position reports in m²
+    fn measurement_variance(&self) -> f32 {
+        self.config.measurement_noise.get::<meter>().powi(2)
+    }
+
+    /// Spectral density of the process noise
+    fn process_noise(&self) -> f32 {
+        match self.config.filter {
+            TrackFilter::AlphaBeta { .. } => 0.0,
+            TrackFilter::Kalman { acceleration_noise } => {
+                acceleration_noise.get::<meter_per_second_squared>().powi(2)
+            }
+        }
+    }
+
+    /// Incorporates a position report and returns the updated track
+    ///
+    /// Reports that are not newer than the last report are ignored.
+    pub fn update(&mut self, report: Report) -> Track {
+        let r = self.measurement_variance();
+        let z = report.position.map(|p| p.get::<meter>());
+
+        match self.last_report {
+            None => {
+                for (axis, z) in self.axes.iter_mut().zip(z) {
+                    *axis = Axis {
+                        position: z,
+                        velocity: 0.0,
+                        covariance: [[r, 0.0], [0.0, INITIAL_VELOCITY_STD.powi(2)]],
+                    };
+                }
+            }
+            Some(last) if report.time > last => {
+                let dt = (report.time - last).get::<second>();
+                let q = self.process_noise();
+
+                for (axis, z) in self.axes.iter_mut().zip(z) {
+                    let predicted = axis.predict(dt, q);
+                    let gain = match self.config.filter {
+                        TrackFilter::AlphaBeta { alpha, beta } => [alpha, beta / dt],
+                        TrackFilter::Kalman { .. } => {
+                            let [[p00, _], [p10, _]] = predicted.covariance;
+                            [p00 / (p00 + r), p10 / (p00 + r)]
+                        }
+                    };
+                    *axis = predicted.update(z, r, gain);
+                }
+            }
+            Some(_) => return self.track_at(self.last_report.unwrap()),
+        }
+
+        self.last_report = Some(report.time);
+        self.quality = self.quality.saturating_add(1);
+        self.track_at(report.time)
+    }
+
+    /// The track predicted to `time`
+    ///
+    /// Returns `None` if there was no report yet, or if the last report is older than the coast
+    /// limit, in which case the track is considered lost.
+    pub fn track(&self, time: Time) -> Option<Track> {
+        let last = self.last_report?;
+        (time - last <= self.config.coast_limit).then(|| self.track_at(time))
+    }
+
+    fn track_at(&self, time: Time) -> Track {
+        let last = self.last_report.unwrap();
+        let dt = (time - last).get::<second>().max(0.0);
+        let q = self.process_noise();
+        let axes = self.axes.map(|axis| axis.predict(dt, q));
+
+        Track {
+            time,
+            position: axes.map(|a| Length::new::<meter>(a.position)),
+            velocity: axes.map(|a| Velocity::new::<meter_per_second>(a.velocity)),
+            covariance: axes.map(|a| a.covariance),
+            quality: self.quality,
+            coasting: time > last,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::encounter::Encounter;
+    use uom::si::angle::degree;
+
+    /// Deterministic noise in `-1..=1`
+    fn noise(i: u32) -> f32 {
+        ((i.wrapping_mul(2_654_435_761) >> 16) % 2001) as f32 / 1000.0 - 1.0
+    }
+
+    fn config(filter: TrackFilter) -> TrackerConfig {
+        TrackerConfig {
+            filter,
+            measurement_noise: Length::new::<meter>(30.0),
+            coast_limit: Time::new::<second>(5.0),
+        }
+    }
+
+    /// Feeds 60 noisy reports of an intruder flying south-west with 150 m/s and returns the
+    /// last track
+    fn track_intruder(tracker: &mut Tracker) -> Track {
+        let velocity = [-120.0, -90.0, 2.0];
+        let mut track = None;
+
+        for i in 0..60 {
+            let t = i as f32;
+            let position = [0, 1, 2].map(|axis| {
+                let truth = [8000.0, 6000.0, 300.0][axis] + velocity[axis] * t;
+                Length::new::<meter>(truth + 30.0 * noise(3 * i + axis as u32))
+            });
+            track = Some(tracker.update(Report {
+                time: Time::new::<second>(t),
+                position,
+            }));
+        }
+
+        track.unwrap()
+    }
+
+    fn assert_velocity(track: &Track, tolerance: f32) {
+        let velocity = track.velocity.map(|v| v.get::<meter_per_second>());
+        for (v, expected) in velocity.iter().zip([-120.0, -90.0, 2.0]) {
+            assert!(
+                (v - expected).abs() < tolerance,
+                "velocity {velocity:?} is off"
+            );
+        }
+    }
+
+    #[test]
+    pub fn alpha_beta_smooths_velocity() {
+        let mut tracker = Tracker::new(config(TrackFilter::AlphaBeta {
+            alpha: 0.5,
+            beta: 0.1,
+        }));
+        let track = track_intruder(&mut tracker);
+
+        assert_velocity(&track, 10.0);
+        assert_eq!(track.quality, 60);
+        assert!(!track.coasting);
+        assert!(track.position_std()[0].get::<meter>() < 30.0);
+    }
+
+    #[test]
+    pub fn kalman_converges() {
+        let mut tracker = Tracker::new(config(TrackFilter::Kalman {
+            acceleration_noise: Acceleration::new::<meter_per_second_squared>(0.5),
+        }));
+        let track = track_intruder(&mut tracker);
+
+        assert_velocity(&track, 5.0);
+        let [[p00, p01], [p10, p11]] = track.covariance[0];
+        assert!((p01 - p10).abs() < 1e-3 * p01.abs());
+        assert!(p00 < 30.0 * 30.0 && p11 < 10.0);
+
+        // coasting grows the uncertainty, until the track is lost
+        let coast = tracker.track(Time::new::<second>(62.0)).unwrap();
+        assert!(coast.coasting);
+        assert!(coast.covariance[0][0][0] > p00);
+        assert_eq!(tracker.track(Time::new::<second>(64.5)), None);
+    }
+
+    #[test]
+    pub fn stale_reports_are_ignored() {
+        let mut tracker = Tracker::new(config(TrackFilter::AlphaBeta {
+            alpha: 0.5,
+            beta: 0.1,
+        }));
+        assert_eq!(tracker.track(Time::new::<second>(0.0)), None);
+
+        let report = |t, x| Report {
+            time: Time::new::<second>(t),
+            position: [x, 0.0, 0.0].map(Length::new::<meter>),
+        };
+        let first = tracker.update(report(1.0, 100.0));
+        assert_eq!(tracker.update(report(1.0, 500.0)), first);
+        assert_eq!(tracker.update(report(0.5, 500.0)), first);
+    }
+
+    #[test]
+    pub fn track_feeds_encounter() {
+        let mut tracker = Tracker::new(config(TrackFilter::Kalman {
+            acceleration_noise: Acceleration::new::<meter_per_second_squared>(0.5),
+        }));
+        let track = track_intruder(&mut tracker);
+        let intruder = track.aircraft_state(Frame::Enu);
+
+        // the intruder is heading south-west, about 233 degrees
+        let heading = intruder.heading.get::<degree>().rem_euclid(360.0);
+        assert!((heading - 233.13).abs() < 2.0, "heading {heading}");
+
+        let ownship = AircraftState {
+            position: [0.0; 3].map(Length::new::<meter>),
+            velocity: [0.0, 150.0, 0.0].map(Velocity::new::<meter_per_second>),
+            heading: Angle::new::<degree>(0.0),
+        };
+        let encounter = Encounter::new(Frame::Enu, ownship, intruder);
+        assert!(encounter.range_rate().get::<meter_per_second>() < 0.0);
+        assert!(encounter.horizontal_tau().is_some());
+    }
+}
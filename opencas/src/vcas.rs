@@ -0,0 +1,396 @@
+use core::convert::TryFrom;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use uom::si::f32::*;
+use uom::si::length::foot;
+use uom::si::time::second;
+use uom::si::velocity::foot_per_minute;
+
+use crate::error::{ensure_finite, ensure_non_negative};
+use crate::inference::Vector;
+use crate::multi::VIntruder;
+pub use crate::nnets::VCasNNet;
+use crate::{nnets, Advisory, AdvisoryScores, CasError, CasInput};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+
+/// This will store the last given advisory in order to locate the correct network in the
+/// evaluation.
+///
+/// All evaluation happens in the stateless functions of the [`crate::vcas`] module, this struct
+/// only feeds the last advisory back into them.
+pub struct VCas {
+    pub last_advisory: VAdvisory,
+}
+
+/// VAdvisory stores all possible output evaluations.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VAdvisory {
+    #[default]
+    ClearOfConflict = 0,
+    DoNotClimb = 1,
+    DoNotDescend = 2,
+    Descend1500 = 3,
+    Climb1500 = 4,
+    StrengthenDescend1500 = 5,
+    StrengthenClimb1500 = 6,
+    StrengthenDescend2500 = 7,
+    StrengthenClimb2500 = 8,
+}
+
+impl TryFrom<u8> for VAdvisory {
+    type Error = CasError;
+
+    fn try_from(v: u8) -> Result<Self, Self::Error> {
+        Ok(match v {
+            0 => Self::ClearOfConflict,
+            1 => Self::DoNotClimb,
+            2 => Self::DoNotDescend,
+            3 => Self::Descend1500,
+            4 => Self::Climb1500,
+            5 => Self::StrengthenDescend1500,
+            6 => Self::StrengthenClimb1500,
+            7 => Self::StrengthenDescend2500,
+            8 => Self::StrengthenClimb2500,
+            _ => return Err(CasError::InvalidAdvisory(v)),
+        })
+    }
+}
+
+impl Advisory for VAdvisory {
+    type Scores = [f32; 9];
+
+    const ALL: &'static [Self] = &[
+        Self::ClearOfConflict,
+        Self::DoNotClimb,
+        Self::DoNotDescend,
+        Self::Descend1500,
+        Self::Climb1500,
+        Self::StrengthenDescend1500,
+        Self::StrengthenClimb1500,
+        Self::StrengthenDescend2500,
+        Self::StrengthenClimb2500,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Stateless version of [`VCas::process`]
+///
+/// The network is selected by the explicitly given previous advisory `pra`.
+pub fn evaluate(
+    pra: VAdvisory,
+    height: Length,
+    vertical_speed_homeship: Velocity,
+    vertical_speed_intruder: Velocity,
+    tau: Time,
+) -> AdvisoryScores<VAdvisory> {
+    AdvisoryScores::new(
+        network_scores(
+            pra,
+            height,
+            vertical_speed_homeship,
+            vertical_speed_intruder,
+            tau,
+        )
+        .into(),
+    )
+}
+
+/// Stateless version of [`VCas::try_process`]
+pub fn try_evaluate(
+    pra: VAdvisory,
+    height: Length,
+    vertical_speed_homeship: Velocity,
+    vertical_speed_intruder: Velocity,
+    tau: Time,
+) -> Result<AdvisoryScores<VAdvisory>, CasError> {
+    ensure_finite(height.get::<foot>(), CasInput::Height)?;
+    ensure_finite(
+        vertical_speed_homeship.get::<foot_per_minute>(),
+        CasInput::VerticalSpeedHomeship,
+    )?;
+    ensure_finite(
+        vertical_speed_intruder.get::<foot_per_minute>(),
+        CasInput::VerticalSpeedIntruder,
+    )?;
+    ensure_non_negative(tau.get::<second>(), CasInput::Tau)?;
+
+    let scores = evaluate(
+        pra,
+        height,
+        vertical_speed_homeship,
+        vertical_speed_intruder,
+        tau,
+    );
+    if !scores.is_finite() {
+        return Err(CasError::NonFiniteOutput);
+    }
+
+    Ok(scores)
+}
+
+/// Batched version of [`evaluate`], for many geometries at once
+///
+/// Every intruder in `intruders` is evaluated with the previous advisory `pra` and the vertical
+/// speed of the homeship, [`crate::inference::BATCH`] at a time. The scores are written to the
+/// same index of `scores`.
+///
+/// # Panics
+///
+/// Panics if `intruders` and `scores` differ in length.
+pub fn evaluate_batch(
+    pra: VAdvisory,
+    vertical_speed_homeship: Velocity,
+    intruders: &[VIntruder],
+    scores: &mut [AdvisoryScores<VAdvisory>],
+) {
+    assert_eq!(
+        intruders.len(),
+        scores.len(),
+        "one score vector per intruder expected"
+    );
+
+    let inputs = intruders.iter().enumerate().map(|(k, i)| {
+        let inputs = network_inputs(
+            i.height,
+            vertical_speed_homeship,
+            i.vertical_speed_intruder,
+            i.tau,
+        );
+        (k, inputs)
+    });
+
+    network(pra).eval_keyed(inputs, |k, output| {
+        scores[k] = AdvisoryScores::new(output.into())
+    });
+}
+
+/// The network [`evaluate`] selects for the previous advisory `pra`
+///
+/// Its inputs are relative intruder altitude [ft], vertical speed of homeship [ft/min], vertical
+/// speed of intruder [ft/min] and tau [s], its outputs are the scores of the advisories.
+pub fn network(pra: VAdvisory) -> &'static VCasNNet {
+    // find the correct network by selecting the last given advisory
+    &nnets::VCAS_NNETS[pra as usize]
+}
+
+/// Selects the network matching the previous advisory and evaluates it
+fn network_scores(
+    pra: VAdvisory,
+    height: Length,
+    vertical_speed_homeship: Velocity,
+    vertical_speed_intruder: Velocity,
+    tau: Time,
+) -> Vector<9> {
+    // evaluate the network
+    network(pra).eval(network_inputs(
+        height,
+        vertical_speed_homeship,
+        vertical_speed_intruder,
+        tau,
+    ))
+}
+
+/// Generates the network inputs as a vector [height, vs homeship, vs intruder, tau]
+fn network_inputs(
+    height: Length,
+    vertical_speed_homeship: Velocity,
+    vertical_speed_intruder: Velocity,
+    tau: Time,
+) -> Vector<4> {
+    nalgebra::vector![
+        height.get::<foot>(),
+        vertical_speed_homeship.get::<foot_per_minute>(),
+        vertical_speed_intruder.get::<foot_per_minute>(),
+        tau.get::<second>()
+    ]
+}
+
+impl VCas {
+    /// Creates a new VerticalCAS with the given last advisory
+    pub const fn new(last_advisory: VAdvisory) -> Self {
+        Self { last_advisory }
+    }
+
+    /// Evaluates many geometries at once with the network selected by `last_advisory`, see
+    /// [`evaluate_batch`]
+    ///
+    /// As the geometries are unrelated, `last_advisory` is not updated.
+    pub fn evaluate_batch(
+        &self,
+        vertical_speed_homeship: Velocity,
+        intruders: &[VIntruder],
+        scores: &mut [AdvisoryScores<VAdvisory>],
+    ) {
+        evaluate_batch(
+            self.last_advisory,
+            vertical_speed_homeship,
+            intruders,
+            scores,
+        )
+    }
+
+    /// The VerticalCAS contains 9 different networks.
+    ///
+    /// There are 4 specific inputs:
+    /// + Relative intruder altitude [ft]: vertical distance between intruder and homeship
+    /// + Vertical speed of homeship [ft/min]
+    /// + Vertical speed of intruder [ft/min]
+    /// + time until horizontal seperation loss: tau [sec]
+    ///
+    /// The scores of all advisories are returned. The advisory with the highest score is stored
+    /// as the new `last_advisory`.
+    pub fn process(
+        &mut self,
+        height: Length,
+        vertical_speed_homeship: Velocity,
+        vertical_speed_intruder: Velocity,
+        tau: Time,
+    ) -> AdvisoryScores<VAdvisory> {
+        self.update(evaluate(
+            self.last_advisory,
+            height,
+            vertical_speed_homeship,
+            vertical_speed_intruder,
+            tau,
+        ))
+    }
+
+    /// Same as [`VCas::process`], but rejects inputs the networks can not handle
+    ///
+    /// Every input must be finite, and `tau` may not be negative. The networks clamp their inputs
+    /// to the trained range, so a `tau` beyond 40 s is accepted and treated like 40 s. If an error
+    /// is returned, `last_advisory` is left untouched.
+    pub fn try_process(
+        &mut self,
+        height: Length,
+        vertical_speed_homeship: Velocity,
+        vertical_speed_intruder: Velocity,
+        tau: Time,
+    ) -> Result<AdvisoryScores<VAdvisory>, CasError> {
+        try_evaluate(
+            self.last_advisory,
+            height,
+            vertical_speed_homeship,
+            vertical_speed_intruder,
+            tau,
+        )
+        .and_then(|s| self.try_update(s))
+    }
+
+    /// Stores the advisory with the highest score and passes the scores on
+    fn update(&mut self, scores: AdvisoryScores<VAdvisory>) -> AdvisoryScores<VAdvisory> {
+        self.last_advisory = scores.advisory();
+        scores
+    }
+
+    /// Same as [`VCas::update`], without panicking
+    fn try_update(
+        &mut self,
+        scores: AdvisoryScores<VAdvisory>,
+    ) -> Result<AdvisoryScores<VAdvisory>, CasError> {
+        self.last_advisory = scores.try_advisory()?;
+        Ok(scores)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    pub fn stateless_matches_stateful() {
+        let (height, vs_own, vs_int, tau) = (
+            Length::new::<foot>(150.0),
+            Velocity::new::<foot_per_minute>(-800.0),
+            Velocity::new::<foot_per_minute>(300.0),
+            Time::new::<second>(18.0),
+        );
+
+        for &pra in VAdvisory::ALL {
+            let mut vcas = VCas::new(pra);
+            let scores = evaluate(pra, height, vs_own, vs_int, tau);
+
+            assert_eq!(vcas.process(height, vs_own, vs_int, tau), scores);
+            assert_eq!(vcas.last_advisory, scores.advisory());
+        }
+    }
+
+    #[test]
+    pub fn batch_matches_scalar() {
+        let vertical_speed_homeship = Velocity::new::<foot_per_minute>(-20.0);
+        let intruders: Vec<VIntruder> = (0..37)
+            .map(|i| VIntruder {
+                height: Length::new::<foot>(i as f32 * 100.0 - 2e3),
+                vertical_speed_intruder: Velocity::new::<foot_per_minute>(i as f32 * 5.0 - 90.0),
+                tau: Time::new::<second>(i as f32),
+            })
+            .collect();
+
+        let vcas = VCas::new(VAdvisory::DoNotClimb);
+        let mut scores = vec![AdvisoryScores::new([0.0; 9]); intruders.len()];
+        vcas.evaluate_batch(vertical_speed_homeship, &intruders, &mut scores);
+
+        for (i, scores) in intruders.iter().zip(scores) {
+            let expected = evaluate(
+                vcas.last_advisory,
+                i.height,
+                vertical_speed_homeship,
+                i.vertical_speed_intruder,
+                i.tau,
+            );
+            for (a, b) in scores.scores().iter().zip(expected.scores()) {
+                assert!((a - b).abs() <= 1e-4 * b.abs().max(1.0), "{a} != {b}");
+            }
+        }
+    }
+
+    #[test]
+    pub fn try_process_matches_process() {
+        let (height, vs_own, vs_int, tau) = (
+            Length::new::<foot>(-200.0),
+            Velocity::new::<foot_per_minute>(1000.0),
+            Velocity::new::<foot_per_minute>(-500.0),
+            Time::new::<second>(20.0),
+        );
+        let mut vcas = VCas {
+            last_advisory: VAdvisory::ClearOfConflict,
+        };
+        let mut try_vcas = vcas;
+        assert_eq!(
+            try_vcas.try_process(height, vs_own, vs_int, tau),
+            Ok(vcas.process(height, vs_own, vs_int, tau))
+        );
+        assert_eq!(try_vcas, vcas);
+    }
+
+    #[test]
+    pub fn reject_garbage_inputs() {
+        let mut vcas = VCas {
+            last_advisory: VAdvisory::Climb1500,
+        };
+        let zero = Length::new::<foot>(0.0);
+        let tau = Time::new::<second>(10.0);
+        let speed = Velocity::new::<foot_per_minute>(0.0);
+        assert_eq!(
+            vcas.try_process(
+                zero,
+                speed,
+                Velocity::new::<foot_per_minute>(f32::NEG_INFINITY),
+                tau
+            ),
+            Err(CasError::NonFiniteInput(CasInput::VerticalSpeedIntruder))
+        );
+        assert_eq!(
+            vcas.try_process(zero, speed, speed, Time::new::<second>(-0.5)),
+            Err(CasError::OutOfDomain(CasInput::Tau))
+        );
+        assert_eq!(vcas.last_advisory, VAdvisory::Climb1500);
+    }
+}
@@ -0,0 +1,476 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use std::time::{Duration, Instant};
+
+use uom::si::f32::Time;
+
+use crate::analysis::{propagate, Bounds, Interval, Method};
+use crate::inference::NNet;
+use crate::{nnets, Advisory, AdvisoryScores, HAdvisory, VAdvisory, HCAS_TAU_SCHEDULE};
+
+/// A property of the advisories of a network, which is to hold for every input of a region
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Property<A: Advisory> {
+    /// The advisory is never the one with the best score
+    NeverChosen(A),
+
+    /// The advisory always has the best score
+    AlwaysChosen(A),
+
+    /// The score of the first advisory is always strictly below the score of the second one
+    ScoreBelow(A, A),
+}
+
+impl<A: Advisory> Property<A> {
+    /// Whether the property holds for every input the bounds were propagated from
+    ///
+    /// This is sound but incomplete, a property may hold although the bounds are too loose to
+    /// show it.
+    fn proven<const I: usize, const O: usize>(&self, bounds: &Bounds<I, O>) -> bool {
+        match *self {
+            Self::NeverChosen(advisory) => A::ALL
+                .iter()
+                .any(|&other| other != advisory && beats(bounds, other, advisory)),
+            Self::AlwaysChosen(advisory) => A::ALL
+                .iter()
+                .all(|&other| other == advisory || beats(bounds, advisory, other)),
+            Self::ScoreBelow(a, b) => bounds.difference(a.index(), b.index()).upper < 0.0,
+        }
+    }
+
+    /// Whether the scores of a single network evaluation violate the property
+    fn violated_by(&self, scores: &AdvisoryScores<A>) -> bool {
+        match *self {
+            Self::NeverChosen(advisory) => scores.advisory() == advisory,
+            Self::AlwaysChosen(advisory) => scores.advisory() != advisory,
+            // NaN scores can not be shown to be below anything
+            Self::ScoreBelow(a, b) => {
+                scores.score(a).partial_cmp(&scores.score(b)) != Some(Ordering::Less)
+            }
+        }
+    }
+}
+
+/// Whether `a` ranks strictly before `b` for every input the bounds were propagated from
+fn beats<A: Advisory, const I: usize, const O: usize>(bounds: &Bounds<I, O>, a: A, b: A) -> bool {
+    let difference = bounds.difference(a.index(), b.index());
+    if A::MINIMIZE {
+        difference.upper < 0.0
+    } else {
+        difference.lower > 0.0
+    }
+}
+
+impl<A: Advisory> fmt::Display for Property<A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NeverChosen(advisory) => write!(f, "{advisory:?} is never chosen"),
+            Self::AlwaysChosen(advisory) => write!(f, "{advisory:?} is always chosen"),
+            Self::ScoreBelow(a, b) => write!(f, "score of {a:?} < score of {b:?}"),
+        }
+    }
+}
+
+/// The outcome of a verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Verdict {
+    /// The property holds for every input of the region
+    Proven,
+
+    /// At least one counterexample was found
+    Violated,
+
+    /// The search stopped before every part of the region was either proven or violated
+    Unknown,
+}
+
+/// An input for which the network violates the property
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Counterexample<const N_INPUT: usize, const N_OUTPUT: usize> {
+    /// The inputs, in the same units as for [`NNet::eval`]
+    pub inputs: [f32; N_INPUT],
+
+    /// The outputs of the network for `inputs`
+    pub scores: [f32; N_OUTPUT],
+}
+
+/// The result of [`Verifier::check`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report<const N_INPUT: usize, const N_OUTPUT: usize> {
+    pub verdict: Verdict,
+
+    /// Volume of the parts of the region proven to satisfy the property, relative to the
+    /// volume of the whole region
+    pub proven_fraction: f64,
+
+    pub counterexamples: Vec<Counterexample<N_INPUT, N_OUTPUT>>,
+
+    /// Boxes which neither could be proven nor contained a counterexample when the search
+    /// stopped
+    pub unresolved: Vec<[Interval; N_INPUT]>,
+
+    /// Number of boxes the bounds were propagated for
+    pub boxes: usize,
+
+    pub elapsed: Duration,
+}
+
+impl<const N_INPUT: usize, const N_OUTPUT: usize> fmt::Display for Report<N_INPUT, N_OUTPUT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?}: {:.2}% proven, {} counterexamples, {} unresolved boxes, {} boxes in {:?}",
+            self.verdict,
+            self.proven_fraction * 100.0,
+            self.counterexamples.len(),
+            self.unresolved.len(),
+            self.boxes,
+            self.elapsed,
+        )
+    }
+}
+
+/// The report of one of the networks checked by [`Verifier::check_hcas`] or
+/// [`Verifier::check_vcas`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkReport<A: Advisory, const N_INPUT: usize, const N_OUTPUT: usize> {
+    /// The previous advisory the network is selected by
+    pub pra: A,
+
+    /// The trained tau the network is selected by, for networks split by tau
+    pub tau: Option<Time>,
+
+    pub report: Report<N_INPUT, N_OUTPUT>,
+}
+
+/// Checks properties of networks by branch and bound
+///
+/// The bounds of the network outputs are propagated for the whole input region. If they do not
+/// suffice to prove the property, the network is evaluated at the center of the region to look
+/// for a counterexample. If there is none either, the region is split in half along its widest
+/// dimension, and both halves are checked the same way.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Verifier {
+    pub method: Method,
+
+    /// How often a box may be split, before it is given up as unresolved
+    pub max_depth: u32,
+
+    /// Maximum number of boxes to propagate the bounds for
+    pub max_boxes: usize,
+
+    /// Maximum time to spend on one network
+    pub time_limit: Option<Duration>,
+
+    /// The search stops once this many counterexamples are found
+    pub max_counterexamples: usize,
+}
+
+impl Default for Verifier {
+    fn default() -> Self {
+        Self {
+            method: Method::Symbolic,
+            max_depth: 30,
+            max_boxes: 100_000,
+            time_limit: None,
+            max_counterexamples: 1,
+        }
+    }
+}
+
+impl Verifier {
+    /// Checks `property` for every input of `region`
+    ///
+    /// `region` is given in the same units as the inputs of [`NNet::eval`].
+    pub fn check<
+        A: Advisory,
+        const N_INPUT: usize,
+        const N_MAT: usize,
+        const N_NEURON: usize,
+        const N_OUTPUT: usize,
+    >(
+        &self,
+        nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+        region: &[Interval; N_INPUT],
+        property: Property<A>,
+    ) -> Report<N_INPUT, N_OUTPUT>
+    where
+        A::Scores: From<[f32; N_OUTPUT]>,
+    {
+        self.search(
+            nnet,
+            region,
+            |bounds| property.proven(bounds),
+            |scores| property.violated_by(&AdvisoryScores::<A>::new((*scores).into())),
+        )
+    }
+
+    /// The branch and bound search behind [`Verifier::check`]
+    ///
+    /// `proven` tells whether the bounds of a box suffice to prove the property, `violated`
+    /// whether the outputs of a single evaluation violate it.
+    pub(crate) fn search<
+        const N_INPUT: usize,
+        const N_MAT: usize,
+        const N_NEURON: usize,
+        const N_OUTPUT: usize,
+    >(
+        &self,
+        nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+        region: &[Interval; N_INPUT],
+        proven: impl Fn(&Bounds<N_INPUT, N_OUTPUT>) -> bool,
+        violated: impl Fn(&[f32; N_OUTPUT]) -> bool,
+    ) -> Report<N_INPUT, N_OUTPUT> {
+        let start = Instant::now();
+        let mut report = Report {
+            verdict: Verdict::Unknown,
+            proven_fraction: 0.0,
+            counterexamples: Vec::new(),
+            unresolved: Vec::new(),
+            boxes: 0,
+            elapsed: Duration::ZERO,
+        };
+
+        // depth first, so that the stack stays small
+        let mut stack = Vec::from([(*region, 0)]);
+        while let Some((bounds_box, depth)) = stack.pop() {
+            let timed_out = self
+                .time_limit
+                .is_some_and(|limit| start.elapsed() >= limit);
+            if timed_out
+                || report.boxes >= self.max_boxes
+                || report.counterexamples.len() >= self.max_counterexamples
+            {
+                report.unresolved.push(bounds_box);
+                report.unresolved.extend(stack.drain(..).map(|(b, _)| b));
+                break;
+            }
+            report.boxes += 1;
+
+            let bounds = propagate(nnet, &bounds_box, self.method);
+            if proven(&bounds) {
+                report.proven_fraction += relative_volume(&bounds_box, region);
+                continue;
+            }
+
+            let center = bounds_box.map(|i| i.lower + i.width() / 2.0);
+            let scores: [f32; N_OUTPUT] = nnet.eval(center.into()).into();
+            if violated(&scores) {
+                report.counterexamples.push(Counterexample {
+                    inputs: center,
+                    scores,
+                });
+                continue;
+            }
+
+            match split(&bounds_box, region) {
+                Some((left, right)) if depth < self.max_depth => {
+                    stack.push((right, depth + 1));
+                    stack.push((left, depth + 1));
+                }
+                _ => report.unresolved.push(bounds_box),
+            }
+        }
+
+        report.verdict = if !report.counterexamples.is_empty() {
+            Verdict::Violated
+        } else if report.unresolved.is_empty() {
+            Verdict::Proven
+        } else {
+            Verdict::Unknown
+        };
+        report.elapsed = start.elapsed();
+        report
+    }
+
+    /// Checks `property` for every network of the HorizontalCAS
+    ///
+    /// `region` contains forward range [ft], left range [ft] and psi [rad], like the inputs of
+    /// [`crate::hcas::network`].
+    pub fn check_hcas(
+        &self,
+        region: &[Interval; 3],
+        property: Property<HAdvisory>,
+    ) -> Vec<NetworkReport<HAdvisory, 3, 5>> {
+        let mut reports = Vec::new();
+        for (pra, nnets) in HAdvisory::ALL.iter().zip(nnets::HCAS_NNETS.iter()) {
+            for (index, nnet) in nnets.iter().enumerate() {
+                reports.push(NetworkReport {
+                    pra: *pra,
+                    tau: Some(HCAS_TAU_SCHEDULE.trained_tau(index)),
+                    report: self.check(nnet, region, property),
+                });
+            }
+        }
+        reports
+    }
+
+    /// Checks `property` for every network of the VerticalCAS
+    ///
+    /// `region` contains relative intruder altitude [ft], vertical speed of homeship [ft/min],
+    /// vertical speed of intruder [ft/min] and tau [s], like the inputs of
+    /// [`crate::vcas::network`].
+    pub fn check_vcas(
+        &self,
+        region: &[Interval; 4],
+        property: Property<VAdvisory>,
+    ) -> Vec<NetworkReport<VAdvisory, 4, 9>> {
+        VAdvisory::ALL
+            .iter()
+            .zip(nnets::VCAS_NNETS.iter())
+            .map(|(pra, nnet)| NetworkReport {
+                pra: *pra,
+                tau: None,
+                report: self.check(nnet, region, property),
+            })
+            .collect()
+    }
+}
+
+/// Volume of `bounds_box` relative to the volume of `region`
+///
+/// Dimensions in which the region is a single point do not count.
+fn relative_volume<const N: usize>(bounds_box: &[Interval; N], region: &[Interval; N]) -> f64 {
+    bounds_box
+        .iter()
+        .zip(region)
+        .filter(|(_, r)| r.width() > 0.0)
+        .map(|(b, r)| b.width() as f64 / r.width() as f64)
+        .product()
+}
+
+/// Splits `bounds_box` in half along the dimension which is widest relative to `region`
+///
+/// Returns `None` if the box can not be split any further.
+fn split<const N: usize>(
+    bounds_box: &[Interval; N],
+    region: &[Interval; N],
+) -> Option<([Interval; N], [Interval; N])> {
+    let (dimension, _) = bounds_box
+        .iter()
+        .zip(region)
+        .map(|(b, r)| b.width() / r.width())
+        .enumerate()
+        .filter(|(_, relative)| *relative > 0.0)
+        // on ties, the first dimension is split
+        .reduce(|widest, next| if next.1 > widest.1 { next } else { widest })?;
+
+    let interval = bounds_box[dimension];
+    let middle = interval.lower + interval.width() / 2.0;
+    if middle <= interval.lower || middle >= interval.upper {
+        return None;
+    }
+
+    let mut left = *bounds_box;
+    let mut right = *bounds_box;
+    left[dimension].upper = middle;
+    right[dimension].lower = middle;
+    Some((left, right))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{hcas, vcas};
+    use core::f32::consts::PI;
+    use uom::si::time::second;
+
+    #[test]
+    pub fn split_widest_relative_dimension() {
+        let region = [Interval::new(0.0, 1000.0), Interval::new(0.0, 1.0)];
+        let (left, right) = split(&region, &region).unwrap();
+        assert_eq!(left[0], Interval::new(0.0, 500.0));
+        assert_eq!(right[0], Interval::new(500.0, 1000.0));
+        assert_eq!(left[1], region[1]);
+
+        // now the second dimension is the widest relative to the region
+        let (left, _) = split(&left, &region).unwrap();
+        assert_eq!(left[1], Interval::new(0.0, 0.5));
+        assert_eq!(relative_volume(&left, &region), 0.25);
+
+        let point = [Interval::point(1.0), Interval::point(2.0)];
+        assert_eq!(split(&point, &point), None);
+    }
+
+    #[test]
+    pub fn proves_far_apart_encounters() {
+        // vertically far apart with small vertical speeds, the strongest climb is never needed
+        let region = [
+            Interval::new(3000.0, 4000.0),
+            Interval::new(-10.0, 10.0),
+            Interval::new(-10.0, 10.0),
+            Interval::new(20.0, 40.0),
+        ];
+        let property = Property::NeverChosen(VAdvisory::StrengthenClimb2500);
+        let verifier = Verifier::default();
+
+        let report = verifier.check(vcas::network(VAdvisory::ClearOfConflict), &region, property);
+        assert_eq!(report.verdict, Verdict::Proven, "{report}");
+        assert!((report.proven_fraction - 1.0).abs() < 1e-9);
+        assert!(report.counterexamples.is_empty() && report.unresolved.is_empty());
+
+        // the other networks are allowed just as many boxes, to keep the test fast
+        let verifier = Verifier {
+            max_boxes: report.boxes,
+            ..verifier
+        };
+        let reports = verifier.check_vcas(&region, property);
+        assert_eq!(reports.len(), VAdvisory::ALL.len());
+        assert_eq!(reports[0].report, {
+            let mut expected = report.clone();
+            expected.elapsed = reports[0].report.elapsed;
+            expected
+        });
+    }
+
+    #[test]
+    pub fn finds_counterexample() {
+        let nnet = hcas::network(HAdvisory::ClearOfConflict, Time::new::<second>(20.0));
+        let head_on = [
+            Interval::new(4e3, 6e3),
+            Interval::new(-500.0, 500.0),
+            Interval::new(-PI, -3.0),
+        ];
+        let property = Property::ScoreBelow(HAdvisory::StrongLeft, HAdvisory::ClearOfConflict);
+
+        let report = Verifier::default().check(nnet, &head_on, property);
+        assert_eq!(report.verdict, Verdict::Violated, "{report}");
+        let counterexample = report.counterexamples[0];
+        for (input, interval) in counterexample.inputs.iter().zip(&head_on) {
+            assert!(interval.contains(*input));
+        }
+        let scores = nnet.eval(counterexample.inputs.into());
+        assert_eq!(scores.as_slice(), &counterexample.scores);
+        assert!(scores[HAdvisory::StrongLeft.index()] >= scores[0]);
+
+        // head on, the homeship has to turn away
+        let property = Property::NeverChosen(HAdvisory::ClearOfConflict);
+        let report = Verifier::default().check(nnet, &head_on, property);
+        assert_eq!(report.verdict, Verdict::Proven, "{report}");
+    }
+
+    #[test]
+    pub fn stops_at_budget() {
+        let nnet = hcas::network(HAdvisory::ClearOfConflict, Time::new::<second>(20.0));
+        let region = [
+            Interval::new(40e3, 50e3),
+            Interval::new(-5e3, 5e3),
+            Interval::new(-PI, PI),
+        ];
+        let verifier = Verifier {
+            max_boxes: 100,
+            ..Default::default()
+        };
+
+        let report = verifier.check(nnet, &region, Property::NeverChosen(HAdvisory::StrongLeft));
+        assert_eq!(report.verdict, Verdict::Unknown, "{report}");
+        assert_eq!(report.boxes, 100);
+        let unresolved: f64 = report
+            .unresolved
+            .iter()
+            .map(|b| relative_volume(b, &region))
+            .sum();
+        assert!((report.proven_fraction + unresolved - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,819 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::string::{String, ToString};
+use std::vec::Vec;
+use std::{format, vec};
+
+use crate::analysis::{next_down, next_up, Bounds, Interval};
+use crate::inference::NNet;
+use crate::verify::{Property, Report, Verifier};
+use crate::Advisory;
+
+/// Errors that may occur while parsing a VNN-LIB property or checking it
+#[derive(Debug, Clone, PartialEq)]
+pub enum VnnLibError {
+    /// The parentheses are not balanced
+    UnbalancedParentheses,
+
+    /// The given expression is not part of the supported subset of VNN-LIB
+    Unsupported(String),
+
+    /// The given variable is not declared, or its name is neither `X_i` nor `Y_j`
+    UnknownVariable(String),
+
+    /// The given literal is not a finite number
+    InvalidNumber(String),
+
+    /// The given input is not bounded from both sides
+    UnboundedInput(usize),
+
+    /// The bounds of the given input contradict each other
+    EmptyInput(usize),
+
+    /// The number of inputs or outputs does not match the network
+    DimensionMismatch { inputs: usize, outputs: usize },
+}
+
+impl fmt::Display for VnnLibError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnbalancedParentheses => write!(f, "unbalanced parentheses"),
+            Self::Unsupported(expr) => write!(f, "unsupported expression {expr}"),
+            Self::UnknownVariable(name) => write!(f, "unknown variable {name}"),
+            Self::InvalidNumber(literal) => write!(f, "invalid number {literal}"),
+            Self::UnboundedInput(i) => write!(f, "input X_{i} is not bounded"),
+            Self::EmptyInput(i) => write!(f, "bounds of input X_{i} contradict each other"),
+            Self::DimensionMismatch { inputs, outputs } => write!(
+                f,
+                "property with {inputs} inputs and {outputs} outputs does not fit the network"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VnnLibError {}
+
+/// The linear constraint `sum(coefficients[j] * Y_j) <= bound` on the outputs
+#[derive(Debug, Clone, PartialEq)]
+pub struct Constraint {
+    /// One coefficient per output
+    pub coefficients: Vec<f32>,
+    pub bound: f32,
+}
+
+impl Constraint {
+    /// The constraint `Y_a <= Y_b`
+    pub fn at_most(n_output: usize, a: usize, b: usize) -> Self {
+        let mut coefficients = vec![0.0; n_output];
+        coefficients[a] += 1.0;
+        coefficients[b] -= 1.0;
+        Self {
+            coefficients,
+            bound: 0.0,
+        }
+    }
+
+    /// Whether `outputs` satisfy the constraint
+    pub fn satisfied_by(&self, outputs: &[f32]) -> bool {
+        let sum: f32 = self
+            .coefficients
+            .iter()
+            .zip(outputs)
+            .map(|(c, y)| c * y)
+            .sum();
+        sum <= self.bound
+    }
+}
+
+/// A property in VNN-LIB, see the [module documentation](self)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Spec {
+    /// Bounds of every input `X_i`
+    pub inputs: Vec<Interval>,
+
+    /// Number of outputs `Y_j`
+    pub n_output: usize,
+
+    /// The property is violated by outputs which satisfy every constraint of any of the clauses
+    pub violation: Vec<Vec<Constraint>>,
+}
+
+impl Spec {
+    /// Converts `property` over `region` for the given network
+    ///
+    /// The region is clamped to the input range of `nnet`, which does not change the property
+    /// as the network clamps its inputs the same way. Ties in the scores are counted as
+    /// violations, as VNN-LIB does not know the order in which `NNet::eval` resolves them.
+    pub fn from_property<
+        A: Advisory,
+        const N_INPUT: usize,
+        const N_MAT: usize,
+        const N_NEURON: usize,
+        const N_OUTPUT: usize,
+    >(
+        nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+        region: &[Interval; N_INPUT],
+        property: Property<A>,
+    ) -> Self {
+        let n = N_OUTPUT;
+        // whether `a` may rank before or equal to `b`
+        let ranks_before = |a: A, b: A| {
+            if A::MINIMIZE {
+                Constraint::at_most(n, a.index(), b.index())
+            } else {
+                Constraint::at_most(n, b.index(), a.index())
+            }
+        };
+        let others = |advisory: A| A::ALL.iter().copied().filter(move |&a| a != advisory);
+
+        let violation = match property {
+            Property::NeverChosen(advisory) => {
+                vec![others(advisory)
+                    .map(|o| ranks_before(advisory, o))
+                    .collect()]
+            }
+            Property::AlwaysChosen(advisory) => others(advisory)
+                .map(|o| vec![ranks_before(o, advisory)])
+                .collect(),
+            Property::ScoreBelow(a, b) => vec![vec![Constraint::at_most(n, b.index(), a.index())]],
+        };
+
+        Self {
+            inputs: region
+                .iter()
+                .enumerate()
+                .map(|(i, r)| r.clamp(nnet.min_input[i], nnet.max_input[i]))
+                .collect(),
+            n_output: N_OUTPUT,
+            violation,
+        }
+    }
+
+    /// Parse a property from VNN-LIB text
+    pub fn parse(source: &str) -> Result<Self, VnnLibError> {
+        let mut parser = Parser {
+            n_input: 0,
+            n_output: 0,
+            bounds: BTreeMap::new(),
+            // a single empty clause, which is always satisfied
+            violation: vec![Vec::new()],
+        };
+
+        for command in expressions(source)? {
+            let Expr::List(list) = &command else {
+                return Err(VnnLibError::Unsupported(command.to_string()));
+            };
+            match list.as_slice() {
+                [Expr::Atom("declare-const"), Expr::Atom(name), Expr::Atom("Real")] => {
+                    parser.declare(name)?
+                }
+                [Expr::Atom("assert"), formula] => {
+                    let clauses = parser.formula(formula)?;
+                    parser.conjoin(clauses);
+                }
+                _ => return Err(VnnLibError::Unsupported(command.to_string())),
+            }
+        }
+
+        let inputs = (0..parser.n_input)
+            .map(|i| {
+                let (lower, upper) = parser
+                    .bounds
+                    .get(&i)
+                    .copied()
+                    .unwrap_or((f32::NEG_INFINITY, f32::INFINITY));
+                if !lower.is_finite() || !upper.is_finite() {
+                    Err(VnnLibError::UnboundedInput(i))
+                } else if lower > upper {
+                    Err(VnnLibError::EmptyInput(i))
+                } else {
+                    Ok(Interval::new(lower, upper))
+                }
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            inputs,
+            n_output: parser.n_output,
+            violation: parser.violation,
+        })
+    }
+
+    /// Whether `outputs` violate the property
+    pub fn violated_by(&self, outputs: &[f32]) -> bool {
+        self.violation
+            .iter()
+            .any(|clause| clause.iter().all(|c| c.satisfied_by(outputs)))
+    }
+}
+
+/// Writes the property as VNN-LIB text
+impl fmt::Display for Spec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in 0..self.inputs.len() {
+            writeln!(f, "(declare-const X_{i} Real)")?;
+        }
+        for j in 0..self.n_output {
+            writeln!(f, "(declare-const Y_{j} Real)")?;
+        }
+
+        writeln!(f, "\n; input bounds")?;
+        for (i, input) in self.inputs.iter().enumerate() {
+            writeln!(f, "(assert (>= X_{i} {}))", number(input.lower))?;
+            writeln!(f, "(assert (<= X_{i} {}))", number(input.upper))?;
+        }
+
+        writeln!(f, "\n; violation")?;
+        if let [clause] = self.violation.as_slice() {
+            for constraint in clause {
+                writeln!(f, "(assert {})", ConstraintExpr(constraint))?;
+            }
+        } else {
+            writeln!(f, "(assert (or")?;
+            for clause in &self.violation {
+                write!(f, "    (and")?;
+                for constraint in clause {
+                    write!(f, " {}", ConstraintExpr(constraint))?;
+                }
+                writeln!(f, ")")?;
+            }
+            writeln!(f, "))")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a [`Constraint`] as a VNN-LIB comparison, with positive coefficients on both sides
+struct ConstraintExpr<'a>(&'a Constraint);
+
+impl fmt::Display for ConstraintExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Constraint {
+            coefficients,
+            bound,
+        } = self.0;
+        let side = |sign: f32, constant: f32| {
+            let mut terms: Vec<String> = coefficients
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| **c * sign > 0.0)
+                .map(|(j, c)| {
+                    if c * sign == 1.0 {
+                        format!("Y_{j}")
+                    } else {
+                        format!("(* {} Y_{j})", number(c * sign))
+                    }
+                })
+                .collect();
+            if constant != 0.0 || terms.is_empty() {
+                terms.push(number(constant));
+            }
+            match terms.as_slice() {
+                [term] => term.clone(),
+                _ => format!("(+ {})", terms.join(" ")),
+            }
+        };
+        write!(f, "(<= {} {})", side(1.0, 0.0), side(-1.0, *bound))
+    }
+}
+
+/// Formats `value` as a decimal literal
+///
+/// The literal is the shortest one for the value widened to `f64`, so that parsing it gives back
+/// exactly `value` instead of rounding it outward.
+fn number(value: f32) -> String {
+    let literal = f64::from(value).to_string();
+    if literal.contains('.') {
+        literal
+    } else {
+        literal + ".0"
+    }
+}
+
+impl Verifier {
+    /// Checks `spec` on `nnet`, see [`Verifier::check`]
+    ///
+    /// The inputs of `spec` are given in the same units as for [`NNet::eval`], like for the
+    /// properties written by [`Spec::from_property`].
+    pub fn check_spec<
+        const N_INPUT: usize,
+        const N_MAT: usize,
+        const N_NEURON: usize,
+        const N_OUTPUT: usize,
+    >(
+        &self,
+        nnet: &NNet<N_INPUT, N_MAT, N_NEURON, N_OUTPUT>,
+        spec: &Spec,
+    ) -> Result<Report<N_INPUT, N_OUTPUT>, VnnLibError> {
+        let mismatch = || VnnLibError::DimensionMismatch {
+            inputs: spec.inputs.len(),
+            outputs: spec.n_output,
+        };
+        let region: [Interval; N_INPUT] =
+            spec.inputs.as_slice().try_into().map_err(|_| mismatch())?;
+        if spec.n_output != N_OUTPUT {
+            return Err(mismatch());
+        }
+
+        let mut violation = Vec::new();
+        for clause in &spec.violation {
+            let mut constraints = Vec::new();
+            for constraint in clause {
+                let coefficients: [f32; N_OUTPUT] = constraint
+                    .coefficients
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| mismatch())?;
+                constraints.push((coefficients, constraint.bound));
+            }
+            violation.push(constraints);
+        }
+
+        // proven if every clause contains a constraint which can not be satisfied
+        let proven = |bounds: &Bounds<N_INPUT, N_OUTPUT>| {
+            violation.iter().all(|clause| {
+                clause
+                    .iter()
+                    .any(|(coefficients, bound)| bounds.combination(coefficients).lower > *bound)
+            })
+        };
+        Ok(self.search(nnet, &region, proven, |scores| spec.violated_by(scores)))
+    }
+}
+
+/// An S-expression
+#[derive(Debug, Clone, PartialEq)]
+enum Expr<'a> {
+    Atom(&'a str),
+    List(Vec<Expr<'a>>),
+}
+
+impl fmt::Display for Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Atom(atom) => f.write_str(atom),
+            Self::List(list) => {
+                write!(f, "(")?;
+                for (i, expr) in list.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{expr}")?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
+
+/// Splits `source` into its top-level S-expressions, skipping comments
+fn expressions(source: &str) -> Result<Vec<Expr<'_>>, VnnLibError> {
+    let mut stack: Vec<Vec<Expr>> = [Vec::new()].into();
+    for line in source.lines() {
+        let code = line.split(';').next().unwrap_or_default();
+        for word in code.split_whitespace() {
+            let mut rest = word;
+            while !rest.is_empty() {
+                let end = rest.find(['(', ')']).unwrap_or(rest.len());
+                let (atom, tail) = rest.split_at(end);
+                if !atom.is_empty() {
+                    stack.last_mut().unwrap().push(Expr::Atom(atom));
+                }
+                rest = match tail.chars().next() {
+                    Some('(') => {
+                        stack.push(Vec::new());
+                        &tail[1..]
+                    }
+                    Some(')') => {
+                        let list = stack.pop().unwrap();
+                        stack
+                            .last_mut()
+                            .ok_or(VnnLibError::UnbalancedParentheses)?
+                            .push(Expr::List(list));
+                        &tail[1..]
+                    }
+                    _ => tail,
+                };
+            }
+        }
+    }
+
+    match stack.pop() {
+        Some(top) if stack.is_empty() => Ok(top),
+        _ => Err(VnnLibError::UnbalancedParentheses),
+    }
+}
+
+/// A linear function of the inputs and outputs
+///
+/// The coefficients are kept in `f64`, so that literals and constants folded from them are only
+/// narrowed to `f32` once the bounds are known, see [`narrow_up`] and [`narrow_down`].
+#[derive(Debug, Clone, Default)]
+struct Linear {
+    inputs: BTreeMap<usize, f64>,
+    outputs: BTreeMap<usize, f64>,
+    constant: f64,
+}
+
+impl Linear {
+    fn scale(mut self, factor: f64) -> Self {
+        self.inputs.values_mut().for_each(|c| *c *= factor);
+        self.outputs.values_mut().for_each(|c| *c *= factor);
+        self.constant *= factor;
+        self
+    }
+
+    fn add(mut self, other: Self) -> Self {
+        for (i, c) in other.inputs {
+            *self.inputs.entry(i).or_default() += c;
+        }
+        for (j, c) in other.outputs {
+            *self.outputs.entry(j).or_default() += c;
+        }
+        self.constant += other.constant;
+        self
+    }
+
+    /// The value of the function, if it is constant
+    fn as_constant(&self) -> Option<f64> {
+        let zero = |c: &f64| *c == 0.0;
+        (self.inputs.values().all(zero) && self.outputs.values().all(zero)).then_some(self.constant)
+    }
+}
+
+/// The state of [`Spec::parse`]
+struct Parser {
+    n_input: usize,
+    n_output: usize,
+    /// Lower and upper bound of the inputs, by their index
+    bounds: BTreeMap<usize, (f32, f32)>,
+    /// The constraints on the outputs, as a disjunction of conjunctions
+    violation: Vec<Vec<Constraint>>,
+}
+
+impl Parser {
+    fn declare(&mut self, name: &str) -> Result<(), VnnLibError> {
+        let unknown = || VnnLibError::UnknownVariable(name.into());
+        let (prefix, index) = name.split_once('_').ok_or_else(unknown)?;
+        let index: usize = index.parse().map_err(|_| unknown())?;
+        // the variables have to be declared in order
+        match prefix {
+            "X" if index == self.n_input => self.n_input += 1,
+            "Y" if index == self.n_output => self.n_output += 1,
+            _ => return Err(unknown()),
+        }
+        Ok(())
+    }
+
+    /// Parses a linear term
+    fn term(&self, expr: &Expr) -> Result<Linear, VnnLibError> {
+        let unsupported = || VnnLibError::Unsupported(expr.to_string());
+        match expr {
+            Expr::Atom(atom) => {
+                if let Some(index) = atom.strip_prefix("X_") {
+                    let i = index.parse().ok().filter(|i| *i < self.n_input);
+                    let i = i.ok_or_else(|| VnnLibError::UnknownVariable(atom.to_string()))?;
+                    Ok(Linear {
+                        inputs: [(i, 1.0)].into(),
+                        ..Default::default()
+                    })
+                } else if let Some(index) = atom.strip_prefix("Y_") {
+                    let j = index.parse().ok().filter(|j| *j < self.n_output);
+                    let j = j.ok_or_else(|| VnnLibError::UnknownVariable(atom.to_string()))?;
+                    Ok(Linear {
+                        outputs: [(j, 1.0)].into(),
+                        ..Default::default()
+                    })
+                } else {
+                    let constant: f64 = atom
+                        .parse()
+                        .ok()
+                        .filter(|c: &f64| c.is_finite())
+                        .ok_or_else(|| VnnLibError::InvalidNumber(atom.to_string()))?;
+                    Ok(Linear {
+                        constant,
+                        ..Default::default()
+                    })
+                }
+            }
+            Expr::List(list) => {
+                let (Expr::Atom(op), args) = list.split_first().ok_or_else(unsupported)? else {
+                    return Err(unsupported());
+                };
+                let args = args
+                    .iter()
+                    .map(|arg| self.term(arg))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match (*op, args.as_slice()) {
+                    ("+", [_, ..]) => Ok(args.into_iter().reduce(Linear::add).unwrap()),
+                    ("-", [single]) => Ok(single.clone().scale(-1.0)),
+                    ("-", [first, rest @ ..]) => Ok(rest
+                        .iter()
+                        .fold(first.clone(), |sum, t| sum.add(t.clone().scale(-1.0)))),
+                    ("*", [a, b]) => match (a.as_constant(), b.as_constant()) {
+                        (Some(factor), _) => Ok(b.clone().scale(factor)),
+                        (_, Some(factor)) => Ok(a.clone().scale(factor)),
+                        _ => Err(unsupported()),
+                    },
+                    _ => Err(unsupported()),
+                }
+            }
+        }
+    }
+
+    /// Parses a formula into a disjunction of conjunctions of output constraints
+    ///
+    /// Bounds on single inputs are recorded directly, as they may not appear in a disjunction.
+    fn formula(&mut self, expr: &Expr) -> Result<Vec<Vec<Constraint>>, VnnLibError> {
+        let unsupported = || VnnLibError::Unsupported(expr.to_string());
+        let Expr::List(list) = expr else {
+            return Err(unsupported());
+        };
+        let (Expr::Atom(op), args) = list.split_first().ok_or_else(unsupported)? else {
+            return Err(unsupported());
+        };
+
+        match *op {
+            "and" => {
+                let mut clauses = vec![Vec::new()];
+                for arg in args {
+                    let other = self.formula(arg)?;
+                    clauses = product(&clauses, &other);
+                }
+                Ok(clauses)
+            }
+            "or" => {
+                // input bounds within a disjunction would be recorded unconditionally
+                let bounds = self.bounds.clone();
+                let mut clauses = Vec::new();
+                for arg in args {
+                    clauses.extend(self.formula(arg)?);
+                }
+                if self.bounds != bounds {
+                    return Err(unsupported());
+                }
+                Ok(clauses)
+            }
+            "<=" | "<" | ">=" | ">" => {
+                let [lhs, rhs] = args else {
+                    return Err(unsupported());
+                };
+                let (lhs, rhs) = (self.term(lhs)?, self.term(rhs)?);
+                // the function which is at most zero
+                let linear = if op.starts_with('<') {
+                    lhs.add(rhs.scale(-1.0))
+                } else {
+                    rhs.add(lhs.scale(-1.0))
+                };
+
+                let inputs: Vec<_> = linear.inputs.iter().filter(|(_, c)| **c != 0.0).collect();
+                let outputs = linear.outputs.values().any(|c| *c != 0.0);
+                match (inputs.as_slice(), outputs) {
+                    // c * X_i + constant <= 0
+                    ([(&i, &c)], false) => {
+                        // the quotient is off by at most half an f64 step, so rounding it
+                        // outward to an f32 beyond it is safe. A quotient which already is an
+                        // f32 is only kept if it is exact, which the product with a coefficient
+                        // that is an f32 as well shows, as that product is exact in f64.
+                        let value = -linear.constant / c;
+                        let representable = f64::from(value as f32) == value;
+                        let exact = !representable
+                            || (f64::from(c as f32) == c && value * c == -linear.constant);
+                        let (lower, upper) = self
+                            .bounds
+                            .entry(i)
+                            .or_insert((f32::NEG_INFINITY, f32::INFINITY));
+                        if c > 0.0 {
+                            let bound = narrow_up(value);
+                            *upper = upper.min(if exact { bound } else { next_up(bound) });
+                        } else {
+                            let bound = narrow_down(value);
+                            *lower = lower.max(if exact { bound } else { next_down(bound) });
+                        }
+                        Ok(vec![Vec::new()])
+                    }
+                    ([], true) => {
+                        let mut coefficients = vec![0.0; self.n_output];
+                        for (j, c) in linear.outputs {
+                            coefficients[j] = c as f32;
+                        }
+                        // a larger bound only allows more violations, which keeps proofs sound
+                        Ok(vec![vec![Constraint {
+                            coefficients,
+                            bound: narrow_up(-linear.constant),
+                        }]])
+                    }
+                    _ => Err(unsupported()),
+                }
+            }
+            _ => Err(unsupported()),
+        }
+    }
+
+    /// Adds the disjunction `clauses` to the conjunction of all assertions
+    fn conjoin(&mut self, clauses: Vec<Vec<Constraint>>) {
+        self.violation = product(&self.violation, &clauses);
+    }
+}
+
+/// The smallest `f32` not below `x`
+fn narrow_up(x: f64) -> f32 {
+    let narrowed = x as f32;
+    if f64::from(narrowed) < x {
+        next_up(narrowed)
+    } else {
+        narrowed
+    }
+}
+
+/// The largest `f32` not above `x`
+fn narrow_down(x: f64) -> f32 {
+    -narrow_up(-x)
+}
+
+/// The conjunction of two disjunctions of conjunctions, again as a disjunction of conjunctions
+fn product(a: &[Vec<Constraint>], b: &[Vec<Constraint>]) -> Vec<Vec<Constraint>> {
+    a.iter()
+        .flat_map(|x| b.iter().map(move |y| x.iter().chain(y).cloned().collect()))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::verify::Verdict;
+    use crate::{hcas, HAdvisory};
+    use core::f32::consts::PI;
+    use uom::si::f32::Time;
+    use uom::si::time::second;
+
+    /// Written like the properties of the VNN-COMP benchmarks
+    const PROPERTY: &str = "
+        ; a comment
+        (declare-const X_0 Real)
+        (declare-const X_1 Real)
+        (declare-const Y_0 Real)
+        (declare-const Y_1 Real)
+        (declare-const Y_2 Real)
+
+        (assert (<= X_0 0.6798578))
+        (assert (>= X_0 0.6))
+        (assert (and (<= X_1 5e-1) (>= X_1 (- 0.5))))
+
+        (assert (or
+            (and (<= Y_0 Y_1) (<= Y_0 Y_2))
+            (and (>= (+ Y_1 (* 2 Y_2)) 3.5))
+        ))
+    ";
+
+    #[test]
+    pub fn parse_property() {
+        let spec = Spec::parse(PROPERTY).unwrap();
+        assert_eq!(
+            spec.inputs,
+            [
+                Interval::new(next_down(0.6), next_up(0.6798578)),
+                Interval::new(-0.5, 0.5)
+            ]
+        );
+        assert_eq!(spec.n_output, 3);
+        assert_eq!(
+            spec.violation,
+            [
+                [Constraint::at_most(3, 0, 1), Constraint::at_most(3, 0, 2)].to_vec(),
+                [Constraint {
+                    coefficients: [0.0, -1.0, -2.0].into(),
+                    bound: -3.5
+                }]
+                .to_vec(),
+            ]
+        );
+
+        assert!(spec.violated_by(&[0.0, 1.0, 1.0]));
+        assert!(spec.violated_by(&[5.0, 1.5, 1.0]));
+        assert!(!spec.violated_by(&[5.0, 1.0, 1.0]));
+
+        // written and parsed again, the property stays the same
+        assert_eq!(Spec::parse(&spec.to_string()).unwrap(), spec);
+    }
+
+    #[test]
+    pub fn divided_bounds_round_outward() {
+        let spec = Spec::parse(
+            "(declare-const X_0 Real) (declare-const Y_0 Real)
+            (assert (<= (* 3 X_0) 1))
+            (assert (>= (* 3 X_0) (- 1)))
+            (assert (<= Y_0 0))",
+        )
+        .unwrap();
+        let Interval { lower, upper } = spec.inputs[0];
+
+        // a third is not representable, so both bounds are rounded outward
+        assert!(f64::from(upper) * 3.0 > 1.0);
+        assert!(f64::from(lower) * 3.0 < -1.0);
+        assert_eq!(upper, 1.0 / 3.0);
+        assert_eq!(lower, -1.0 / 3.0);
+    }
+
+    #[test]
+    pub fn literals_round_outward() {
+        let spec = Spec::parse(
+            "(declare-const X_0 Real) (declare-const Y_0 Real)
+            (assert (<= X_0 0.7))
+            (assert (>= X_0 (+ 0.1 0.2)))
+            (assert (>= Y_0 0.7))",
+        )
+        .unwrap();
+        let Interval { lower, upper } = spec.inputs[0];
+
+        // the nearest f32 to 0.7 is below it, and the one to 0.3 above it, but the one to -0.7
+        // is above it, which is the outward direction for the bound of the outputs
+        assert_eq!(upper, next_up(0.7));
+        assert_eq!(lower, next_down(0.3));
+        assert_eq!(spec.violation[0][0].bound, -0.7);
+        assert!(f64::from(spec.violation[0][0].bound) > -0.7);
+
+        // bounds which are f32 already are kept
+        let spec = Spec::parse(
+            "(declare-const X_0 Real) (declare-const Y_0 Real)
+            (assert (<= X_0 0.5)) (assert (>= X_0 (- 0.25)))
+            (assert (<= Y_0 0))",
+        )
+        .unwrap();
+        assert_eq!(spec.inputs[0], Interval::new(-0.25, 0.5));
+    }
+
+    #[test]
+    pub fn reject_unsupported() {
+        let declarations = "(declare-const X_0 Real) (declare-const Y_0 Real)";
+        let parse = |assertions: &str| Spec::parse(&format!("{declarations} {assertions}"));
+
+        assert_eq!(
+            parse("(assert (<= X_0 1))"),
+            Err(VnnLibError::UnboundedInput(0))
+        );
+        assert_eq!(
+            parse("(assert (<= X_0 1)) (assert (>= X_0 2))"),
+            Err(VnnLibError::EmptyInput(0))
+        );
+        assert_eq!(
+            parse("(assert (<= X_0 Y_1))"),
+            Err(VnnLibError::UnknownVariable("Y_1".into()))
+        );
+        assert_eq!(
+            parse("(assert (<= X_0 Y_0))"),
+            Err(VnnLibError::Unsupported("(<= X_0 Y_0)".into()))
+        );
+        assert_eq!(
+            parse("(assert (or (<= X_0 1) (<= Y_0 1)))"),
+            Err(VnnLibError::Unsupported(
+                "(or (<= X_0 1) (<= Y_0 1))".into()
+            ))
+        );
+        assert_eq!(
+            parse("(assert (<= X_0 1)"),
+            Err(VnnLibError::UnbalancedParentheses)
+        );
+    }
+
+    #[test]
+    pub fn check_converted_property() {
+        let nnet = hcas::network(HAdvisory::ClearOfConflict, Time::new::<second>(20.0));
+        let head_on = [
+            Interval::new(4e3, 6e3),
+            Interval::new(-500.0, 500.0),
+            Interval::new(-PI, -3.0),
+        ];
+        let verifier = Verifier::default();
+
+        for (property, verdict) in [
+            (
+                Property::ScoreBelow(HAdvisory::StrongLeft, HAdvisory::ClearOfConflict),
+                Verdict::Violated,
+            ),
+            (
+                Property::NeverChosen(HAdvisory::ClearOfConflict),
+                Verdict::Proven,
+            ),
+        ] {
+            let spec = Spec::from_property(nnet, &head_on, property);
+            assert_eq!(Spec::parse(&spec.to_string()).unwrap(), spec);
+
+            let report = verifier.check_spec(nnet, &spec).unwrap();
+            assert_eq!(report.verdict, verdict, "{property}: {report}");
+            assert_eq!(verifier.check(nnet, &head_on, property).verdict, verdict);
+            for counterexample in &report.counterexamples {
+                assert!(spec.violated_by(&counterexample.scores));
+            }
+        }
+
+        let spec = Spec::parse(PROPERTY).unwrap();
+        assert_eq!(
+            verifier.check_spec(nnet, &spec),
+            Err(VnnLibError::DimensionMismatch {
+                inputs: 2,
+                outputs: 3
+            })
+        );
+    }
+}